@@ -57,6 +57,7 @@ function applyStaticTranslations() {
     contributeLink.href = t.contribute_url;
   }
   setText('lang-hide-r18', t.hide_r18);
+  setText('lang-featured', t.featured);
 
   const search = document.getElementById('search');
   if (search) {
@@ -102,12 +103,25 @@ function setHtml(id, html) {
   }
 }
 
+// Frontmatter title when set, else the file name without its extension.
+function displayTitle(item) {
+  const title = item.meta && item.meta.title ? item.meta.title.trim() : '';
+  return title || item.name.replace(/\.(md|markdown|txt)$/i, '').trim();
+}
+
 // Percent-encode each path segment individually so reserved chars like '#'
 // in titles aren't read as fragment separators by the browser.
 function encodePath(path) {
   return path.split('/').map(encodeURIComponent).join('/');
 }
 
+// - Drop the leading slash of a root-relative URL ("/works/…" → "works/…").
+// - The home page is the site root, so it resolves the same here — and also
+//   from a static export (--export) hosted under a sub-path.
+function siteRelative(url) {
+  return /^\/(?!\/)/.test(url) ? url.slice(1) : url;
+}
+
 // - Build an href that preserves lang + r18 state so navigation doesn't reset the user's filter
 // - Reads live checkbox state each call so mid-session toggles reflect on re-render
 function buildHref(linkPath) {
@@ -119,7 +133,7 @@ function buildHref(linkPath) {
   if (hideR18 && !hideR18.checked) {
     parts.push('r18=0');
   }
-  return encodePath(linkPath) + (parts.length ? '?' + parts.join('&') : '');
+  return siteRelative(encodePath(linkPath)) + (parts.length ? '?' + parts.join('&') : '');
 }
 
 // - Debounce typing so we don't churn the URL bar on every keystroke
@@ -213,7 +227,7 @@ function renderTree(data, query, hideR18) {
         return true;
       }
 
-      const name = item.name.replace(/\.md$/i, '');
+      const name = displayTitle(item);
       const creator = (item.meta && item.meta.creator) ? item.meta.creator : '';
       return workMatchesSearch(query, name, creator, tags);
     });
@@ -260,14 +274,11 @@ function renderTree(data, query, hideR18) {
     filesDiv.className = 'files';
 
     items.forEach(item => {
-      const displayName = item.name.replace(/\.md$/i, '').trim();
-      let linkPath = item.path;
-      if (linkPath.endsWith('.md')) {
-        linkPath = linkPath.slice(0, -3);
-      }
+      const displayName = displayTitle(item);
+      const linkPath = item.path.replace(/\.(md|markdown|txt)$/i, '');
 
       const creator = (item.meta && item.meta.creator) ? item.meta.creator : '';
-      const tagline = (item.meta && item.meta.tagline) ? item.meta.tagline : '';
+      const tagline = (item.meta && item.meta.tagline) ? item.meta.tagline : (item.excerpt || '');
       const tags = (item.meta && item.meta.tags) ? item.meta.tags : [];
 
       var tagInfo = (typeof TAG_INFO !== 'undefined') ? TAG_INFO : {};
@@ -291,11 +302,11 @@ function renderTree(data, query, hideR18) {
       if (item.thumbnail && item.thumbnail_composite) {
         // CSS-encode first, entity-escape second — &#39; would decode back to a quote inside url('…')
         thumbHtml = '<div class="card-thumb">' + badges +
-          '<div class="card-thumb-composite" style="background-image:url(\'' + escapeHtml(escapeCssUrl(item.thumbnail)) + '\')"></div></div>';
+          '<div class="card-thumb-composite" style="background-image:url(\'' + escapeHtml(escapeCssUrl(siteRelative(item.thumbnail))) + '\')"></div></div>';
       } else if (item.thumbnail) {
         // alt="" is intentional: .card-title below is the accessible label, and empty alt avoids flashing game titles in the image box during slow loads
         thumbHtml = '<div class="card-thumb">' + badges +
-          '<img src="' + escapeHtml(item.thumbnail) + '" alt="" loading="lazy" onerror="retryImage.call(this)" /></div>';
+          '<img src="' + escapeHtml(siteRelative(item.thumbnail)) + '" alt="" loading="lazy" onerror="retryImage.call(this)" /></div>';
       } else {
         thumbHtml = '<div class="card-thumb-placeholder">' + badges + '✨</div>';
       }
@@ -306,7 +317,7 @@ function renderTree(data, query, hideR18) {
       if (creator) {
         const links = creator.split(',').map(function(c) {
           const nm = c.trim();
-          return '<a class="creator-link" href="/creator/' + encodeURIComponent(nm) + '">' + escapeHtml(nm) + '</a>';
+          return '<a class="creator-link" href="creator/' + encodeURIComponent(nm) + '">' + escapeHtml(nm) + '</a>';
         }).join(', ');
         creatorHtml = '<div class="card-creator">by ' + links + '</div>';
       }
@@ -318,6 +329,7 @@ function renderTree(data, query, hideR18) {
           '<div class="card-title">' + escapeHtml(displayName) + '</div>' +
           creatorHtml +
           (tagline ? '<div class="card-tagline">' + escapeHtml(tagline) + '</div>' : '') +
+          (item.comment_count ? '<div class="card-comments">' + escapeHtml((t.comments || '{n} comments').replace('{n}', item.comment_count)) + '</div>' : '') +
         '</div>';
 
       filesDiv.appendChild(card);
@@ -359,14 +371,11 @@ function buildRibbon(data) {
     if (year.children) {
       year.children.forEach(item => {
         if (item.thumbnail) {
-          let path = item.path;
-          if (path.endsWith('.md')) {
-            path = path.slice(0, -3);
-          }
-          const title = item.name.replace(/\.md$/i, '').trim();
+          const path = item.path.replace(/\.(md|markdown|txt)$/i, '');
+          const title = displayTitle(item);
           // - thumbnail_ribbon is the smaller (240x140) proxy URL for GitHub user-attachments
           // - Falls back to thumbnail for non-proxied URLs
-          const url = item.thumbnail_ribbon || item.thumbnail;
+          const url = siteRelative(item.thumbnail_ribbon || item.thumbnail);
           items.push({ url: url, path: path, title: title, composite: !!item.thumbnail_composite });
         }
       });
@@ -16,6 +16,30 @@
     });
   }
 
+  // - Typeset TeX the server wrapped in .math spans/divs. KaTeX is only
+  //   loaded (deferred, ahead of this script) on pages that have math, and
+  //   only the \( \) / \[ \] delimiters the server emits are recognised.
+  if (window.renderMathInElement) {
+    document.querySelectorAll('.math').forEach(function (el) {
+      window.renderMathInElement(el, {
+        delimiters: [
+          { left: '\\(', right: '\\)', display: false },
+          { left: '\\[', right: '\\]', display: true }
+        ],
+        throwOnError: false
+      });
+    });
+  }
+
+  // - Draw the ```mermaid blocks the server emitted as <pre class="mermaid">.
+  //   Mermaid is only loaded (deferred, ahead of this script) on pages that
+  //   have one; strict keeps diagram labels from carrying script.
+  if (window.mermaid) {
+    var theme = document.body.classList.contains('print') ? 'default' : 'dark';
+    window.mermaid.initialize({ startOnLoad: false, theme: theme, securityLevel: 'strict' });
+    window.mermaid.run({ querySelector: 'pre.mermaid' });
+  }
+
   var share = document.querySelector('.share-btn');
   if (share) {
     share.addEventListener('click', function () {
@@ -1,9 +1,14 @@
 pub mod app;
 
-use pulldown_cmark::{html, Event, Parser};
+use pulldown_cmark::{
+    html, CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd, TextMergeStream,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::sync::OnceLock;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::SystemTime;
 
 pub const RELEASED_UNKNOWN: &str = "unknown";
 
@@ -22,6 +27,11 @@ pub struct LangStrings {
     pub creator_more_works: String,
     pub creator_view: String,
     pub creator_all_works: String,
+    pub comment: String,
+    pub comments: String,
+    pub reading_time: String,
+    pub previous_work: String,
+    pub next_work: String,
 }
 
 struct LangPair {
@@ -58,6 +68,11 @@ fn load_lang() -> &'static LangPair {
                 creator_more_works: get("creator_more_works"),
                 creator_view: get("creator_view"),
                 creator_all_works: get("creator_all_works"),
+                comment: get("comment"),
+                comments: get("comments"),
+                reading_time: get("reading_time"),
+                previous_work: get("previous_work"),
+                next_work: get("next_work"),
             }
         }
 
@@ -106,6 +121,9 @@ pub fn creator_work_key<'a>(released: Option<&'a str>, folder_year: &'a str) ->
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct GameMeta {
+    /// Display title for the page header and cards; the file stem stays the URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default)]
     pub creator: Option<String>,
     #[serde(default)]
@@ -118,12 +136,34 @@ pub struct GameMeta {
     pub link_url: Option<String>,
     #[serde(default)]
     pub tagline: Option<String>,
+    /// Card summary for the tree's `excerpt`, in place of the body's first paragraph.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     #[serde(default)]
     pub extra_links: Option<Vec<ExtraLink>>,
     #[serde(default)]
     pub tags: Option<Vec<String>>,
     #[serde(default)]
     pub thumbnail_index: Option<usize>,
+    /// Work-in-progress entry: left out of every listing, and its page 404s
+    /// unless previewed (see `draft_preview_ok`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft: Option<bool>,
+    /// Colour overrides for the work page (see `theme_css`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<WorkTheme>,
+}
+
+/// - A work page's colours, overriding the site's `--bg`, `--text` and
+///   `--accent`; each is optional, and only safe colours are applied.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct WorkTheme {
+    #[serde(default)]
+    pub bg: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub accent: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
@@ -134,28 +174,49 @@ pub struct ExtraLink {
 
 /// - Split YAML frontmatter from markdown body.
 /// - Returns (parsed meta, body without frontmatter).
+/// - A leading byte-order mark (Windows editors add one) is skipped, so the
+///   block is still found instead of rendering as an `<hr>` and YAML text.
 pub fn parse_frontmatter(content: &str) -> (GameMeta, &str) {
-    let trimmed = content.trim_start();
+    match frontmatter_block(content) {
+        Some(Some((yaml_str, body))) => match serde_yaml::from_str::<GameMeta>(yaml_str) {
+            Ok(meta) => (meta, body),
+            Err(_) => (GameMeta::default(), content),
+        },
+        _ => (GameMeta::default(), content),
+    }
+}
+
+/// - Why `parse_frontmatter` would fall back to defaults for `content` that
+///   opens a frontmatter block: the block is never closed, or its YAML doesn't
+///   parse as GameMeta (bad syntax, a field of the wrong type).
+/// - None when there's no block or it parses.
+pub fn frontmatter_error(content: &str) -> Option<String> {
+    match frontmatter_block(content)? {
+        None => Some("frontmatter is never closed with `---`".to_string()),
+        Some((yaml_str, _)) => serde_yaml::from_str::<GameMeta>(yaml_str)
+            .err()
+            .map(|e| format!("invalid frontmatter: {}", e)),
+    }
+}
+
+// - None: no frontmatter. Some(None): opened but never closed.
+//   Some(Some((yaml, body))) otherwise.
+fn frontmatter_block(content: &str) -> Option<Option<(&str, &str)>> {
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
 
     if !trimmed.starts_with("---") {
-        return (GameMeta::default(), content);
+        return None;
     }
 
     let after_open = &trimmed[3..];
     let after_open = after_open.trim_start_matches(['\r', '\n']);
 
-    if let Some(close_idx) = after_open.find("\n---") {
+    Some(after_open.find("\n---").map(|close_idx| {
         let yaml_str = &after_open[..close_idx];
         let body_start = close_idx + 4;
         let body = after_open[body_start..].trim_start_matches(['\r', '\n']);
-
-        match serde_yaml::from_str::<GameMeta>(yaml_str) {
-            Ok(meta) => (meta, body),
-            Err(_) => (GameMeta::default(), content),
-        }
-    } else {
-        (GameMeta::default(), content)
-    }
+        (yaml_str, body)
+    }))
 }
 
 #[derive(Clone, Debug)]
@@ -163,6 +224,7 @@ pub struct ImageInfo {
     pub url: String,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    pub alt: Option<String>, // see image_alt; None when missing or GitHub's "image"
 }
 
 impl ImageInfo {
@@ -215,18 +277,173 @@ pub fn resize_thumbnail(img: &image::DynamicImage, size: ThumbSize) -> image::Dy
     }
 }
 
+/// - Width the tree's cards and ribbon get local thumbnails at: the card's
+///   ThumbSize width, which also covers the narrower ribbon, so one cached
+///   size serves both.
+pub const LOCAL_THUMB_WIDTH: u32 = 600;
+
+/// - Widest `/thumb/{path}?w=` a client may ask for; anything wider is a 400,
+///   so the resize proxy can't be driven to arbitrary sizes.
+pub const MAX_LOCAL_THUMB_WIDTH: u32 = 1024;
+
+/// - The widths the resize proxy produces; other requests are snapped to one
+///   (see `local_thumb_width`), so an image has at most this many cached sizes.
+pub const LOCAL_THUMB_WIDTHS: [u32; 4] = [160, 320, LOCAL_THUMB_WIDTH, MAX_LOCAL_THUMB_WIDTH];
+
+/// - Most resized images kept in `Config::thumb_cache_dir` (see
+///   `sweep_thumb_cache`).
+pub const MAX_LOCAL_THUMB_CACHE_ENTRIES: usize = 4096;
+
+/// - The LOCAL_THUMB_WIDTHS entry a `?w=` request is served at: the
+///   narrowest at least `w` wide. None for 0 or past MAX_LOCAL_THUMB_WIDTH.
+pub fn local_thumb_width(w: u32) -> Option<u32> {
+    if w == 0 {
+        return None;
+    }
+    LOCAL_THUMB_WIDTHS.into_iter().find(|&width| width >= w)
+}
+
+/// - Trim `dir` to its `keep` most recently written files, deleting the
+///   rest; returns how many went. Unreadable entries are left alone.
+pub fn sweep_thumb_cache(dir: &Path, keep: usize) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let meta = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((meta.modified().ok()?, entry.path()))
+        })
+        .collect();
+    if files.len() <= keep {
+        return 0;
+    }
+    files.sort_unstable_by_key(|(mtime, _)| std::cmp::Reverse(*mtime));
+    files[keep..]
+        .iter()
+        .filter(|(_, path)| std::fs::remove_file(path).is_ok())
+        .count()
+}
+
+/// - The resize-proxy URL for a local image: `/raw/2023/cover.png` →
+///   `/thumb/2023/cover.png?w=600`. PNG and JPEG only, the formats the
+///   `image` build decodes.
+/// - `None` for anything else: remote URLs, other formats, URLs already
+///   carrying a query or fragment.
+pub fn local_thumb_url(url: &str, width: u32) -> Option<String> {
+    let path = url.strip_prefix("/raw/")?;
+    if path.contains(['?', '#']) {
+        return None;
+    }
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    matches!(ext.as_str(), "png" | "jpg" | "jpeg").then(|| format!("/thumb/{}?w={}", path, width))
+}
+
+/// - `img` scaled to `width` pixels wide, aspect ratio kept; never upscaled,
+///   so a narrower source comes back as-is.
+pub fn resize_to_width(img: &image::DynamicImage, width: u32) -> image::DynamicImage {
+    if img.width() <= width {
+        return img.clone();
+    }
+    img.resize(width, u32::MAX, image::imageops::FilterType::Triangle)
+}
+
+/// - File name a resized local image is cached under: a hash of the source
+///   path, width and mtime, plus the source's extension. An edited image or
+///   another width never finds a stale entry.
+pub fn local_thumb_cache_name(source: &Path, width: u32, mtime: SystemTime) -> String {
+    let nanos = mtime
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let key = format!("{}\0{}\0{}", source.display(), width, nanos);
+    let ext = source
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    format!("{}-{}.{}", content_hash(key.as_bytes()), width, ext)
+}
+
 fn extract_attr_u32(tag: &str, attr: &str) -> Option<u32> {
+    extract_attr(tag, attr)?.parse().ok()
+}
+
+// - `attr="…"` only as a whole attribute name, so `alt` never matches
+//   inside `data-alt`.
+fn extract_attr<'a>(tag: &'a str, attr: &str) -> Option<&'a str> {
     let needle = format!("{}=\"", attr);
-    let start = tag.find(&needle)? + needle.len();
+    let at = tag.match_indices(&needle).find_map(|(i, _)| {
+        tag[..i]
+            .ends_with(|c: char| c.is_ascii_whitespace())
+            .then_some(i)
+    })?;
+    let start = at + needle.len();
     let end = start + tag[start..].find('"')?;
-    tag[start..end].parse().ok()
+    Some(&tag[start..end])
+}
+
+/// - An image's alt text as worth showing: trimmed, and `None` when empty or
+///   GitHub's drag-and-drop placeholder "image", which describes nothing.
+fn image_alt(alt: &str) -> Option<String> {
+    let alt = alt.trim();
+    (!alt.is_empty() && !alt.eq_ignore_ascii_case("image")).then(|| alt.to_string())
 }
 
+/// - The image to use as a work's thumbnail: `images[index]` (frontmatter
+///   `thumbnail_index`), else the first in document order, counting only
+///   images whose URL starts with one of `sources` (see
+///   `Config::thumbnail_sources`) — a tracking pixel or arbitrary remote
+///   image never becomes a card.
+/// - None when no image qualifies.
+pub fn pick_thumbnail<'a>(
+    images: &'a [ImageInfo],
+    index: Option<usize>,
+    sources: &[String],
+) -> Option<&'a ImageInfo> {
+    let allowed = |img: &&ImageInfo| sources.iter().any(|s| img.url.starts_with(s.as_str()));
+    index
+        .and_then(|i| images.get(i))
+        .filter(allowed)
+        .or_else(|| images.iter().find(allowed))
+}
+
+/// - Every image in a markdown body, in document order: GitHub user-attachment
+///   `<img>` tags in raw HTML (with their width/height, for composite
+///   detection) and markdown `![alt](url)` images (no dimensions).
+/// - images[0] is the hero/thumbnail, so whichever syntax comes first wins.
 pub fn extract_all_images(md: &str) -> Vec<ImageInfo> {
-    let parser = Parser::new(md);
+    let parser = Parser::new_ext(md, gfm_options());
     let mut images = Vec::new();
+    // - A markdown image's alt is the text between its Start and End.
+    let mut alt: Option<String> = None;
 
     for event in parser {
+        if let Event::Start(Tag::Image { dest_url, .. }) = &event {
+            // - `![](trailer.mp4)` is rendered as a player, not an image.
+            if !dest_url.is_empty() && media_element(dest_url).is_none() {
+                images.push(ImageInfo {
+                    url: dest_url.to_string(),
+                    width: None,
+                    height: None,
+                    alt: None,
+                });
+                alt = Some(String::new());
+            }
+            continue;
+        }
+        if let Some(text) = alt.as_mut() {
+            match &event {
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                Event::End(TagEnd::Image) => {
+                    if let Some(image) = images.last_mut() {
+                        image.alt = image_alt(text);
+                    }
+                    alt = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
         if let Event::Html(html) = event {
             let html_str = html.to_string();
             let mut search_from = 0;
@@ -247,6 +464,7 @@ pub fn extract_all_images(md: &str) -> Vec<ImageInfo> {
                         url,
                         width: extract_attr_u32(tag, "width"),
                         height: extract_attr_u32(tag, "height"),
+                        alt: extract_attr(tag, "alt").and_then(image_alt),
                     });
                     search_from = abs_start + end_quote;
                 } else {
@@ -273,83 +491,3114 @@ pub fn first_offsite_image(body: &str) -> Option<String> {
     let b = lower.as_bytes();
     let n = b.len();
 
-    let flag = |url: &str| -> Option<String> {
-        let url = url.trim();
-        (!url.is_empty() && !url.starts_with(OK)).then(|| url.to_string())
-    };
+    let flag = |url: &str| -> Option<String> {
+        let url = url.trim();
+        (!url.is_empty() && !url.starts_with(OK)).then(|| url.to_string())
+    };
+
+    // Read the value of an attribute whose '=' is at `eq`; handles quoted and
+    // bare forms. Returns the raw value.
+    let read_value = |eq: usize| -> Option<&str> {
+        let mut j = eq + 1;
+        while j < n && b[j].is_ascii_whitespace() {
+            j += 1;
+        }
+        if j >= n {
+            return None;
+        }
+        if b[j] == b'"' || b[j] == b'\'' {
+            let q = b[j];
+            let start = j + 1;
+            let end = start + lower[start..].find(q as char)?;
+            Some(&lower[start..end])
+        } else {
+            let start = j;
+            let end = start
+                + lower[start..]
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(lower.len() - start);
+            Some(&lower[start..end])
+        }
+    };
+
+    for (i, _) in lower.char_indices() {
+        // markdown image: ![alt](url "optional title")
+        if b[i] == b'!' && lower[i..].starts_with("![") {
+            if let Some(rel) = lower[i..].find("](") {
+                let us = i + rel + 2;
+                if let Some(e) = lower[us..].find(')') {
+                    let url = lower[us..us + e].split_whitespace().next().unwrap_or("");
+                    if let Some(bad) = flag(url) {
+                        return Some(bad);
+                    }
+                }
+            }
+        }
+
+        // src= / srcset= attribute, at an attribute-name boundary (so
+        // "described" and "data-src" don't match).
+        let boundary = i == 0 || matches!(b[i - 1], b' ' | b'\t' | b'\n' | b'\r' | b'<' | b'/');
+        if boundary && lower[i..].starts_with("src") {
+            let mut j = i + 3;
+            if lower[j..].starts_with("set") {
+                j += 3;
+            }
+            while j < n && b[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j < n && b[j] == b'=' {
+                if let Some(val) = read_value(j) {
+                    // srcset is a comma-separated "url descriptor" list.
+                    for cand in val.split(',') {
+                        let url = cand.split_whitespace().next().unwrap_or("");
+                        if let Some(bad) = flag(url) {
+                            return Some(bad);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Deepest include chain `expand_includes` follows before giving up.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Why an `{{ include: … }}` directive couldn't be expanded. Each variant
+/// carries the directive's path as written.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IncludeError {
+    /// Absolute, or resolves outside the content root.
+    Escape(String),
+    /// The file includes itself, directly or through a chain.
+    Cycle(String),
+    /// Nested deeper than `MAX_INCLUDE_DEPTH`.
+    TooDeep(String),
+    /// Missing or unreadable.
+    Missing(String),
+}
+
+impl std::fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncludeError::Escape(rel) => write!(f, "{} is outside the works dir", rel),
+            IncludeError::Cycle(rel) => write!(f, "{} includes itself", rel),
+            IncludeError::TooDeep(rel) => {
+                write!(
+                    f,
+                    "{} is nested over {} includes deep",
+                    rel, MAX_INCLUDE_DEPTH
+                )
+            }
+            IncludeError::Missing(rel) => write!(f, "{} is missing", rel),
+        }
+    }
+}
+
+/// - Inline every `{{ include: path.md }}` directive with the referenced file's
+///   markdown, recursively, before rendering. Directives inside code blocks
+///   are left as written, so a work can show the syntax.
+/// - Paths resolve relative to the including file and must stay under `root`
+///   (compared after canonicalizing, so `..` and symlinks can't escape).
+/// - Shared snippets belong in an `_`-prefixed folder (e.g. `works/_shared/`),
+///   which the games index skips, so they never show up as works themselves.
+pub fn expand_includes(
+    body: &str,
+    current_file: &Path,
+    root: &Path,
+) -> Result<String, IncludeError> {
+    let root = root
+        .canonicalize()
+        .map_err(|_| IncludeError::Missing(root.display().to_string()))?;
+    let mut stack = vec![canonical_or_self(current_file)];
+    expand_includes_inner(body, &root, &mut stack, None)
+}
+
+/// - `expand_includes` for rendering: a directive that can't be expanded
+///   (see `IncludeError`) is replaced by an inline note saying why, and the
+///   rest of the body still expands. The errors come back for logging.
+/// - An error anywhere down an include chain notes the body's own directive
+///   that started it.
+pub fn expand_includes_with_notes(
+    body: &str,
+    current_file: &Path,
+    root: &Path,
+) -> (String, Vec<IncludeError>) {
+    let mut errors = Vec::new();
+    let Ok(root) = root.canonicalize() else {
+        return (body.to_string(), errors);
+    };
+    let mut stack = vec![canonical_or_self(current_file)];
+    let out = expand_includes_inner(body, &root, &mut stack, Some(&mut errors))
+        .unwrap_or_else(|_| body.to_string());
+    (out, errors)
+}
+
+// - Markdown standing in for a directive that failed.
+fn include_note(error: &IncludeError) -> String {
+    format!("*(include failed: {})*", error)
+}
+
+fn canonical_or_self(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+// - `notes`, when given, collects failing directives (noted inline) instead
+//   of failing the whole expansion; includes below the top level are strict.
+fn expand_includes_inner(
+    body: &str,
+    root: &Path,
+    stack: &mut Vec<PathBuf>,
+    mut notes: Option<&mut Vec<IncludeError>>,
+) -> Result<String, IncludeError> {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    // - Most bodies have no directives; skip the extra parse for them.
+    let code = match body.contains("{{") {
+        true => code_block_ranges(body),
+        false => Vec::new(),
+    };
+
+    while let Some(open) = rest.find("{{") {
+        let at = body.len() - rest.len() + open;
+        if let Some(block) = code.iter().find(|r| r.contains(&at)) {
+            // In a code block: an example of the syntax, kept as written.
+            let end = block.end - (body.len() - rest.len());
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let Some(close) = rest[open..].find("}}").map(|c| open + c) else {
+            break;
+        };
+        let inner = rest[open + 2..close].trim();
+        let Some(rel) = inner.strip_prefix("include:").map(str::trim) else {
+            // Not ours: keep the braces verbatim and scan past them.
+            out.push_str(&rest[..open + 2]);
+            rest = &rest[open + 2..];
+            continue;
+        };
+        out.push_str(&rest[..open]);
+        rest = &rest[close + 2..];
+
+        match (include_file(rel, root, stack), notes.as_deref_mut()) {
+            (Ok(expanded), _) => out.push_str(expanded.trim_end_matches(['\r', '\n'])),
+            (Err(e), Some(notes)) => {
+                out.push_str(&include_note(&e));
+                notes.push(e);
+            }
+            (Err(e), None) => return Err(e),
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+// - Byte ranges of `body`'s code blocks, whose directives are shown, not
+//   expanded. Spoiler fences hold markdown (see `spoiler_summary`), so theirs
+//   still expand.
+fn code_block_ranges(body: &str) -> Vec<std::ops::Range<usize>> {
+    Parser::new_ext(body, gfm_options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+                if spoiler_summary(&info).is_some() =>
+            {
+                None
+            }
+            Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+// - One directive's target, itself expanded; `stack` is left as it was found.
+fn include_file(rel: &str, root: &Path, stack: &mut Vec<PathBuf>) -> Result<String, IncludeError> {
+    if Path::new(rel).is_absolute() {
+        return Err(IncludeError::Escape(rel.to_string()));
+    }
+    let dir = stack
+        .last()
+        .and_then(|p| p.parent())
+        .unwrap_or(root)
+        .to_path_buf();
+    let target = dir
+        .join(rel)
+        .canonicalize()
+        .map_err(|_| IncludeError::Missing(rel.to_string()))?;
+    if !target.starts_with(root) {
+        return Err(IncludeError::Escape(rel.to_string()));
+    }
+    if stack.contains(&target) {
+        return Err(IncludeError::Cycle(rel.to_string()));
+    }
+    if stack.len() > MAX_INCLUDE_DEPTH {
+        return Err(IncludeError::TooDeep(rel.to_string()));
+    }
+    let included =
+        std::fs::read_to_string(&target).map_err(|_| IncludeError::Missing(rel.to_string()))?;
+
+    stack.push(target);
+    let expanded = expand_includes_inner(&included, root, stack, None);
+    stack.pop();
+    expanded
+}
+
+/// - Tweaks to how work bodies are rendered.
+/// - `auto_alt`: an image with empty alt text gets one derived from its
+///   filename (see `alt_from_filename`) instead of staying blank.
+/// - `asset_root` + `work_dir`: when set, a local image (see
+///   `local_asset_path`) with `.avif`/`.webp` siblings on disk is wrapped in a
+///   `<picture>` offering them ahead of the original.
+/// - `image_root`: when set, `/raw/…` images get `width`/`height` read from
+///   the file under it (see `add_image_hints`).
+/// - `mount`: the collection the work is mounted under, if any; `work_dir`
+///   and `/raw/…` URLs start with it, the two roots above don't.
+/// - `media_hosts`: remote hosts (e.g. "cdn.example.com") whose video/audio
+///   links are embedded like local ones (see `media_element`); any other
+///   remote media stays a plain link.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    pub auto_alt: bool,
+    pub asset_root: Option<PathBuf>,
+    pub work_dir: String,
+    pub image_root: Option<PathBuf>,
+    pub mount: Option<String>,
+    pub media_hosts: Vec<String>,
+}
+
+impl RenderOptions {
+    // - `file` (as `local_asset_path` returns it) under `root`, less the mount.
+    fn asset_file(&self, root: &Path, file: &str) -> Option<PathBuf> {
+        let file = match &self.mount {
+            Some(mount) => file.strip_prefix(mount.as_str())?.strip_prefix('/')?,
+            None => file,
+        };
+        Some(root.join(file))
+    }
+
+    // - Whether a media link (already resolved, see `resolve_image_url`) may
+    //   be embedded: local `/raw/…` files, or an http(s) URL on `media_hosts`.
+    fn embeds_media(&self, url: &str) -> bool {
+        if url.starts_with("/raw/") {
+            return true;
+        }
+        let Some(rest) = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+        else {
+            return false;
+        };
+        let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+        self.media_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+}
+
+/// - File extensions rendered as `<video controls>` / `<audio controls>`
+///   when a work links to (or embeds as an image) such a file.
+pub const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "webm", "ogv", "m4v"];
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "ogg", "oga", "wav", "m4a", "flac", "opus"];
+
+/// - The player element for a media URL: "video" or "audio" by its file
+///   extension (case-insensitive, query and fragment ignored); None otherwise.
+pub fn media_element(url: &str) -> Option<&'static str> {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let (_, ext) = path.rsplit('/').next().unwrap_or("").rsplit_once('.')?;
+    let ext = ext.to_ascii_lowercase();
+    if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        Some("video")
+    } else if AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        Some("audio")
+    } else {
+        None
+    }
+}
+
+/// - Modern formats offered ahead of an image's original, best first.
+pub const PICTURE_FORMATS: [(&str, &str); 2] = [("avif", "image/avif"), ("webp", "image/webp")];
+
+/// - A value derived from an optional file that an external tool maintains,
+///   re-derived when the file's mtime changes: one stat per `current()`, the
+///   read + derive only on change, so edits land without a restart (and without
+///   a watcher — see docs/design_decisions.md).
+/// - `derive` gets `None` for a missing/unreadable file.
+/// - `new` derives up front; `lazy` waits for the first `current()` (or a
+///   background warm-up calling it).
+/// - Derives run outside the `cached` lock and are swapped in when done, so
+///   a read of a fresh value never waits on a derive. Stale reads take turns
+///   on `deriving`: concurrent ones wait for one derive, not run their own.
+pub struct Reloading<T> {
+    file: PathBuf,
+    derive: Derive<T>,
+    cached: Mutex<Option<(Option<SystemTime>, Arc<T>)>>,
+    deriving: Mutex<()>,
+}
+
+type Derive<T> = Box<dyn Fn(Option<&str>) -> T + Send + Sync>;
+
+impl<T> Reloading<T> {
+    pub fn new(file: PathBuf, derive: impl Fn(Option<&str>) -> T + Send + Sync + 'static) -> Self {
+        let reloading = Self::lazy(file, derive);
+        reloading.current();
+        reloading
+    }
+
+    pub fn lazy(file: PathBuf, derive: impl Fn(Option<&str>) -> T + Send + Sync + 'static) -> Self {
+        Reloading {
+            file,
+            derive: Box::new(derive),
+            cached: Mutex::new(None),
+            deriving: Mutex::new(()),
+        }
+    }
+
+    fn read(file: &Path, derive: &dyn Fn(Option<&str>) -> T) -> (Option<SystemTime>, Arc<T>) {
+        let mtime = std::fs::metadata(file).and_then(|m| m.modified()).ok();
+        let raw = mtime.and_then(|_| std::fs::read_to_string(file).ok());
+        (mtime, Arc::new(derive(raw.as_deref())))
+    }
+
+    pub fn current(&self) -> Arc<T> {
+        let mtime = std::fs::metadata(&self.file)
+            .and_then(|m| m.modified())
+            .ok();
+        if let Some(value) = self.fresh(mtime) {
+            return value;
+        }
+        let _deriving = self.deriving.lock().unwrap_or_else(|e| e.into_inner());
+        // - Whoever held `deriving` before us may have just derived it.
+        if let Some(value) = self.fresh(mtime) {
+            return value;
+        }
+        let (at, value) = Self::read(&self.file, &self.derive);
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = Some((at, value.clone()));
+        value
+    }
+
+    fn fresh(&self, mtime: Option<SystemTime>) -> Option<Arc<T>> {
+        let cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        cached
+            .as_ref()
+            .filter(|(at, _)| *at == mtime)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// - Drop the derived value, so the next `current()` derives afresh even if
+    ///   the file hasn't changed — for when what `derive` reads besides the
+    ///   file has.
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// - Whether a value has been derived yet (always, after `new`). Doesn't
+    ///   wait on a derive in progress.
+    pub fn is_loaded(&self) -> bool {
+        self.cached
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+}
+
+/// - What a rendered work page depends on: the work, and a `variant` string
+///   covering everything else that shapes the page (language, r18 filter,
+///   comment count…).
+/// - Nothing taken from a header the client makes up freely (the Host) may go
+///   in `variant`, or one client could flush the cache by varying it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderKey {
+    pub path: String, // canonical "/works/YYYY/title"
+    pub variant: String,
+}
+
+/// - Rendered work pages, least recently used evicted past `capacity`.
+/// - Emptied with `clear` whenever the works are re-indexed (see
+///   `watch_works`), so pages are never served stale.
+/// - Capacity 0 disables the cache; every lookup renders.
+/// - Lookups are counted (see `hits_misses`); a disabled cache only misses.
+pub struct RenderCache {
+    pages: Option<Mutex<lru::LruCache<RenderKey, Arc<str>>>>,
+    hits: std::sync::atomic::AtomicU64,
+    misses: std::sync::atomic::AtomicU64,
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize) -> Self {
+        RenderCache {
+            pages: std::num::NonZeroUsize::new(capacity)
+                .map(|cap| Mutex::new(lru::LruCache::new(cap))),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    /// - Lookups served from the cache, and those that rendered, so far.
+    pub fn hits_misses(&self) -> (u64, u64) {
+        use std::sync::atomic::Ordering::Relaxed;
+        (self.hits.load(Relaxed), self.misses.load(Relaxed))
+    }
+
+    /// - The cached page for `key`, else `render()`'s, cached on success.
+    /// - The lock isn't held while rendering, so two first hits on one key may
+    ///   both render; the later insert wins, with identical bytes.
+    pub fn get_or_render<E>(
+        &self,
+        key: RenderKey,
+        render: impl FnOnce() -> Result<String, E>,
+    ) -> Result<Arc<str>, E> {
+        use std::sync::atomic::Ordering::Relaxed;
+        let Some(pages) = &self.pages else {
+            self.misses.fetch_add(1, Relaxed);
+            return render().map(Arc::from);
+        };
+        let lock = || pages.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(page) = lock().get(&key) {
+            self.hits.fetch_add(1, Relaxed);
+            return Ok(page.clone());
+        }
+        self.misses.fetch_add(1, Relaxed);
+        let page: Arc<str> = Arc::from(render()?);
+        lock().put(key, page.clone());
+        Ok(page)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages
+            .as_ref()
+            .map_or(0, |p| p.lock().unwrap_or_else(|e| e.into_inner()).len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// - Drop every cached variant of the work at `path`; returns how many.
+    pub fn evict(&self, path: &str) -> usize {
+        let Some(pages) = &self.pages else {
+            return 0;
+        };
+        let mut pages = pages.lock().unwrap_or_else(|e| e.into_inner());
+        let stale: Vec<RenderKey> = pages
+            .iter()
+            .filter(|(key, _)| key.path == path)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &stale {
+            pages.pop(key);
+        }
+        stale.len()
+    }
+
+    /// - Drop every cached page, e.g. after the works were re-indexed.
+    pub fn clear(&self) {
+        if let Some(pages) = &self.pages {
+            pages.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+}
+
+/// - Quiet period after the last change under works/ before acting on a
+///   burst of them, so a large sync re-indexes once rather than per file.
+pub const WORKS_WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// - Watch `works_dir` and, once changes have been quiet for `debounce`,
+///   call `on_change` with the canonical paths of the works that changed
+///   (empty when only other files did — an image, an included partial).
+///   `mount` is the collection `works_dir` is served as, if any (see
+///   `Config::roots`).
+/// - `on_change` runs on the watcher's own thread, so it may block.
+/// - Watching stops when the returned watcher is dropped.
+pub fn watch_works(
+    works_dir: &Path,
+    mount: Option<&str>,
+    mut on_change: impl FnMut(&HashSet<String>) + Send + 'static,
+    debounce: std::time::Duration,
+) -> notify::Result<notify::RecommendedWatcher> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    let root = std::fs::canonicalize(works_dir).unwrap_or_else(|_| works_dir.to_path_buf());
+    let mount = mount.map(str::to_string);
+    let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    std::thread::spawn(move || {
+        let mut changed = HashSet::new();
+        // - Whether `event` is a change (reads only raise Access events, and
+        //   serving the works dir does plenty of those); notes its works.
+        let note = |event: notify::Result<notify::Event>, changed: &mut HashSet<String>| {
+            let Ok(event) = event else { return false };
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                return false;
+            }
+            for path in event.paths {
+                let rel = path.strip_prefix(&root).unwrap_or(&path);
+                let rel = rel.to_string_lossy().replace('\\', "/");
+                if let Some((year, title)) = split_work_path(&rel) {
+                    let year = year_dir(mount.as_deref(), &year);
+                    changed.insert(format!("/works/{}/{}", year, title));
+                }
+            }
+            true
+        };
+        // - Ends once the watcher, and with it the sender, is dropped.
+        while let Ok(first) = rx.recv() {
+            if !note(first, &mut changed) {
+                continue;
+            }
+            // - Only changes push the quiet period back.
+            let mut quiet_at = std::time::Instant::now() + debounce;
+            loop {
+                let wait = quiet_at.saturating_duration_since(std::time::Instant::now());
+                match rx.recv_timeout(wait) {
+                    Ok(event) => {
+                        if note(event, &mut changed) {
+                            quiet_at = std::time::Instant::now() + debounce;
+                        }
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            on_change(&changed);
+            changed.clear();
+        }
+    });
+    Ok(watcher)
+}
+
+/// - Default `Config::max_fs_concurrency`: enough to keep a local disk busy
+///   without running a networked mount out of descriptors.
+pub const DEFAULT_FS_CONCURRENCY: usize = 16;
+
+/// - Blocking counting semaphore around filesystem reads during the index
+///   build, so a parallel walk over a slow/networked mount can't open more
+///   than `max` files at once and exhaust descriptors.
+/// - Blocking (not tokio's) because the build runs on plain threads before
+///   the server starts.
+pub struct FsLimiter {
+    max: usize,
+    in_use: Mutex<usize>,
+    freed: Condvar,
+}
+
+/// - A held read slot; released on drop.
+pub struct FsPermit<'a> {
+    limiter: &'a FsLimiter,
+}
+
+impl FsLimiter {
+    pub fn new(max: usize) -> Self {
+        FsLimiter {
+            max: max.max(1),
+            in_use: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn acquire(&self) -> FsPermit<'_> {
+        let mut in_use = self.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        while *in_use >= self.max {
+            in_use = self.freed.wait(in_use).unwrap_or_else(|e| e.into_inner());
+        }
+        *in_use += 1;
+        FsPermit { limiter: self }
+    }
+}
+
+impl Drop for FsPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_use = self
+            .limiter
+            .in_use
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *in_use -= 1;
+        self.limiter.freed.notify_one();
+    }
+}
+
+/// - Per-client token buckets: each key (a client IP) may make `per_minute`
+///   requests in a burst, refilled at `per_minute` a minute.
+/// - `check` takes the clock as an argument so tests can step it.
+/// - Clients idle long enough to be full again are forgotten once more than
+///   RATE_LIMIT_TRACKED are tracked, bounding memory under a spray of IPs.
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, (f64, std::time::Instant)>>,
+}
+
+/// - Tracked clients before idle ones are swept (see RateLimiter).
+pub const RATE_LIMIT_TRACKED: usize = 10_000;
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute: per_minute.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// - Spend one token for `key` at `now`: `Ok` if one was available,
+    ///   otherwise `Err` with how long until the next one is.
+    pub fn check(&self, key: &str, now: std::time::Instant) -> Result<(), std::time::Duration> {
+        let capacity = f64::from(self.per_minute);
+        let per_sec = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > RATE_LIMIT_TRACKED && !buckets.contains_key(key) {
+            let full_after = std::time::Duration::from_secs(60);
+            buckets.retain(|_, (_, seen)| now.saturating_duration_since(*seen) < full_after);
+        }
+        let (tokens, seen) = buckets.entry(key.to_string()).or_insert((capacity, now));
+        let refilled = now.saturating_duration_since(*seen).as_secs_f64() * per_sec;
+        *tokens = (*tokens + refilled).min(capacity);
+        *seen = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(std::time::Duration::from_secs_f64(
+                (1.0 - *tokens) / per_sec,
+            ))
+        }
+    }
+}
+
+/// - `f` over `items` on up to `workers` scoped threads, results in input
+///   order, so callers assemble them exactly as a sequential loop would.
+/// - Items are handed out one at a time, so a slow one doesn't hold up a
+///   pre-assigned chunk. A panic in `f` propagates to the caller.
+pub fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    workers: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let workers = workers.clamp(1, items.len().max(1));
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            return done;
+                        };
+                        done.push((i, f(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// - Where the server reads content and static files from, and where it listens.
+/// - Paths may be relative (to the working directory) or absolute; page
+///   templates are compiled in and don't depend on `public_dir`.
+/// - Parsed from the command line by the binary; each flag falls back to its
+///   env var, then to its `#[arg]` default — also what `Default` gives, so
+///   defaults are written once, here.
+#[derive(Clone, Debug, PartialEq, Eq, clap::Parser)]
+#[command(version, about = "Serves the Light.vn works archive")]
+pub struct Config {
+    /// Directory holding the works (YYYY/title.md)
+    #[arg(long, env = "WORKS_DIR", default_value = "works")]
+    pub works_dir: PathBuf,
+    /// Directory of static files (CSS, JS, images)
+    #[arg(long, env = "PUBLIC_DIR", default_value = "public")]
+    pub public_dir: PathBuf,
+    /// Port to listen on
+    #[arg(long, env = "PORT", default_value_t = 8080)]
+    pub port: u16,
+    /// Address to bind to
+    #[arg(long, env = "BIND", default_value = "0.0.0.0")]
+    pub bind: IpAddr,
+    /// Rendered work pages kept in memory (0 disables the cache)
+    #[arg(long, env = "RENDER_CACHE_SIZE", default_value_t = DEFAULT_RENDER_CACHE_SIZE)]
+    pub render_cache_size: usize,
+    /// Write a static copy of the site to OUT_DIR and exit instead of serving
+    #[arg(long, value_name = "OUT_DIR")]
+    pub export: Option<PathBuf>,
+    /// Validate every work (frontmatter, includes, internal links), print a
+    /// report and exit nonzero on problems instead of serving
+    #[arg(long)]
+    pub check: bool,
+    /// Directory resized local images (`/thumb/{path}?w=`) are cached in
+    #[arg(long, env = "THUMB_CACHE_DIR", default_value_os_t = default_thumb_cache_dir())]
+    pub thumb_cache_dir: PathBuf,
+    /// Seconds a request may take before it's answered with 408
+    #[arg(long, env = "REQUEST_TIMEOUT", default_value_t = DEFAULT_REQUEST_TIMEOUT,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    pub request_timeout: u64,
+    /// Cache-Control max-ages overriding the defaults, as comma-separated
+    /// `KEY=SECONDS` rules keyed by `.ext`, `type/subtype`, `type/*` or a
+    /// `/path/` prefix (e.g. `text/html=60,image/*=604800`)
+    #[arg(long, env = "CACHE_POLICY")]
+    pub cache_policy: Option<String>,
+    /// Origins allowed to call /api/* cross-origin, comma-separated; `*` for any
+    #[arg(long, env = "CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+    /// Requests per minute per client to /api/search and /api/tree (0 disables)
+    #[arg(long, env = "RATE_LIMIT", default_value_t = DEFAULT_RATE_LIMIT)]
+    pub rate_limit: u32,
+    /// Take the client IP from X-Forwarded-For (only behind a proxy that sets it)
+    #[arg(long, env = "TRUST_FORWARDED_FOR", value_parser = clap::builder::BoolishValueParser::new())]
+    pub trust_forwarded_for: bool,
+    /// Site name, shown in page titles and the header bar
+    #[arg(long, env = "SITE_TITLE", default_value = DEFAULT_SITE_TITLE)]
+    pub site_title: String,
+    /// Logo image shown next to the site name in the header bar
+    #[arg(long, env = "SITE_LOGO_URL")]
+    pub site_logo_url: Option<String>,
+    /// Serve request and cache counters at /metrics (Prometheus text format)
+    #[arg(long, env = "METRICS", value_parser = clap::builder::BoolishValueParser::new())]
+    pub metrics: bool,
+    /// Named content root NAME=DIR, served under /works/NAME (repeatable;
+    /// replaces --works-dir as the content when given)
+    #[arg(long = "collection", env = "COLLECTIONS", value_delimiter = ',')]
+    pub collections: Vec<Collection>,
+    /// Largest work file, in bytes, that's rendered; bigger ones answer 413
+    /// (0 disables the limit)
+    #[arg(long, env = "MAX_WORK_BYTES", default_value_t = DEFAULT_MAX_WORK_BYTES)]
+    pub max_work_bytes: u64,
+    /// Most work files read at once while building the index
+    #[arg(long, env = "MAX_FS_CONCURRENCY", default_value_t = DEFAULT_FS_CONCURRENCY,
+          value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_fs_concurrency: usize,
+    /// Tell every crawler to keep out via robots.txt (e.g. on staging)
+    #[arg(long, env = "DISALLOW_CRAWLERS", value_parser = clap::builder::BoolishValueParser::new())]
+    pub disallow_crawlers: bool,
+    /// URL prefixes a work's thumbnail may come from (comma-separated);
+    /// images elsewhere are passed over for the next one that qualifies
+    #[arg(
+        long,
+        env = "THUMBNAIL_SOURCES",
+        value_delimiter = ',',
+        default_values = DEFAULT_THUMBNAIL_SOURCES
+    )]
+    pub thumbnail_sources: Vec<String>,
+    /// Remote hosts (e.g. `cdn.example.com`, comma-separated) whose video
+    /// and audio links are embedded as players; other remote media stays a link
+    #[arg(long, env = "MEDIA_HOSTS", value_delimiter = ',')]
+    pub media_hosts: Vec<String>,
+    /// Give images without alt text one made from their file name
+    /// (`my-cover.png` → "My Cover")
+    #[arg(long, env = "AUTO_ALT", value_parser = clap::builder::BoolishValueParser::new())]
+    pub auto_alt: bool,
+    /// Language of unsuffixed work files, and the one served when a visitor's
+    /// language has no variant ("Title.ja.md" beside "Title.md")
+    #[arg(long, env = "BASE_LANG", default_value = DEFAULT_BASE_LANG)]
+    pub base_lang: String,
+    /// Extra URL each work answers at, built from its release date and
+    /// title, e.g. `/:year/:month/:slug`
+    #[arg(long, env = "PERMALINK_PATTERN")]
+    pub permalink_pattern: Option<String>,
+    /// Per-work comment counts written by a comment service (default
+    /// `_comments.json` in the works dir)
+    #[arg(long, env = "COMMENTS_FILE")]
+    pub comments_file: Option<PathBuf>,
+    /// Where view counts persist across restarts (unset: kept in memory only)
+    #[arg(long, env = "STATS_FILE")]
+    pub stats_file: Option<PathBuf>,
+    /// Works featured on the homepage, in order (default `_featured.toml` in
+    /// the works dir)
+    #[arg(long, env = "FEATURED_FILE")]
+    pub featured_file: Option<PathBuf>,
+    /// Secret `?preview=` value that shows draft works (unset: drafts are
+    /// never shown)
+    #[arg(long, env = "DRAFT_PREVIEW_TOKEN", hide_env_values = true)]
+    pub draft_preview_token: Option<String>,
+}
+
+/// - A named content root (`--collection games=path/to/games`): its works are
+///   served under `/works/{name}/…`, its files under `/raw/{name}/…`, as if
+///   `dir` were a folder `name/` of one works tree.
+/// - Names are one path segment, not starting with `_` or `.` (those are
+///   hidden in works/).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Collection {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl std::str::FromStr for Collection {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let (name, dir) = raw
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=DIR, got {:?}", raw))?;
+        let (name, dir) = (name.trim(), dir.trim());
+        if name.is_empty()
+            || dir.is_empty()
+            || name.contains(['/', '\\'])
+            || name.starts_with(['_', '.'])
+        {
+            return Err(format!("invalid collection {:?}", raw));
+        }
+        Ok(Collection {
+            name: name.to_string(),
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+/// - Default `Config::site_title`.
+pub const DEFAULT_SITE_TITLE: &str = "Light.vn Works";
+
+/// - Default `Config::rate_limit`: off. Behind a reverse proxy every client
+///   shares the proxy's address unless `trust_forwarded_for` is set, so a
+///   default budget would cap the whole site; a deployment opts in to both.
+pub const DEFAULT_RATE_LIMIT: u32 = 0;
+
+/// - Default `Config::request_timeout`: far above any real page or tree
+///   build, so only a stuck handler hits it.
+pub const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+
+/// - Default `Config::thumb_cache_dir`: under the system temp dir, so a fresh
+///   machine starts cold but nothing needs creating up front.
+pub fn default_thumb_cache_dir() -> PathBuf {
+    std::env::temp_dir().join("lightvn-thumbs")
+}
+
+/// - Default `Config::max_work_bytes`: far beyond any hand-written work, so
+///   only a stray dump or generated file is refused.
+pub const DEFAULT_MAX_WORK_BYTES: u64 = 8 * 1024 * 1024;
+
+/// - Default `Config::thumbnail_sources`: GitHub's upload host, and files
+///   served from the works dir.
+pub const DEFAULT_THUMBNAIL_SOURCES: [&str; 2] = ["https://github.com/user-attachments/", "/raw/"];
+
+/// - Default `Config::base_lang`.
+pub const DEFAULT_BASE_LANG: &str = "en";
+
+/// - Default `Config::render_cache_size`: every work in a couple of language
+///   variants, at ~30 KB a page a few MB at most.
+pub const DEFAULT_RENDER_CACHE_SIZE: usize = 512;
+
+impl Default for Config {
+    // - The `#[arg]` defaults, the single place they're written: an empty
+    //   command line with the env lookups switched off, so a default doesn't
+    //   depend on where it's built.
+    fn default() -> Self {
+        use clap::{CommandFactory, FromArgMatches};
+        let matches = Config::command()
+            .mut_args(|arg| arg.env(None))
+            .get_matches_from([env!("CARGO_PKG_NAME")]);
+        Config::from_arg_matches(&matches).expect("the #[arg] defaults parse")
+    }
+}
+
+impl Config {
+    /// - The settings the binary gets without flags: each from its env var
+    ///   (named on its `#[arg(env)]`), else its default — parsed by clap, so
+    ///   env and CLI take the same values.
+    /// - A value the CLI would reject panics with clap's message rather than
+    ///   quietly keeping the default: an on/off setting with a typo could be
+    ///   a security setting left off.
+    pub fn from_env() -> Self {
+        use clap::Parser;
+        Config::try_parse_from([env!("CARGO_PKG_NAME")]).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// - The content roots and the segment each is mounted under: every
+    ///   collection (a repeated name keeps its first dir), or `works_dir`
+    ///   unmounted when there are none.
+    pub fn roots(&self) -> Vec<(Option<&str>, &Path)> {
+        if self.collections.is_empty() {
+            return vec![(None, self.works_dir.as_path())];
+        }
+        let mut seen = HashSet::new();
+        self.collections
+            .iter()
+            .filter(|c| seen.insert(c.name.as_str()))
+            .map(|c| (Some(c.name.as_str()), c.dir.as_path()))
+            .collect()
+    }
+
+    /// - The root holding `rel`, a path in the works tree as URLs see it
+    ///   ("2024/Title.md", or "games/2024/Title.md" with collections), and
+    ///   `rel` within that root. None for an unknown collection.
+    pub fn locate<'a>(&'a self, rel: &'a str) -> Option<(&'a Path, &'a str)> {
+        self.roots()
+            .into_iter()
+            .find_map(|(mount, root)| match mount {
+                None => Some((root, rel)),
+                Some(name) if rel == name => Some((root, "")),
+                Some(name) => Some((root, rel.strip_prefix(name)?.strip_prefix('/')?)),
+            })
+    }
+
+    /// - Where `rel` (see `locate`) is on disk.
+    pub fn source_path(&self, rel: &str) -> Option<PathBuf> {
+        let (root, rest) = self.locate(rel)?;
+        Some(root.join(rest))
+    }
+
+    /// - Socket address to listen on.
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+}
+
+/// - Cache-Control by response type: rules match a request path's extension
+///   (`.png`), an exact content type (`text/html`), a type wildcard
+///   (`image/*`) or a path prefix (`/raw/`, longest wins), in that order of
+///   precedence, mapping to a max-age in seconds.
+/// - `0` means `no-cache` (store, but revalidate every time), the default for
+///   anything unmatched. Longer max-ages only suit content that can't go stale
+///   under the same URL for that long.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachePolicy {
+    rules: Vec<(String, u64)>,
+}
+
+impl Default for CachePolicy {
+    // - Images (covers, screenshots, icons) change rarely and are the bulk of
+    //   the bytes, so a day; so do the other assets under /raw/ (audio, video,
+    //   downloads). HTML/JSON/CSS/JS keep revalidating: pages embed live
+    //   counts, unhashed CSS/JS URLs would go stale after a deploy, and /raw/
+    //   also serves work sources and `_comments.json`.
+    fn default() -> Self {
+        let rules = [
+            ("image/*", 86_400),
+            ("/raw/", 86_400),
+            ("text/*", 0),
+            ("application/json", 0),
+        ];
+        CachePolicy {
+            rules: rules.iter().map(|(k, s)| (k.to_string(), *s)).collect(),
+        }
+    }
+}
+
+impl CachePolicy {
+    /// - Defaults overlaid with `Config::cache_policy` overrides:
+    ///   `text/html=60,image/*=604800,.svg=0`. Malformed entries are
+    ///   skipped; a later rule for the same key wins.
+    pub fn parse(raw: &str) -> Self {
+        let mut policy = CachePolicy::default();
+        for entry in raw.split(',') {
+            let Some((key, secs)) = entry.split_once('=') else {
+                continue;
+            };
+            let (key, Ok(secs)) = (key.trim().to_ascii_lowercase(), secs.trim().parse()) else {
+                continue;
+            };
+            if key.is_empty() {
+                continue;
+            }
+            policy.rules.retain(|(k, _)| *k != key);
+            policy.rules.push((key, secs));
+        }
+        policy
+    }
+
+    /// - Max-age for a response to `path` with `content_type` (parameters
+    ///   like `; charset=utf-8` ignored).
+    pub fn max_age(&self, path: &str, content_type: Option<&str>) -> u64 {
+        let find = |key: &str| self.rules.iter().find(|(k, _)| k == key).map(|(_, s)| *s);
+        let ext = path
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, ext)| format!(".{}", ext.to_ascii_lowercase()));
+        let mime = content_type
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_ascii_lowercase());
+        let prefix = || {
+            self.rules
+                .iter()
+                .filter(|(k, _)| k.starts_with('/') && path.starts_with(k.as_str()))
+                .max_by_key(|(k, _)| k.len())
+                .map(|(_, s)| *s)
+        };
+        ext.and_then(|e| find(&e))
+            .or_else(|| mime.as_deref().and_then(find))
+            .or_else(|| {
+                let major = mime.as_deref()?.split('/').next()?;
+                find(&format!("{}/*", major))
+            })
+            .or_else(prefix)
+            .unwrap_or(0)
+    }
+
+    /// - The Cache-Control value for `max_age`.
+    pub fn header_value(max_age: u64) -> String {
+        match max_age {
+            0 => "no-cache".to_string(),
+            secs => format!("public, max-age={}", secs),
+        }
+    }
+}
+
+/// - Which publishing-quality checks `quality_failures` applies.
+/// - A work's title is its filename, so it can't be missing; the date check
+///   is on `released` (an explicit "unknown" counts as set).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QualityRules {
+    pub thumbnail: bool,
+    pub released: bool,
+    pub min_words: Option<usize>,
+    pub headings: bool,
+}
+
+/// - Default body-length floor for the `short_body` rule.
+pub const DEFAULT_MIN_WORDS: usize = 30;
+
+impl Default for QualityRules {
+    fn default() -> Self {
+        QualityRules {
+            thumbnail: true,
+            released: true,
+            min_words: Some(DEFAULT_MIN_WORDS),
+            headings: true,
+        }
+    }
+}
+
+impl QualityRules {
+    /// - From `?rules=thumbnail,released,words,headings&min_words=N`; each
+    ///   param falls back to the defaults (all rules, `DEFAULT_MIN_WORDS`).
+    pub fn from_params(rules: Option<&str>, min_words: Option<&str>) -> Self {
+        let enabled = |name: &str| rules.is_none_or(|r| r.split(',').any(|x| x.trim() == name));
+        let floor = min_words
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap_or(DEFAULT_MIN_WORDS);
+        QualityRules {
+            thumbnail: enabled("thumbnail"),
+            released: enabled("released"),
+            min_words: enabled("words").then_some(floor),
+            headings: enabled("headings"),
+        }
+    }
+}
+
+/// - The rules a work fails, by report key: `missing_thumbnail`,
+///   `missing_released`, `short_body`, `no_headings`.
+pub fn quality_failures(game: &ParsedGame, rules: &QualityRules) -> Vec<&'static str> {
+    let mut failed = Vec::new();
+    if rules.thumbnail && game.thumbnail.is_none() {
+        failed.push("missing_thumbnail");
+    }
+    if rules.released
+        && game
+            .meta
+            .released
+            .as_deref()
+            .is_none_or(|r| r.trim().is_empty())
+    {
+        failed.push("missing_released");
+    }
+    if rules
+        .min_words
+        .is_some_and(|floor| word_count(&game.body_html) < floor)
+    {
+        failed.push("short_body");
+    }
+    let has_heading = (1..=6).any(|n| game.body_html.contains(&format!("<h{}", n)));
+    if rules.headings && !has_heading {
+        failed.push("no_headings");
+    }
+    failed
+}
+
+/// - Words in rendered HTML, tags stripped: whitespace-separated runs, except
+///   that each CJK character counts as one word (Japanese/Chinese text has no
+///   spaces, and a character is roughly a word for length purposes).
+pub fn word_count(html: &str) -> usize {
+    count_words(html, true)
+}
+
+// - Shared by `word_count` (over HTML, tags skipped) and
+//   `markdown_word_count` (over plain text, where a literal '<' is just a
+//   character).
+fn count_words(text: &str, skip_tags: bool) -> usize {
+    let mut count = 0;
+    let mut in_tag = false;
+    let mut in_word = false;
+    for c in text.chars() {
+        match c {
+            '<' if skip_tags => {
+                in_tag = true;
+                in_word = false;
+            }
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            c if c.is_whitespace() => in_word = false,
+            c if is_cjk(c) => {
+                count += 1;
+                in_word = false;
+            }
+            _ => {
+                if !in_word {
+                    count += 1;
+                }
+                in_word = true;
+            }
+        }
+    }
+    count
+}
+
+/// - Reading pace behind `reading_time_minutes`, in `word_count` words.
+pub const READING_WORDS_PER_MINUTE: usize = 220;
+
+/// - Whole minutes to read `words` words, rounded up; at least 1. The one
+///   reading-time estimate: a work's is taken from its `ParsedGame::word_count`
+///   (`markdown_word_count`, so code blocks don't count) and shown on its
+///   page, tree node and feed item.
+/// - Saturates at u32::MAX rather than wrapping.
+pub fn reading_time_minutes(words: usize) -> u32 {
+    let minutes = words.div_ceil(READING_WORDS_PER_MINUTE).max(1);
+    u32::try_from(minutes).unwrap_or(u32::MAX)
+}
+
+/// - Words in a markdown body (frontmatter already split off), counted as
+///   `word_count` does. Fenced and indented code blocks don't count — nobody
+///   reads a script listing at prose speed — but inline code does.
+pub fn markdown_word_count(md_body: &str) -> usize {
+    let mut text = String::new();
+    let mut in_code_block = false;
+    for event in Parser::new_ext(md_body, gfm_options()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(t) | Event::Code(t) if !in_code_block => text.push_str(&t),
+            // - Inline ends (`**bo**ld`) stay joined; block and line
+            //   boundaries separate words the events would run together.
+            Event::End(
+                TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Link
+                | TagEnd::Image,
+            ) => {}
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+    count_words(&text, false)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF   // hiragana, katakana
+        | 0x3400..=0x4DBF // CJK extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xAC00..=0xD7AF // hangul syllables
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+    )
+}
+
+/// - Cap on `/api/search` results.
+pub const MAX_SEARCH_RESULTS: usize = 50;
+
+/// - Length, in characters, of a search result's snippet window.
+pub const SEARCH_SNIPPET_CHARS: usize = 200;
+
+/// - Plain text of rendered HTML for full-text search: tags dropped (block
+///   tags become a space, so words either side of a block boundary don't
+///   fuse; inline ones vanish), the escapes `html_escape` writes decoded,
+///   whitespace collapsed.
+pub fn html_to_text(html: &str) -> String {
+    const BLOCK_TAGS: &[&str] = &[
+        "p",
+        "div",
+        "br",
+        "hr",
+        "li",
+        "ul",
+        "ol",
+        "pre",
+        "table",
+        "tr",
+        "td",
+        "th",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "blockquote",
+        "nav",
+        "section",
+    ];
+    let mut stripped = String::with_capacity(html.len());
+    let mut tag = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag
+                    .trim_start_matches('/')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or("")
+                    .to_ascii_lowercase();
+                if BLOCK_TAGS.contains(&name.as_str()) {
+                    stripped.push(' ');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            _ => stripped.push(c),
+        }
+    }
+    let decoded = stripped
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// - Plain text of the first `<p>` in rendered HTML that has any, cut to
+///   `max_chars` with "…" — a page description for works without a tagline.
+/// - Paragraphs holding only images (the screenshot block) are skipped.
+pub fn first_paragraph(html: &str, max_chars: usize) -> Option<String> {
+    let mut rest = html;
+    while let Some(start) = rest.find("<p>") {
+        rest = &rest[start + 3..];
+        let end = rest.find("</p>").unwrap_or(rest.len());
+        let text = html_to_text(&rest[..end]);
+        if !text.is_empty() {
+            if text.chars().count() <= max_chars {
+                return Some(text);
+            }
+            let cut: String = text.chars().take(max_chars).collect();
+            return Some(format!("{}…", cut.trim_end()));
+        }
+        rest = &rest[end..];
+    }
+    None
+}
+
+/// Longest tree-node excerpt, in characters, before "…".
+pub const EXCERPT_CHARS: usize = 160;
+
+/// - `text` cut to at most `max_chars` characters at a word boundary, with
+///   "…" appended when anything was dropped.
+/// - A single word longer than `max_chars` is cut mid-word.
+pub fn truncate_at_word(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars + 1).collect();
+    let kept = match cut.rfind(char::is_whitespace) {
+        Some(space) if space > 0 => &cut[..space],
+        _ => {
+            &cut[..cut
+                .char_indices()
+                .nth(max_chars)
+                .map_or(cut.len(), |(i, _)| i)]
+        }
+    };
+    format!("{}…", kept.trim_end())
+}
+
+/// - Card summary for a work: its frontmatter `description`, else the first
+///   paragraph of the rendered body (headings and image-only paragraphs are
+///   skipped), cut to EXCERPT_CHARS at a word boundary.
+/// - None when neither has text (e.g. a .txt work without a description).
+pub fn work_excerpt(meta: &GameMeta, body_html: &str) -> Option<String> {
+    let text = meta
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(str::to_string)
+        .or_else(|| first_paragraph(body_html, usize::MAX))?;
+    Some(truncate_at_word(&text, EXCERPT_CHARS))
+}
+
+/// - Snippet for a full-text match of `query` against `text`: every
+///   whitespace-separated term must occur, case-insensitively; None when one
+///   doesn't or the query is blank.
+/// - The window is SEARCH_SNIPPET_CHARS characters around the earliest hit
+///   of any term, with "…" where the text was cut.
+pub fn search_snippet(text: &str, query: &str) -> Option<String> {
+    // - Fold per character so indices into the folded text are indices into
+    //   the original (a full to_lowercase can change the length).
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().copied().map(fold).collect();
+    let mut first: Option<(usize, usize)> = None;
+    let mut any_term = false;
+    for term in query.split_whitespace() {
+        any_term = true;
+        let term: Vec<char> = term.chars().map(fold).collect();
+        let at = folded
+            .windows(term.len())
+            .position(|w| w == term.as_slice())?;
+        if first.is_none_or(|(hit, _)| at < hit) {
+            first = Some((at, term.len()));
+        }
+    }
+    if !any_term {
+        return None;
+    }
+    let (hit, len) = first?;
+    let lead = SEARCH_SNIPPET_CHARS.saturating_sub(len) / 2;
+    let end = (hit.saturating_sub(lead) + SEARCH_SNIPPET_CHARS).min(chars.len());
+    let start = end.saturating_sub(SEARCH_SNIPPET_CHARS);
+    let mut snippet: String = chars[start..end].iter().collect();
+    snippet = snippet.trim().to_string();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// - How much longer than a fuzzy search term a word may be and still match
+///   it (see `fuzzy_search`).
+pub const FUZZY_SLACK: usize = 2;
+
+/// - Typo-tolerant match of `query` against `text`, for `/api/search?fuzzy=true`:
+///   each whitespace-separated term is scored against every word of `text`
+///   with the Skim algorithm (the term's letters in order, case-insensitive,
+///   gaps allowed), keeping its best word. "protagnist" finds "protagonist".
+/// - A word more than FUZZY_SLACK characters longer than the term doesn't
+///   count, so a short term can't match letters strewn across a long word.
+/// - The summed score (higher is closer) and the word the first term matched,
+///   to centre a snippet on; None when a term matches no word or the query is blank.
+pub fn fuzzy_search(text: &str, query: &str) -> Option<(i64, String)> {
+    use fuzzy_matcher::skim::SkimMatcherV2;
+    use fuzzy_matcher::FuzzyMatcher;
+
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mut total = 0;
+    let mut first_word = None;
+    for term in query.split_whitespace() {
+        let len = term.chars().count();
+        let (score, word) = words
+            .iter()
+            .filter(|w| w.chars().count() <= len + FUZZY_SLACK)
+            .filter_map(|w| Some((matcher.fuzzy_match(w, term)?, *w)))
+            .max_by_key(|(score, _)| *score)?;
+        total += score;
+        first_word.get_or_insert(word);
+    }
+    Some((total, first_word?.to_string()))
+}
+
+/// - Outcome of `normalize_path` for an incoming request path.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PathNormalization {
+    /// Already canonical; route as-is.
+    Canonical,
+    /// Sloppy but safe (`//`, `/./`); redirect here.
+    Redirect(String),
+    /// Contains a `..` segment; never resolved, even when it'd stay in bounds.
+    Reject,
+}
+
+/// - Collapse duplicate slashes and drop `.` segments from a raw request
+///   path, e.g. `/works//2023/./Title` → `/works/2023/Title`.
+/// - `..` (also %-encoded, which is how it'd slip past a naive check) is
+///   rejected rather than resolved. A trailing slash is preserved.
+pub fn normalize_path(path: &str) -> PathNormalization {
+    let mut segments = Vec::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        match segment.to_ascii_lowercase().as_str() {
+            "." | "%2e" => {}
+            ".." | "%2e%2e" | ".%2e" | "%2e." => return PathNormalization::Reject,
+            _ => segments.push(segment),
+        }
+    }
+    let mut canonical = format!("/{}", segments.join("/"));
+    if path.ends_with('/') && !segments.is_empty() {
+        canonical.push('/');
+    }
+    if canonical == path {
+        PathNormalization::Canonical
+    } else {
+        PathNormalization::Redirect(canonical)
+    }
+}
+
+/// - Every link and image target in a markdown body, in document order:
+///   `[..](url)` / `![..](url)` destinations plus `src="…"` / `href="…"` in
+///   inline HTML (where GitHub-style `<img>` tags live).
+/// - Raw, as written — callers decide what's internal (see `local_asset_path`).
+pub fn extract_link_targets(md: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    for event in Parser::new_ext(md, gfm_options()) {
+        match event {
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => {
+                targets.push(dest_url.to_string());
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                for attr in ["src=\"", "href=\""] {
+                    let mut rest = html.as_ref();
+                    while let Some(start) = rest.find(attr) {
+                        rest = &rest[start + attr.len()..];
+                        let Some(end) = rest.find('"') else {
+                            break;
+                        };
+                        targets.push(rest[..end].to_string());
+                        rest = &rest[end..];
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    targets
+}
+
+/// - One heading in a work body, as anchored in it and listed in the
+///   `/api/work` outline; the table of contents links the `h2`/`h3`s.
+/// - Serialized as `{level, slug, text}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    pub level: u8, // 1–6
+    #[serde(rename = "slug")]
+    pub id: String, // unique slug, the heading's id attribute
+    pub text: String, // plain text, as shown
+}
+
+/// - URL-safe slug of heading text: lowercased letters and digits (any
+///   script, so Japanese headings keep their words), runs of anything else
+///   collapsed to one `-`, none at the ends.
+/// - "section" when nothing is left.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// - Every heading in a markdown body, in document order, with unique ids:
+///   a repeated slug gets `-2`, `-3`, … appended.
+/// - `markdown_to_html_with` sets the same ids, so TOC and outline links
+///   always land.
+pub fn extract_headings(md: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut open: Option<(u8, String)> = None;
+    for event in Parser::new_ext(md, gfm_options()) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                open = Some((level as u8, String::new()));
+            }
+            Event::Text(t) => {
+                if let Some((_, text)) = open.as_mut() {
+                    text.push_str(&expand_emoji_shortcodes(&t));
+                }
+            }
+            Event::Code(t) => {
+                if let Some((_, text)) = open.as_mut() {
+                    text.push_str(&t);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, text)) = open.take() {
+                    let base = slugify(&text);
+                    let mut id = base.clone();
+                    let mut n = 1;
+                    while !used.insert(id.clone()) {
+                        n += 1;
+                        id = format!("{}-{}", base, n);
+                    }
+                    headings.push(Heading {
+                        level,
+                        id,
+                        text: text.trim().to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+/// - `<nav class="toc">` linking each `h2`/`h3`, h3s nested visually by class.
+/// - Empty below two of them: a one-entry contents list is just noise.
+pub fn render_toc(headings: &[Heading]) -> String {
+    let listed: Vec<&Heading> = headings
+        .iter()
+        .filter(|h| matches!(h.level, 2 | 3))
+        .collect();
+    if listed.len() < 2 {
+        return String::new();
+    }
+    let items: String = listed
+        .iter()
+        .map(|h| {
+            format!(
+                r##"<li class="toc-h{}"><a href="#{}">{}</a></li>"##,
+                h.level,
+                html_escape(&h.id),
+                html_escape(&h.text)
+            )
+        })
+        .collect();
+    format!(r#"<nav class="toc"><ul>{}</ul></nav>"#, items)
+}
+
+/// - A link target as written in a work, resolved to a `/`-separated path
+///   with no leading slash: query and fragment dropped, %-escapes decoded,
+///   `.`/`..` applied. Relative targets resolve against `base`
+///   ("works/2024"); ones starting with `/` against the root.
+/// - `None` for anything that isn't a local path (external URLs, `//host`,
+///   anchors, `mailto:`), for a `..` climbing above the root, and when
+///   nothing is left.
+pub fn resolve_relative(base: &str, target: &str) -> Option<String> {
+    let target = target.split(['?', '#']).next().unwrap_or("").trim();
+    if target.is_empty() || target.starts_with("//") || target.contains(':') {
+        return None;
+    }
+    let decoded = percent_encoding::percent_decode_str(target)
+        .decode_utf8()
+        .ok()?;
+    let mut parts: Vec<&str> = match decoded.starts_with('/') {
+        true => Vec::new(),
+        false => base.split('/').filter(|p| !p.is_empty()).collect(),
+    };
+    for part in decoded.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            p => parts.push(p),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// - The file under works/ a link target points at, relative to works/
+///   ("2024/cover.png"), for a work living in `work_dir` ("2024").
+/// - `/raw/…` targets are root-relative; bare/`./`/`../` targets resolve
+///   against `work_dir` (see `resolve_relative`).
+/// - `None` for anything that isn't a local file: page routes like
+///   `/works/…`, paths escaping works/, and whatever `resolve_relative` rejects.
+pub fn local_asset_path(work_dir: &str, target: &str) -> Option<String> {
+    let path = resolve_relative(&format!("raw/{}", work_dir), target)?;
+    path.strip_prefix("raw/").map(str::to_string)
+}
+
+/// - Where the browser should fetch an image a work in `work_dir` embeds:
+///   relative targets ("screens/title.png", "./a.png") resolve to the
+///   `/raw/{work_dir}/…` mount, query and fragment kept.
+/// - Absolute URLs, root-relative paths, and targets escaping works/ are
+///   returned as written; so is everything when `work_dir` is unknown ("").
+pub fn resolve_image_url(work_dir: &str, url: &str) -> String {
+    let is_relative =
+        !(url.is_empty() || url.starts_with('/') || url.starts_with('#') || url.contains(':'));
+    if work_dir.is_empty() || !is_relative {
+        return url.to_string();
+    }
+    match local_asset_path(work_dir, url) {
+        Some(file) => {
+            let suffix = url.find(['?', '#']).map_or("", |i| &url[i..]);
+            format!("/raw/{}{}", encode_path(&file), suffix)
+        }
+        None => url.to_string(),
+    }
+}
+
+/// - The work a link target points at, as a canonical path ("/works/2023/Title"),
+///   for a link on a page in `work_dir` ("2023", or "2023/Series" when
+///   nested): `/works/…` targets as-is, relative ones against
+///   `/works/{work_dir}/` (so "Other" and "../2023/Other" both work).
+/// - A trailing `.md`/`.txt` is dropped. `None` for anything that isn't
+///   shaped like a work URL. Whether the work exists is left to the caller.
+pub fn work_link_target(work_dir: &str, target: &str) -> Option<String> {
+    let path = resolve_relative(&format!("works/{}", work_dir), target)?;
+    let parts: Vec<&str> = path.split('/').collect();
+    match parts.as_slice() {
+        ["works", year, rest @ ..] if !rest.is_empty() => {
+            let title = rest.join("/");
+            let title = strip_work_ext(&title).map_or(title.as_str(), |(stem, _)| stem);
+            Some(format!("/works/{}/{}", year, title))
+        }
+        _ => None,
+    }
+}
+
+/// - Outcome of checking one link target (see `check_link`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkCheck {
+    /// `http(s)://` or `//host`: listed, never fetched.
+    External,
+    Ok,
+    Broken,
+}
+
+/// - Whether a link target on a page in `work_dir` ("2023", "2023/Series")
+///   still resolves: a work URL (`/works/…`, or a relative title) must name a
+///   work or a directory of works, and a `/raw/…` or relative file path must
+///   be a file under works/ (`file_exists`, given its path relative to works/).
+/// - A relative target passes as either, so "Other" and "cover.png" both work.
+/// - `None` for targets that aren't checked: anchors, `mailto:` and other
+///   schemes, and site routes outside `/works/` and `/raw/`.
+pub fn check_link(
+    games: &HashMap<String, ParsedGame>,
+    work_dir: &str,
+    target: &str,
+    file_exists: impl Fn(&str) -> bool,
+) -> Option<LinkCheck> {
+    let trimmed = target.trim();
+    if trimmed.starts_with("http://")
+        || trimmed.starts_with("https://")
+        || trimmed.starts_with("//")
+    {
+        return Some(LinkCheck::External);
+    }
+    let page = work_link_target(work_dir, trimmed);
+    let file = local_asset_path(work_dir, trimmed);
+    if page.is_none() && file.is_none() {
+        return None;
+    }
+    let page_exists = page.is_some_and(|path| {
+        let dir = format!("{}/", path);
+        games.contains_key(&path) || games.keys().any(|k| k.starts_with(&dir))
+    });
+    let ok = page_exists || file.is_some_and(|f| file_exists(&f));
+    Some(if ok { LinkCheck::Ok } else { LinkCheck::Broken })
+}
+
+// Quoted DOT string literal.
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// - GraphViz DOT of work-to-work links: one node per work (labelled by title,
+///   clustered by year), one edge per distinct link to another existing work.
+/// - Drafts are left out, as nodes and as link targets.
+/// - Output is sorted (years, then paths, then edges) so it diffs cleanly.
+pub fn link_graph_dot(games: &HashMap<String, ParsedGame>) -> String {
+    let listed = || games.values().filter(|g| !is_draft(&g.meta));
+    let mut by_year: BTreeMap<String, Vec<&ParsedGame>> = Default::default();
+    for game in listed() {
+        by_year.entry(game.year_dir()).or_default().push(game);
+    }
+    let mut edges: Vec<(&str, String)> = listed()
+        .flat_map(|g| {
+            g.links
+                .iter()
+                .filter_map(|t| work_link_target(&g.dir(), t))
+                .filter(|to| to != &g.path && games.get(to).is_some_and(|t| !is_draft(&t.meta)))
+                .map(|to| (g.path.as_str(), to))
+        })
+        .collect();
+    edges.sort();
+    edges.dedup();
+
+    let mut out = String::from("digraph works {\n  rankdir=LR;\n  node [shape=box];\n");
+    for (year, mut works) in by_year {
+        works.sort_by(|a, b| a.path.cmp(&b.path));
+        out.push_str(&format!(
+            "  subgraph {} {{\n    label={};\n",
+            dot_quote(&format!("cluster_{}", year)),
+            dot_quote(&year)
+        ));
+        for work in works {
+            out.push_str(&format!(
+                "    {} [label={}];\n",
+                dot_quote(&work.path),
+                dot_quote(&work.title)
+            ));
+        }
+        out.push_str("  }\n");
+    }
+    for (from, to) in edges {
+        out.push_str(&format!("  {} -> {};\n", dot_quote(from), dot_quote(&to)));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// - Whether `path`, with symlinks and `..` resolved, is inside
+///   `canonical_root` (itself already canonicalized).
+/// - False when `path` can't be resolved (dangling link, missing file).
+pub fn resolves_within(canonical_root: &Path, path: &Path) -> bool {
+    std::fs::canonicalize(path).is_ok_and(|resolved| resolved.starts_with(canonical_root))
+}
+
+/// - Files under `root` that nothing references: every non-markdown file not
+///   in `referenced` (root-relative paths, as from `local_asset_path`), with
+///   its size in bytes, sorted by path.
+/// - `_`-prefixed and dot-prefixed entries are support files (include
+///   snippets, `_comments.json`, `.gitkeep`) and never reported.
+pub fn find_orphans(root: &Path, referenced: &HashSet<String>) -> Vec<(String, u64)> {
+    let mut orphans: Vec<(String, u64)> = walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name.starts_with('_') || name.starts_with('.'))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            !matches!(
+                e.path().extension().and_then(|x| x.to_str()),
+                Some("md" | "markdown")
+            )
+        })
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(root).ok()?;
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if referenced.contains(&rel) {
+                return None;
+            }
+            Some((rel, e.metadata().ok()?.len()))
+        })
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+pub fn markdown_to_html(md_content: &str) -> String {
+    markdown_to_html_with(md_content, &RenderOptions::default())
+}
+
+// Raw-HTML tags that survive sanitizing: what markdown itself produces, plus
+// the layout/media tags works reasonably hand-write.
+const SAFE_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "center",
+    "code",
+    "dd",
+    "del",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "picture",
+    "pre",
+    "q",
+    "ruby",
+    "rp",
+    "rt",
+    "s",
+    "small",
+    "source",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+const VOID_TAGS: &[&str] = &["br", "hr", "img", "source"];
+// Dropped along with everything up to their closing tag.
+const UNSAFE_CONTENT_TAGS: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "noscript", "template", "textarea", "svg",
+    "math", "frame", "frameset",
+];
+// No event handlers (on*) and no style: neither appears in works, and both
+// are script/CSS-injection vectors.
+const SAFE_ATTRS: &[&str] = &[
+    "align", "alt", "class", "colspan", "dir", "height", "href", "id", "lang", "loading", "open",
+    "rowspan", "src", "srcset", "start", "title", "type", "width",
+];
+
+/// - Whether a link/image URL is safe to emit: relative, or http(s)/mailto.
+/// - Browsers ignore whitespace and control characters inside a scheme
+///   ("java\tscript:"), so those are removed before looking at it.
+pub fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            let scheme = url[..i].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// - Allowlist sanitizer for raw HTML in work bodies (a compromised work
+///   file must not run script in visitors' browsers).
+/// - Allowlisted tags are rebuilt with only allowlisted attributes and safe
+///   URLs; other tags are dropped but their text kept, except script-like
+///   ones, whose content goes too. Comments are dropped.
+/// - Stateful because pulldown splits raw HTML across events: a `<script>`
+///   in one chunk is skipped until its `</script>` in a later one.
+#[derive(Default)]
+pub struct HtmlSanitizer {
+    skipping: Option<&'static str>,
+}
+
+impl HtmlSanitizer {
+    /// - Inside a dropped element, so intervening text must go too.
+    pub fn is_skipping(&self) -> bool {
+        self.skipping.is_some()
+    }
+
+    pub fn clean(&mut self, html: &str) -> String {
+        let mut out = String::with_capacity(html.len());
+        let mut rest = html;
+        loop {
+            if let Some(name) = self.skipping {
+                // - ASCII lowercasing keeps byte offsets, so indices carry over.
+                let close = format!("</{}", name);
+                let Some(at) = rest.to_ascii_lowercase().find(&close) else {
+                    return out;
+                };
+                let after = &rest[at..];
+                rest = &after[after.find('>').map_or(after.len(), |end| end + 1)..];
+                self.skipping = None;
+            }
+            let Some(lt) = rest.find('<') else {
+                out.push_str(rest);
+                return out;
+            };
+            out.push_str(&rest[..lt]);
+            rest = &rest[lt..];
+            if rest.starts_with("<!--") {
+                rest = &rest[rest.find("-->").map_or(rest.len(), |end| end + 3)..];
+                continue;
+            }
+            let starts_tag = rest[1..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '/' || c == '!');
+            let Some(gt) = starts_tag.then(|| tag_end(rest)).flatten() else {
+                out.push_str("&lt;");
+                rest = &rest[1..];
+                continue;
+            };
+            out.push_str(&self.clean_tag(&rest[1..gt]));
+            rest = &rest[gt + 1..];
+        }
+    }
+
+    fn clean_tag(&mut self, tag: &str) -> String {
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(t) => (true, t),
+            None => (false, tag),
+        };
+        let name_end = tag
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        if let Some(unsafe_tag) = UNSAFE_CONTENT_TAGS.iter().find(|t| **t == name) {
+            if !closing && !tag.trim_end().ends_with('/') {
+                self.skipping = Some(unsafe_tag);
+            }
+            return String::new();
+        }
+        if !SAFE_TAGS.contains(&name.as_str()) {
+            return String::new();
+        }
+        if closing {
+            return format!("</{}>", name);
+        }
+        let mut out = format!("<{}", name);
+        for (attr, value) in parse_attrs(&tag[name_end..]) {
+            if !SAFE_ATTRS.contains(&attr.as_str()) {
+                continue;
+            }
+            let value = value.map(decode_basic_entities);
+            let url_ok = match (attr.as_str(), value.as_deref()) {
+                ("href" | "src", Some(v)) => is_safe_url(v),
+                ("srcset", Some(v)) => v
+                    .split(',')
+                    .all(|c| is_safe_url(c.split_whitespace().next().unwrap_or(""))),
+                _ => true,
+            };
+            if !url_ok {
+                continue;
+            }
+            match value {
+                Some(v) => out.push_str(&format!(r#" {}="{}""#, attr, html_escape(&v))),
+                None => out.push_str(&format!(" {}", attr)),
+            }
+        }
+        out.push_str(if VOID_TAGS.contains(&name.as_str()) {
+            " />"
+        } else {
+            ">"
+        });
+        out
+    }
+}
+
+// Index of the `>` closing the tag that starts `s`, skipping quoted values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// `name="v"`, `name='v'`, `name=v` and bare `name` attributes, names lowercased.
+fn parse_attrs(mut s: &str) -> Vec<(String, Option<&str>)> {
+    let mut attrs = Vec::new();
+    loop {
+        s = s.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let Some(first) = s.chars().next() else {
+            return attrs;
+        };
+        let name_end = s
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(s.len());
+        if name_end == 0 {
+            s = &s[first.len_utf8()..];
+            continue;
+        }
+        let name = s[..name_end].to_ascii_lowercase();
+        s = s[name_end..].trim_start();
+        let value = match s.strip_prefix('=') {
+            Some(v) => {
+                let v = v.trim_start();
+                match v.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let end = v[1..].find(q).map_or(v.len(), |e| e + 1);
+                        s = v.get(end + 1..).unwrap_or("");
+                        Some(&v[1..end])
+                    }
+                    _ => {
+                        let end = v.find(char::is_whitespace).unwrap_or(v.len());
+                        s = &v[end..];
+                        Some(&v[..end])
+                    }
+                }
+            }
+            None => None,
+        };
+        attrs.push((name, value));
+    }
+}
+
+// Undo the escapes attribute values are commonly written with, so they can
+// be re-escaped uniformly without doubling.
+fn decode_basic_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// - GitHub-flavored extensions works rely on: pipe tables (character
+///   stats), strikethrough, task lists (route progress) and footnotes.
+/// - Shared by every parser over a work body so images and links inside
+///   tables and footnotes are found the same way they are rendered.
+pub fn gfm_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_MATH
+}
+
+/// - Whether a markdown body has `$…$` / `$$…$$` math, so its page needs
+///   KaTeX. Code spans/blocks and `\$` never count.
+pub fn has_math(md: &str) -> bool {
+    any_event(md, &|e| {
+        matches!(e, Event::InlineMath(_) | Event::DisplayMath(_))
+    })
+}
+
+/// - Whether a markdown body has a ```` ```mermaid ```` fence, so its page
+///   needs Mermaid.
+pub fn has_mermaid(md: &str) -> bool {
+    any_event(md, &|e| {
+        matches!(e, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+            if info.split_whitespace().next() == Some("mermaid"))
+    })
+}
+
+/// - The `<summary>` of a ```` ```spoiler ```` fence: the rest of its info
+///   string (```` ```spoiler Route B ending ````), else "Spoiler". None for
+///   any other fence.
+pub fn spoiler_summary(info: &str) -> Option<&str> {
+    let rest = info.trim().strip_prefix("spoiler")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(
+        Some(rest.trim())
+            .filter(|s| !s.is_empty())
+            .unwrap_or("Spoiler"),
+    )
+}
+
+// - Whether any event of `md` matches, looking inside spoiler fences too
+//   (their body is markdown, rendered as such by markdown_to_html_with).
+fn any_event(md: &str, matches: &dyn Fn(&Event) -> bool) -> bool {
+    let mut spoiler: Option<String> = None;
+    for event in Parser::new_ext(md, gfm_options()) {
+        if let Some(body) = spoiler.as_mut() {
+            match &event {
+                Event::Text(text) => body.push_str(text),
+                Event::End(TagEnd::CodeBlock) if any_event(body, matches) => return true,
+                Event::End(TagEnd::CodeBlock) => spoiler = None,
+                _ => {}
+            }
+            continue;
+        }
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = &event {
+            if spoiler_summary(info).is_some() {
+                spoiler = Some(String::new());
+                continue;
+            }
+        }
+        if matches(&event) {
+            return true;
+        }
+    }
+    false
+}
+
+static EMOJI: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// - GitHub-style shortcode → emoji table (`config/emoji.json`), the common
+///   faces, hearts and symbols; not GitHub's full list.
+fn emoji_table() -> &'static HashMap<String, String> {
+    EMOJI.get_or_init(|| {
+        serde_json::from_str(include_str!("../config/emoji.json"))
+            .expect("Failed to parse emoji.json")
+    })
+}
+
+/// - `text` with known `:shortcode:`s swapped for their emoji; unknown ones
+///   are left as written.
+/// - A shortcode touching a letter or digit on either side isn't one, so
+///   runs like `:not:an:emoji:` or `10:30:45` stay intact even when a
+///   piece happens to be a known name.
+/// - Text only: `markdown_to_html_with` calls it outside code spans and blocks.
+pub fn expand_emoji_shortcodes(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains(':') {
+        return text.into();
+    }
+    let table = emoji_table();
+    let is_name = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || "_+-".contains(c);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+    let mut changed = false;
+    while let Some(start) = rest.find(':') {
+        out.push_str(&rest[..start]);
+        prev = rest[..start].chars().last().or(prev);
+        let after = &rest[start + 1..];
+        let name_len = after.find(|c: char| !is_name(c)).unwrap_or(after.len());
+        let name = &after[..name_len];
+        let closed = after[name_len..].starts_with(':');
+        let next = after[name_len..].chars().nth(1);
+        let bounded = !prev.is_some_and(|c| c.is_alphanumeric())
+            && !next.is_some_and(|c| c.is_alphanumeric());
+        match table.get(name).filter(|_| closed && bounded) {
+            Some(emoji) => {
+                out.push_str(emoji);
+                rest = &after[name_len + 1..];
+                prev = Some(':');
+                changed = true;
+            }
+            None => {
+                out.push(':');
+                rest = after;
+                prev = Some(':');
+            }
+        }
+    }
+    if !changed {
+        return text.into();
+    }
+    out.push_str(rest);
+    out.into()
+}
+
+/// - Byte ranges of the bare `http://`/`https://` URLs in a run of prose, as
+///   GFM autolinks them: starting at a word boundary, running to whitespace
+///   or `<`, minus trailing punctuation (`.`, `,`, `!`…) and any `)` the URL
+///   doesn't open itself, so "(see https://a.example/x)." keeps its brackets.
+pub fn bare_urls(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut urls = Vec::new();
+    let mut from = 0;
+    while let Some(found) = text[from..].find("http") {
+        let start = from + found;
+        from = start + 4;
+        let rest = &text[start..];
+        let Some(scheme) = ["https://", "http://"]
+            .into_iter()
+            .find(|s| rest.starts_with(s))
+        else {
+            continue;
+        };
+        if text[..start]
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_alphanumeric())
+        {
+            continue;
+        }
+        let mut url = &rest[..rest
+            .find(|c: char| c.is_whitespace() || c == '<')
+            .unwrap_or(rest.len())];
+        loop {
+            let trimmed =
+                url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"', '*', '_', '~']);
+            let trimmed = match trimmed.strip_suffix(')') {
+                Some(inner) if inner.matches('(').count() < trimmed.matches(')').count() => inner,
+                _ => trimmed,
+            };
+            if trimmed.len() == url.len() {
+                break;
+            }
+            url = trimmed;
+        }
+        if url.len() > scheme.len() {
+            urls.push(start..start + url.len());
+            from = start + url.len();
+        }
+    }
+    urls
+}
+
+// - `text` as events with each bare URL in it made a link (see bare_urls).
+fn push_autolinked<'a>(events: &mut Vec<Event<'a>>, text: pulldown_cmark::CowStr<'a>) {
+    let urls = bare_urls(&text);
+    if urls.is_empty() {
+        events.push(Event::Text(text));
+        return;
+    }
+    let mut last = 0;
+    for url in urls {
+        if url.start > last {
+            events.push(Event::Text(text[last..url.start].to_string().into()));
+        }
+        let href = text[url.clone()].to_string();
+        events.push(Event::Start(Tag::Link {
+            link_type: LinkType::Autolink,
+            dest_url: href.clone().into(),
+            title: "".into(),
+            id: "".into(),
+        }));
+        events.push(Event::Text(href.into()));
+        events.push(Event::End(TagEnd::Link));
+        last = url.end;
+    }
+    if last < text.len() {
+        events.push(Event::Text(text[last..].to_string().into()));
+    }
+}
+
+pub fn markdown_to_html_with(md_content: &str, options: &RenderOptions) -> String {
+    let mut html_output = String::new();
+    let mut events: Vec<Event> = Vec::new();
+    // - Whether the image currently open was wrapped in <picture> (images
+    //   don't nest, so one flag is enough).
+    let mut in_picture = false;
+    // - A fenced block with a language hint, buffered until its end so it can
+    //   be swapped for highlighted HTML (or replayed untouched).
+    let mut code: Option<(String, Vec<Event>)> = None;
+    // - Heading ids come from extract_headings so they match the TOC and
+    //   outline; the parse visits them in the same order.
+    let mut headings = extract_headings(md_content).into_iter();
+    let mut open_heading: Option<String> = None;
+    let mut in_code_block = false;
+    // - Inside a markdown link/image or a raw `<a>`: text there is already a
+    //   link (or alt text), so bare URLs in it aren't autolinked.
+    let mut in_link = false;
+    let mut in_raw_link = false;
+    let mut sanitizer = HtmlSanitizer::default();
+    // - A `$$…$$` that is its whole paragraph: the paragraph's Start is held
+    //   back so the display <div> isn't nested in a <p>.
+    let mut unwrapped_paragraph = false;
+    // - A link/image to embeddable media: (element, resolved URL, text so
+    //   far), swallowing its events until the End, where the player is pushed.
+    let mut media: Option<(&str, String, String)> = None;
+    // - Merged so a URL the parser split over several text events is seen whole.
+    for mut event in TextMergeStream::new(Parser::new_ext(md_content, gfm_options())) {
+        // - Raw HTML from the work is sanitized; the HTML this function adds
+        //   itself (picture, anchors, highlighting) is pushed as-is below.
+        match &mut event {
+            Event::Html(raw) | Event::InlineHtml(raw) => {
+                *raw = sanitizer.clean(raw).into();
+            }
+            _ if sanitizer.is_skipping() => continue,
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. })
+                if !is_safe_url(dest_url) =>
+            {
+                *dest_url = "#".into();
+            }
+            _ => {}
+        }
+        if let Some((lang, buffered)) = code.as_mut() {
+            if matches!(event, Event::End(TagEnd::CodeBlock)) {
+                let source: String = buffered
+                    .iter()
+                    .filter_map(|e| match e {
+                        Event::Text(t) => Some(t.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                // - A spoiler fence's body is markdown in its own right,
+                //   rendered inside a closed <details>.
+                if let Some(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))) =
+                    buffered.first()
+                {
+                    if let Some(summary) = spoiler_summary(info) {
+                        events.push(Event::Html(
+                            format!(
+                                "<details class=\"spoiler\"><summary>{}</summary>\n{}</details>\n",
+                                html_escape(summary),
+                                markdown_to_html_with(&source, options)
+                            )
+                            .into(),
+                        ));
+                        code = None;
+                        continue;
+                    }
+                }
+                // - Mermaid source is left for mermaid.js, which reads the
+                //   block's text: escaped here, so the browser hands it back as written.
+                if lang == "mermaid" {
+                    events.push(Event::Html(
+                        format!(r#"<pre class="mermaid">{}</pre>"#, html_escape(&source)).into(),
+                    ));
+                    code = None;
+                    continue;
+                }
+                match highlight_code(lang, &source) {
+                    Some(highlighted) => events.push(Event::Html(highlighted.into())),
+                    None => {
+                        events.append(buffered);
+                        events.push(event);
+                    }
+                }
+                code = None;
+            } else {
+                buffered.push(event);
+            }
+            continue;
+        }
+        if let Some((element, url, text)) = media.as_mut() {
+            match &event {
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                Event::End(TagEnd::Link | TagEnd::Image) => {
+                    // - The link stays inside as the fallback for browsers
+                    //   that can't play the file.
+                    let label = if text.is_empty() { url.as_str() } else { text };
+                    events.push(Event::InlineHtml(
+                        format!(
+                            r#"<{el} controls preload="metadata" src="{src}"><a href="{src}">{label}</a></{el}>"#,
+                            el = element,
+                            src = html_escape(url),
+                            label = html_escape(label)
+                        )
+                        .into(),
+                    ));
+                    media = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if let Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) = &event {
+            if let Some(element) = media_element(dest_url) {
+                let url = resolve_image_url(&options.work_dir, dest_url);
+                if options.embeds_media(&url) {
+                    media = Some((element, url, String::new()));
+                    continue;
+                }
+            }
+        }
+        if let Event::Start(Tag::Heading { id, .. }) = &mut event {
+            if let Some(heading) = headings.next() {
+                *id = Some(heading.id.clone().into());
+                open_heading = Some(heading.id);
+            }
+        }
+        // - The anchor is empty (its "#" is CSS) so it adds nothing to search
+        //   text or word counts.
+        if let (Event::End(TagEnd::Heading(_)), Some(id)) = (&event, open_heading.as_deref()) {
+            events.push(Event::InlineHtml(
+                format!(
+                    r##" <a class="heading-anchor" href="#{}" aria-label="Link to this section"></a>"##,
+                    html_escape(id)
+                )
+                .into(),
+            ));
+            open_heading = None;
+        }
+        // - Math is left for KaTeX's auto-render in the browser: TeX source
+        //   (escaped) inside \(…\) / \[…\], the only delimiters the page
+        //   configures, so a stray `$` in prose is never picked up client-side.
+        if unwrapped_paragraph {
+            unwrapped_paragraph = false;
+            if matches!(event, Event::End(TagEnd::Paragraph)) {
+                continue;
+            }
+            events.push(Event::Start(Tag::Paragraph));
+        }
+        match &event {
+            Event::InlineMath(tex) => {
+                events.push(Event::InlineHtml(
+                    format!(
+                        r#"<span class="math inline">\({}\)</span>"#,
+                        html_escape(tex)
+                    )
+                    .into(),
+                ));
+                continue;
+            }
+            Event::DisplayMath(tex) => {
+                if matches!(events.last(), Some(Event::Start(Tag::Paragraph))) {
+                    events.pop();
+                    unwrapped_paragraph = true;
+                }
+                events.push(Event::InlineHtml(
+                    format!(
+                        r#"<div class="math display">\[{}\]</div>"#,
+                        html_escape(tex)
+                    )
+                    .into(),
+                ));
+                continue;
+            }
+            _ => {}
+        }
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = &event {
+            let lang = info.split_whitespace().next().unwrap_or("").to_string();
+            if !lang.is_empty() {
+                code = Some((lang, vec![event]));
+                continue;
+            }
+        }
+        // - The html writer takes an image's alt from the events between its
+        //   Start and End, so an empty alt is an End right after the Start.
+        if options.auto_alt && matches!(event, Event::End(TagEnd::Image)) {
+            if let Some(Event::Start(Tag::Image { dest_url, .. })) = events.last() {
+                let alt = alt_from_filename(dest_url);
+                if !alt.is_empty() {
+                    events.push(Event::Text(alt.into()));
+                }
+            }
+        }
+        if let Event::Start(Tag::Image { dest_url, .. }) = &mut event {
+            *dest_url = resolve_image_url(&options.work_dir, dest_url).into();
+        }
+        // - Emoji shortcodes in prose; code blocks keep theirs (code spans
+        //   are Event::Code, never touched).
+        match &event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) if !in_code_block => {
+                if let std::borrow::Cow::Owned(expanded) = expand_emoji_shortcodes(text) {
+                    event = Event::Text(expanded.into());
+                }
+            }
+            _ => {}
+        }
+        match &event {
+            Event::Start(Tag::Link { .. } | Tag::Image { .. }) => in_link = true,
+            Event::End(TagEnd::Link | TagEnd::Image) => in_link = false,
+            Event::InlineHtml(raw) | Event::Html(raw) => {
+                let tag = raw.trim_start().to_ascii_lowercase();
+                if tag.starts_with("</a") {
+                    in_raw_link = false;
+                } else if tag.starts_with("<a ") || tag.starts_with("<a>") {
+                    in_raw_link = true;
+                }
+            }
+            _ => {}
+        }
+        match &event {
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                let sources = picture_sources(dest_url, options);
+                if !sources.is_empty() {
+                    events.push(Event::InlineHtml(format!("<picture>{}", sources).into()));
+                    in_picture = true;
+                }
+                events.push(event);
+            }
+            Event::End(TagEnd::Image) if in_picture => {
+                events.push(event);
+                events.push(Event::InlineHtml("</picture>".into()));
+                in_picture = false;
+            }
+            Event::Text(_) if !in_code_block && !in_link && !in_raw_link => {
+                if let Event::Text(text) = event {
+                    push_autolinked(&mut events, text);
+                }
+            }
+            _ => events.push(event),
+        }
+    }
+    html::push_html(&mut html_output, events.into_iter());
+    // - Over the finished HTML so markdown images and hand-written <img> tags
+    //   are treated alike.
+    let html_output = add_image_hints(&html_output, |src| {
+        let root = options.image_root.as_deref()?;
+        if !src.starts_with("/raw/") {
+            return None;
+        }
+        let file = local_asset_path("", src)?;
+        image::ImageReader::open(options.asset_file(root, &file)?)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()
+    });
+    // - The writer emits a bare <table>; the class scopes the dark table
+    //   styling in game.css to markdown tables.
+    html_output.replace("<table>", r#"<table class="md-table">"#)
+}
+
+/// - Adds `loading="lazy"` and `decoding="async"` to every `<img>` in `html`,
+///   and `width`/`height` when `dimensions` knows its `src` (entities
+///   decoded), so the page doesn't jump as images arrive.
+/// - Attributes a tag already sets are kept; size is only added when it
+///   has neither `width` nor `height`.
+pub fn add_image_hints(html: &str, dimensions: impl Fn(&str) -> Option<(u32, u32)>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(at) = rest.find("<img") {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let is_img = rest[4..].starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>');
+        let Some(gt) = is_img.then(|| tag_end(rest)).flatten() else {
+            out.push_str("<img");
+            rest = &rest[4..];
+            continue;
+        };
+        let tag = &rest[..gt];
+        let attrs = parse_attrs(&tag[4..]);
+        let has = |name: &str| attrs.iter().any(|(a, _)| a == name);
+        let mut extra = String::new();
+        if !has("loading") {
+            extra.push_str(r#" loading="lazy""#);
+        }
+        if !has("decoding") {
+            extra.push_str(r#" decoding="async""#);
+        }
+        if !has("width") && !has("height") {
+            let src = attrs
+                .iter()
+                .find(|(a, _)| a == "src")
+                .and_then(|(_, v)| v.map(decode_basic_entities));
+            if let Some((w, h)) = src.as_deref().and_then(&dimensions) {
+                extra.push_str(&format!(r#" width="{}" height="{}""#, w, h));
+            }
+        }
+        match tag.strip_suffix('/') {
+            Some(open) => out.push_str(&format!("{}{} />", open.trim_end(), extra)),
+            None => out.push_str(&format!("{}{}>", tag, extra)),
+        }
+        rest = &rest[gt + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Lexical rules for one highlighted language.
+#[derive(Clone, Copy)]
+struct CodeLang {
+    keywords: &'static str, // space-separated
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+static CODE_LANGS: OnceLock<HashMap<&'static str, CodeLang>> = OnceLock::new();
+
+fn code_langs() -> &'static HashMap<&'static str, CodeLang> {
+    CODE_LANGS.get_or_init(|| {
+        let c_like = |keywords| CodeLang {
+            keywords,
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\''],
+        };
+        let rust = CodeLang {
+            // - No ' quote: it would swallow lifetimes ('a) up to the next '.
+            quotes: &['"'],
+            ..c_like(
+                "as async await break const continue crate else enum false fn for if impl in \
+                 let loop match mod move mut pub ref return self Self static struct super \
+                 trait true type unsafe use where while",
+            )
+        };
+        let js = CodeLang {
+            quotes: &['"', '\'', '`'],
+            ..c_like(
+                "async await break case catch class const continue default delete do else \
+                 export extends false finally for function if import in instanceof let new \
+                 null return switch this throw true try typeof undefined var void while yield",
+            )
+        };
+        let c = c_like(
+            "auto bool break case char class const continue default do double else enum \
+             false float for if int long namespace nullptr return short sizeof static struct \
+             switch template true typedef unsigned void while",
+        );
+        let python = CodeLang {
+            keywords: "and as class def elif else except False finally for from if import in \
+                       is lambda None not or pass raise return True try while with yield",
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: &['"', '\''],
+        };
+        let lua = CodeLang {
+            keywords: "and break do else elseif end false for function if in local nil not or \
+                       repeat return then true until while",
+            line_comments: &["--"],
+            block_comment: Some(("--[[", "]]")),
+            quotes: &['"', '\''],
+        };
+        let shell = CodeLang {
+            keywords: "case do done echo elif else esac export fi for function if in local \
+                       return then while",
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: &['"', '\''],
+        };
+        let json = CodeLang {
+            keywords: "true false null",
+            line_comments: &[],
+            block_comment: None,
+            quotes: &['"'],
+        };
+        let mut langs = HashMap::new();
+        for (names, lang) in [
+            (&["rust", "rs"][..], rust),
+            (&["js", "javascript", "ts", "typescript"][..], js),
+            (&["c", "cpp", "c++", "h"][..], c),
+            (&["python", "py"][..], python),
+            (&["lua"][..], lua),
+            (&["sh", "bash", "shell"][..], shell),
+            (&["json"][..], json),
+        ] {
+            for name in names {
+                langs.insert(*name, lang);
+            }
+        }
+        langs
+    })
+}
+
+// Colors for highlighted tokens, picked against the game page's dark background.
+const CODE_KEYWORD: &str = "#c792ea";
+const CODE_STRING: &str = "#c3e88d";
+const CODE_NUMBER: &str = "#f78c6c";
+const CODE_COMMENT: &str = "#7f7a93";
+
+/// - A fenced code block as `<pre><code>` with inline-styled keyword, string,
+///   number and comment spans, for the languages `code_langs` knows.
+/// - None for any other hint, so the caller keeps pulldown's plain rendering.
+pub fn highlight_code(lang: &str, source: &str) -> Option<String> {
+    let rules = code_langs().get(lang.to_ascii_lowercase().as_str())?;
+    let mut out = format!(r#"<pre><code class="language-{}">"#, html_escape(lang));
+    let span = |out: &mut String, color: &str, text: &str| {
+        out.push_str(&format!(
+            r#"<span style="color:{}">{}</span>"#,
+            color,
+            html_escape(text)
+        ));
+    };
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let token_len = if let Some((open, close)) = rules
+            .block_comment
+            .filter(|(open, _)| rest.starts_with(open))
+        {
+            let len = rest[open.len()..]
+                .find(close)
+                .map_or(rest.len(), |end| open.len() + end + close.len());
+            span(&mut out, CODE_COMMENT, &rest[..len]);
+            len
+        } else if rules.line_comments.iter().any(|p| rest.starts_with(p)) {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            span(&mut out, CODE_COMMENT, &rest[..len]);
+            len
+        } else if rules.quotes.contains(&c) {
+            let mut escaped = false;
+            let len = rest
+                .char_indices()
+                .skip(1)
+                .find(|&(_, ch)| {
+                    let closes = ch == c && !escaped;
+                    escaped = ch == '\\' && !escaped;
+                    closes
+                })
+                .map_or(rest.len(), |(i, _)| i + c.len_utf8());
+            span(&mut out, CODE_STRING, &rest[..len]);
+            len
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_' || ch == '.'))
+                .unwrap_or(rest.len());
+            span(&mut out, CODE_NUMBER, &rest[..len]);
+            len
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            if rules.keywords.split_whitespace().any(|k| k == word) {
+                span(&mut out, CODE_KEYWORD, word);
+            } else {
+                out.push_str(&html_escape(word));
+            }
+            len
+        } else {
+            out.push_str(&html_escape(&rest[..c.len_utf8()]));
+            c.len_utf8()
+        };
+        rest = &rest[token_len..];
+    }
+    out.push_str("</code></pre>\n");
+    Some(out)
+}
+
+// `<source>` tags for each PICTURE_FORMATS sibling of a local image that
+// exists on disk; empty when there are none or the option is off.
+fn picture_sources(url: &str, options: &RenderOptions) -> String {
+    let Some(root) = options.asset_root.as_deref() else {
+        return String::new();
+    };
+    let Some(file) = local_asset_path(&options.work_dir, url) else {
+        return String::new();
+    };
+    let url = url.split(['?', '#']).next().unwrap_or("");
+    let (Some((url_stem, _)), Some((file_stem, ext))) =
+        (url.rsplit_once('.'), file.rsplit_once('.'))
+    else {
+        return String::new();
+    };
+    PICTURE_FORMATS
+        .iter()
+        .filter(|(sibling, _)| !ext.eq_ignore_ascii_case(sibling))
+        .filter(|(sibling, _)| {
+            options
+                .asset_file(root, &format!("{}.{}", file_stem, sibling))
+                .is_some_and(|path| path.is_file())
+        })
+        .map(|(sibling, mime)| {
+            format!(
+                r#"<source srcset="{}.{}" type="{}">"#,
+                html_escape(url_stem),
+                sibling,
+                mime
+            )
+        })
+        .collect()
+}
+
+/// - Humanized alt text from an image URL's filename: "my-cover.png" → "My Cover".
+/// - Query/fragment and extension are dropped, `-`/`_`/`.`/`%20` become
+///   spaces, and each word is capitalized. Empty when nothing readable is left.
+pub fn alt_from_filename(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let name = path.rsplit('/').next().unwrap_or("");
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    };
+    stem.replace("%20", " ")
+        .split(|c: char| c == '-' || c == '_' || c == '.' || c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// - FNV-1a 64 of `bytes` as 16 hex chars — a cache-busting fingerprint for
+///   static assets, not a cryptographic digest.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// - Weak ETag for a rendered response body: `W/"<content_hash>"`.
+/// - Weak because compression re-encodes the bytes; the content is the same.
+pub fn weak_etag(body: &[u8]) -> String {
+    format!("W/\"{}\"", content_hash(body))
+}
+
+/// - Whether an `If-None-Match` header value matches `etag`, by the weak
+///   comparison RFC 9110 prescribes for it: `W/` prefixes ignored, any entry
+///   of a comma-separated list, or `*`.
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == etag)
+}
+
+/// - A Unix timestamp as an RFC 9110 IMF-fixdate, the `Last-Modified` form:
+///   "Sun, 06 Nov 1994 08:49:37 GMT".
+pub fn http_date(secs: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    let days = (secs / 86_400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let time = secs % 86_400;
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// - The Unix timestamp of an IMF-fixdate (see `http_date`); `None` for
+///   anything else, including the obsolete RFC 850 and asctime forms, so an
+///   unparsable `If-Modified-Since` is ignored as RFC 9110 asks.
+/// - The weekday isn't checked against the date.
+pub fn parse_http_date(raw: &str) -> Option<u64> {
+    let (_weekday, rest) = raw.trim().split_once(", ")?;
+    let mut parts = rest.split(' ');
+    let (day, month, year, time, zone) = (
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+    );
+    if parts.next().is_some() || zone != "GMT" || day.len() != 2 || year.len() != 4 {
+        return None;
+    }
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+    let (day, year): (i64, i64) = (day.parse().ok()?, year.parse().ok()?);
+    let mut hms = time.split(':').map(|n| match n.len() {
+        2 => n.parse::<u64>().ok(),
+        _ => None,
+    });
+    let (h, m, s) = (hms.next()??, hms.next()??, hms.next()??);
+    if hms.next().is_some() || !(1..=31).contains(&day) || h > 23 || m > 59 || s > 60 {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86_400 + h * 3_600 + m * 60 + s)
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// - Whether a page last modified at `mtime` (Unix seconds) is unchanged
+///   for a client sending `If-Modified-Since: if_modified_since`: the date
+///   parses and is at or after `mtime`.
+pub fn not_modified_since(if_modified_since: &str, mtime: u64) -> bool {
+    parse_http_date(if_modified_since).is_some_and(|since| since >= mtime)
+}
+
+/// - "style.css" + contents → "style.<content_hash>.css".
+pub fn hashed_asset_name(name: &str, bytes: &[u8]) -> String {
+    let hash = content_hash(bytes);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}.{}.{}", stem, hash, ext),
+        None => format!("{}.{}", name, hash),
+    }
+}
+
+/// - Resolve every `{{asset:NAME}}` in a template to NAME's hashed URL from
+///   `urls` ("style.css" → "/assets/style.<hash>.css").
+/// - A name missing from the manifest falls back to its plain `/assets/NAME`,
+///   so a file the manifest skipped still loads, just without the hash.
+pub fn resolve_asset_refs(template: &str, urls: &HashMap<String, String>) -> String {
+    const OPEN: &str = "{{asset:";
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(OPEN) {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + OPEN.len()..start + len].trim();
+        out.push_str(&rest[..start]);
+        out.push_str(&asset_url(urls, name));
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// - One asset's URL, as `resolve_asset_refs` would substitute it.
+pub fn asset_url(urls: &HashMap<String, String>, name: &str) -> String {
+    urls.get(name)
+        .cloned()
+        .unwrap_or_else(|| format!("/assets/{}", name))
+}
+
+/// - CSS named colours (CSS Color Module Level 4), lowercase, space-separated.
+const CSS_NAMED_COLOURS: &str = "\
+    aliceblue antiquewhite aqua aquamarine azure beige bisque black blanchedalmond blue \
+    blueviolet brown burlywood cadetblue chartreuse chocolate coral cornflowerblue cornsilk \
+    crimson cyan darkblue darkcyan darkgoldenrod darkgray darkgreen darkgrey darkkhaki \
+    darkmagenta darkolivegreen darkorange darkorchid darkred darksalmon darkseagreen \
+    darkslateblue darkslategray darkslategrey darkturquoise darkviolet deeppink deepskyblue \
+    dimgray dimgrey dodgerblue firebrick floralwhite forestgreen fuchsia gainsboro \
+    ghostwhite gold goldenrod gray green greenyellow grey honeydew hotpink indianred indigo \
+    ivory khaki lavender lavenderblush lawngreen lemonchiffon lightblue lightcoral lightcyan \
+    lightgoldenrodyellow lightgray lightgreen lightgrey lightpink lightsalmon lightseagreen \
+    lightskyblue lightslategray lightslategrey lightsteelblue lightyellow lime limegreen \
+    linen magenta maroon mediumaquamarine mediumblue mediumorchid mediumpurple \
+    mediumseagreen mediumslateblue mediumspringgreen mediumturquoise mediumvioletred \
+    midnightblue mintcream mistyrose moccasin navajowhite navy oldlace olive olivedrab \
+    orange orangered orchid palegoldenrod palegreen paleturquoise palevioletred papayawhip \
+    peachpuff peru pink plum powderblue purple rebeccapurple red rosybrown royalblue \
+    saddlebrown salmon sandybrown seagreen seashell sienna silver skyblue slateblue \
+    slategray slategrey snow springgreen steelblue tan teal thistle tomato turquoise violet \
+    wheat white whitesmoke yellow yellowgreen";
+
+/// - Whether `value` is a colour safe to drop into a style block: `#rgb`,
+///   `#rgba`, `#rrggbb`, `#rrggbbaa` or a CSS named colour (any case).
+/// - Nothing else passes, so frontmatter can't close the declaration or the
+///   `<style>` element, and a typo can't blank the page's colours.
+pub fn is_safe_css_colour(value: &str) -> bool {
+    match value.strip_prefix('#') {
+        Some(hex) => {
+            matches!(hex.len(), 3 | 4 | 6 | 8) && hex.bytes().all(|b| b.is_ascii_hexdigit())
+        }
+        None => CSS_NAMED_COLOURS
+            .split_whitespace()
+            .any(|name| name.eq_ignore_ascii_case(value)),
+    }
+}
+
+/// - A `:root` rule overriding the site colours with a work's `theme`, for
+///   the page head; "" when no value is a safe colour (`is_safe_css_colour`).
+/// - Unsafe values are dropped one by one, the rest still apply.
+pub fn theme_css(theme: &WorkTheme) -> String {
+    let vars: String = [
+        ("bg", &theme.bg),
+        ("text", &theme.text),
+        ("accent", &theme.accent),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        let value = value.as_deref()?.trim();
+        is_safe_css_colour(value).then(|| format!("--{}:{};", name, value))
+    })
+    .collect();
+    if vars.is_empty() {
+        String::new()
+    } else {
+        format!(":root{{{}}}", vars)
+    }
+}
+
+/// - The work page (`templates/game.html`).
+/// - Plain `&str` fields are text and get HTML-escaped by the template; the
+///   `*_html` fields, `toc`, `tags_line`, `md_html`, `editor_mockup` and
+///   `more_from_creator` are markup built (and escaped) by the caller, and
+///   `md_html` is the already-sanitized work body — all inserted as-is.
+#[derive(askama::Template)]
+#[template(path = "game.html")]
+pub struct PageTemplate<'a> {
+    pub lang: &'a str, // <html lang>, "en" or "ja"
+    pub strings: &'a LangStrings,
+    pub assets: &'a HashMap<String, String>, // see `asset_url`
+    pub critical_css: &'a str,
+    pub title_display: &'a str,
+    pub year: &'a str,
+    pub year_anchor: &'a str, // the year's section on the homepage: `ParsedGame::year_dir`
+    pub tagline: &'a str,
+    pub og_image: &'a str,
+    pub twitter_card: &'a str,
+    pub canonical_url: &'a str,
+    pub home_suffix: &'a str, // query string for links back to the homepage
+    pub breadcrumb_dirs: &'a str,
+    pub hero_html: &'a str,
+    pub creator_html: &'a str,
+    pub released_html: &'a str,
+    pub reading_time_html: &'a str,
+    pub comments_html: &'a str,
+    pub link_html: &'a str,
+    pub extra_links_html: &'a str,
+    pub tags_line: &'a str,
+    pub toc: &'a str,
+    pub md_html: &'a str,
+    pub gallery_html: &'a str,
+    pub editor_mockup: &'a str,
+    pub more_from_creator: &'a str,
+    pub work_nav_html: &'a str,
+    pub work_langs_html: &'a str, // `work_langs_html`; empty without variants
+    pub math: bool,               // load KaTeX (see `has_math`)
+    pub mermaid: bool,            // load Mermaid (see `has_mermaid`)
+    pub theme_css: &'a str,       // `theme_css` of the work's theme; CSS, inserted as-is
+    pub site_title: &'a str,
+    pub site_header: &'a str, // `site_header_html`, inserted as-is
+    pub print: bool,          // `?print=1`: light print.css for all media, no nav chrome
+}
+
+impl PageTemplate<'_> {
+    fn asset(&self, name: &str) -> String {
+        asset_url(self.assets, name)
+    }
+}
 
-    // Read the value of an attribute whose '=' is at `eq`; handles quoted and
-    // bare forms. Returns the raw value.
-    let read_value = |eq: usize| -> Option<&str> {
-        let mut j = eq + 1;
-        while j < n && b[j].is_ascii_whitespace() {
-            j += 1;
-        }
-        if j >= n {
-            return None;
-        }
-        if b[j] == b'"' || b[j] == b'\'' {
-            let q = b[j];
-            let start = j + 1;
-            let end = start + lower[start..].find(q as char)?;
-            Some(&lower[start..end])
-        } else {
-            let start = j;
-            let end = start
-                + lower[start..]
-                    .find(|c: char| c.is_whitespace() || c == '>')
-                    .unwrap_or(lower.len() - start);
-            Some(&lower[start..end])
-        }
-    };
+/// - The bar linking back to the homepage at the top of work, creator and
+///   directory pages: the site logo (when configured) and title, escaped.
+pub fn site_header_html(title: &str, logo_url: Option<&str>) -> String {
+    format!(
+        r#"<header class="site-bar"><a href="/">{}<span>{}</span></a></header>"#,
+        site_logo_html(logo_url),
+        html_escape(title)
+    )
+}
 
-    for (i, _) in lower.char_indices() {
-        // markdown image: ![alt](url "optional title")
-        if b[i] == b'!' && lower[i..].starts_with("![") {
-            if let Some(rel) = lower[i..].find("](") {
-                let us = i + rel + 2;
-                if let Some(e) = lower[us..].find(')') {
-                    let url = lower[us..us + e].split_whitespace().next().unwrap_or("");
-                    if let Some(bad) = flag(url) {
-                        return Some(bad);
-                    }
-                }
-            }
-        }
+/// - The site logo `<img>` (decorative: the title sits beside it), or
+///   nothing when none is configured.
+pub fn site_logo_html(logo_url: Option<&str>) -> String {
+    logo_url
+        .map(|url| {
+            format!(
+                r#"<img class="site-logo" src="{}" alt="">"#,
+                html_escape(url)
+            )
+        })
+        .unwrap_or_default()
+}
 
-        // src= / srcset= attribute, at an attribute-name boundary (so
-        // "described" and "data-src" don't match).
-        let boundary = i == 0 || matches!(b[i - 1], b' ' | b'\t' | b'\n' | b'\r' | b'<' | b'/');
-        if boundary && lower[i..].starts_with("src") {
-            let mut j = i + 3;
-            if lower[j..].starts_with("set") {
-                j += 3;
-            }
-            while j < n && b[j].is_ascii_whitespace() {
-                j += 1;
-            }
-            if j < n && b[j] == b'=' {
-                if let Some(val) = read_value(j) {
-                    // srcset is a comma-separated "url descriptor" list.
-                    for cand in val.split(',') {
-                        let url = cand.split_whitespace().next().unwrap_or("");
-                        if let Some(bad) = flag(url) {
-                            return Some(bad);
-                        }
-                    }
-                }
-            }
-        }
+/// - The 404 page: `what` is the thing asked for, e.g. "2024/Title.md" for
+///   a `/works/{year}/{title}` that isn't in the catalog.
+#[derive(askama::Template)]
+#[template(path = "not_found.html")]
+pub struct NotFoundTemplate<'a> {
+    pub what: &'a str,
+}
+
+/// - The 404 page for a missing `/works/{year}/{title}`: `template` (the
+///   deployment's `public/404_work.html`, when it has one) with its
+///   `{{year}}` and `{{title}}` placeholders filled in, HTML-escaped; without
+///   one, the built-in `NotFoundTemplate` naming `missing_work`.
+/// - `year` is empty when the URL didn't name one (`/works/{title}`, a
+///   permalink without `:year`).
+pub fn work_not_found_html(template: Option<&str>, year: &str, title: &str) -> String {
+    match template {
+        Some(template) => template
+            .replace("{{year}}", &html_escape(year))
+            .replace("{{title}}", &html_escape(title)),
+        None => askama::Template::render(&NotFoundTemplate {
+            what: &missing_work(year, title),
+        })
+        .unwrap_or_default(),
     }
+}
 
-    None
+/// - The file a missing work would be, as 404s name it: "2024/Title.md", or
+///   the URL's "works/Title" when there's no year.
+pub fn missing_work(year: &str, title: &str) -> String {
+    match year {
+        "" => format!("works/{}", title),
+        year => format!("{}/{}.md", year, title),
+    }
 }
 
-pub fn markdown_to_html(md_content: &str) -> String {
-    let mut html_output = String::new();
-    let parser = Parser::new(md_content);
-    html::push_html(&mut html_output, parser);
-    html_output
+/// - The HTML page for any other error status (see `app::AppError`).
+#[derive(askama::Template)]
+#[template(path = "error.html")]
+pub struct ErrorTemplate<'a> {
+    pub heading: &'a str, // status line, e.g. "400 Bad Request"
+    pub message: &'a str,
+}
+
+/// - A plaintext work body as HTML: escaped verbatim inside a `<pre>`, so
+///   whitespace is kept and markup shows as text rather than rendering.
+pub fn plaintext_to_html(text: &str) -> String {
+    format!(r#"<pre class="plaintext">{}</pre>"#, html_escape(text))
 }
 
 pub fn html_escape(s: &str) -> String {
@@ -410,6 +3659,52 @@ pub fn encode_path(path: &str) -> String {
     out
 }
 
+/// - Rewrite a page's root-relative `href="/…"` / `src="/…"` URLs to be
+///   relative to a page `depth` directories below the site root, for the
+///   static export: `/style.css` at depth 3 → `../../../style.css`.
+/// - Page routes (`/`, `/works/…`, `/creator/…`) become their directory
+///   (`…/works/2024/Title/`), which static hosts serve as its `index.html`;
+///   query and fragment are kept. Protocol-relative `//host` URLs are left.
+/// - Also returns the root-relative paths found (query and fragment cut,
+///   still percent-encoded), for the export to follow.
+pub fn relativize_root_links(html: &str, depth: usize) -> (String, Vec<String>) {
+    let prefix = if depth == 0 {
+        "./".to_string()
+    } else {
+        "../".repeat(depth)
+    };
+    let mut out = String::with_capacity(html.len());
+    let mut found = Vec::new();
+    let mut rest = html;
+    while let Some(i) = [" href=\"/", " src=\"/"]
+        .iter()
+        .filter_map(|attr| rest.find(attr).map(|i| i + attr.len() - 1))
+        .min()
+    {
+        let (before, url) = rest.split_at(i);
+        out.push_str(before);
+        let end = url.find('"').unwrap_or(url.len());
+        let (value, after) = url.split_at(end);
+        rest = after;
+        if value.starts_with("//") {
+            out.push_str(value);
+            continue;
+        }
+        let split = value.find(['?', '#']).unwrap_or(value.len());
+        let (path, tail) = value.split_at(split);
+        found.push(path.to_string());
+        out.push_str(&prefix);
+        out.push_str(&path[1..]);
+        let is_page = path.starts_with("/works/") || path.starts_with("/creator/");
+        if is_page && !path.ends_with('/') {
+            out.push('/');
+        }
+        out.push_str(tail);
+    }
+    out.push_str(rest);
+    (out, found)
+}
+
 /// - Build a URL query string from (key, value) pairs.
 /// - Empty values are filtered out.
 /// - Returns "" for no non-empty pairs, or "?k1=v1&k2=v2".
@@ -426,30 +3721,117 @@ pub fn build_query(params: &[(&str, &str)]) -> String {
     }
 }
 
-/// - Build an XML sitemap listing the home page and every game URL.
+/// One `<url>` in the sitemap.
+pub struct SitemapEntry {
+    pub path: String, // site path, e.g. "/works/YYYY/title" or "/creator/name"
+    pub lastmod: Option<String>, // ISO date "YYYY-MM-DD" (source file mtime), if known
+}
+
+/// - Build an XML sitemap listing the home page and every entry.
 /// - `base_url` is scheme+host without a trailing slash (e.g. https://example.com).
-/// - `game_paths` are canonical paths ("/works/YYYY/title"); each segment is
-///   percent-encoded and paths are sorted for deterministic output.
-/// - No `<lastmod>`: our only date is the release date, which never updates when a
-///   page is edited, so it would misreport freshness (see docs/seo.md).
-pub fn build_sitemap(base_url: &str, game_paths: &[String]) -> String {
+/// - Entry paths are percent-encoded per segment and sorted for deterministic
+///   output; `<lastmod>` is emitted only for entries that carry a date.
+pub fn build_sitemap(base_url: &str, entries: &[SitemapEntry]) -> String {
     let base = base_url.trim_end_matches('/');
-    let mut sorted: Vec<&String> = game_paths.iter().collect();
-    sorted.sort();
+    let mut sorted: Vec<&SitemapEntry> = entries.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
 
     let mut out = String::from(
         "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
          <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
     );
     out.push_str(&format!("  <url><loc>{}/</loc></url>\n", html_escape(base)));
-    for path in sorted {
-        let loc = format!("{}{}", base, encode_path(path));
-        out.push_str(&format!("  <url><loc>{}</loc></url>\n", html_escape(&loc)));
+    for entry in sorted {
+        let loc = format!("{}{}", base, encode_path(&entry.path));
+        match &entry.lastmod {
+            Some(date) => out.push_str(&format!(
+                "  <url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+                html_escape(&loc),
+                html_escape(date)
+            )),
+            None => out.push_str(&format!("  <url><loc>{}</loc></url>\n", html_escape(&loc))),
+        }
     }
     out.push_str("</urlset>\n");
     out
 }
 
+/// - Build robots.txt for the site.
+/// - Normally crawlers may index pages and works but are kept out of the API,
+///   raw sources and draft preview URLs, and pointed at the sitemap.
+/// - With `disallow_all` (staging) everything is off limits and the sitemap is
+///   not advertised.
+pub fn robots_txt(base_url: &str, disallow_all: bool) -> String {
+    if disallow_all {
+        return "User-agent: *\nDisallow: /\n".to_string();
+    }
+    format!(
+        "User-agent: *\n\
+         Disallow: /api/\n\
+         Disallow: /raw/\n\
+         Disallow: /*preview=\n\
+         Allow: /works/\n\
+         Allow: /\n\
+         \n\
+         Sitemap: {}/sitemap.xml\n",
+        base_url.trim_end_matches('/')
+    )
+}
+
+/// First path segments owned by built-in routes; a PERMALINK_PATTERN may not
+/// start with one of these.
+const RESERVED_PREFIXES: &[&str] = &["works", "api", "thumb", "creator", "raw"];
+
+/// - Axum route for a `PERMALINK_PATTERN` such as `/:year/:month/:slug`:
+///   `:name` segments become `{name}` captures.
+/// - None when the pattern is unusable: it must start with '/', contain
+///   `:slug`, use only `:year`/`:month`/`:day`/`:slug` as whole segments, and
+///   not start with a built-in prefix (it would shadow or collide with it).
+pub fn permalink_route(pattern: &str) -> Option<String> {
+    let rest = pattern.strip_prefix('/')?;
+    let segments: Vec<&str> = rest.split('/').collect();
+    if segments.iter().any(|s| s.is_empty()) || !segments.contains(&":slug") {
+        return None;
+    }
+    if RESERVED_PREFIXES.contains(&segments[0]) {
+        return None;
+    }
+    let mut out = String::new();
+    for seg in segments {
+        out.push('/');
+        match seg.strip_prefix(':') {
+            Some(tok @ ("year" | "month" | "day" | "slug")) => {
+                out.push_str(&format!("{{{}}}", tok));
+            }
+            Some(_) => return None,
+            None if seg.contains(['{', '}', '*']) => return None,
+            None => out.push_str(seg),
+        }
+    }
+    Some(out)
+}
+
+/// - A work's permalink under `pattern` (see `permalink_route`), unencoded.
+/// - `:year`/`:month`/`:day` come from `released`; `:slug` is the title (file stem).
+/// - None when the pattern needs a date part the work doesn't have (undated or
+///   partial `released`) — that work stays reachable at `/works/…` only.
+pub fn permalink_for(pattern: &str, released: Option<&str>, title: &str) -> Option<String> {
+    let iso = released.and_then(released_to_iso);
+    let part = |range: std::ops::Range<usize>| iso.as_deref().and_then(|d| d.get(range));
+    let mut out = String::new();
+    for seg in pattern.strip_prefix('/')?.split('/') {
+        out.push('/');
+        match seg {
+            ":year" => out.push_str(part(0..4)?),
+            ":month" => out.push_str(part(5..7)?),
+            ":day" => out.push_str(part(8..10)?),
+            ":slug" => out.push_str(title),
+            literal => out.push_str(literal),
+        }
+    }
+    Some(out)
+}
+
 /// - Convert a `YYYY/MM/DD` (or `YYYY/MM`, `YYYY`) date to ISO `YYYY-MM-DD`, zero-padded.
 /// - Returns None for empty, RELEASED_UNKNOWN, or malformed input.
 pub fn released_to_iso(date: &str) -> Option<String> {
@@ -526,18 +3908,153 @@ pub fn feed_date(meta: &GameMeta) -> Option<String> {
         .or_else(|| meta.released.as_deref().and_then(released_to_iso))
 }
 
-/// One entry in the Atom feed.
+/// - Today's UTC date as ISO `YYYY-MM-DD`, for comparing against frontmatter dates.
+pub fn today_iso() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    iso_date_from_unix(secs)
+}
+
+/// - Civil (proleptic Gregorian) date for a Unix timestamp, as `YYYY-MM-DD`.
+/// - Howard Hinnant's days→civil algorithm; avoids pulling in a date crate
+///   for the handful of places that need "today".
+pub fn iso_date_from_unix(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// - (year, month, day) for days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// - The inverse of civil_from_days (Hinnant's days_from_civil).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// - Parse an external `{work path: comment count}` JSON object.
+/// - Keys are normalized to canonical work paths, so "/works/2024/Title",
+///   "works/2024/Title" and "works/2024/Title.md" all mean the same work.
+/// - `None` when the payload isn't such an object; zero counts are dropped
+///   (absent and 0 are displayed the same way: not at all).
+pub fn parse_comment_counts(raw: &str) -> Option<HashMap<String, u64>> {
+    let parsed: HashMap<String, u64> = serde_json::from_str(raw).ok()?;
+    Some(
+        parsed
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(key, n)| (canonical_work_key(&key), n))
+            .collect(),
+    )
+}
+
+/// - Parse a featured-works TOML file (`works = ["works/2024/Title.md", …]`),
+///   keeping order; keys normalized like `parse_comment_counts`, duplicates
+///   dropped. An empty file (or one without `works`) features nothing.
+/// - `None` when the payload isn't TOML, or `works` isn't a list of strings.
+pub fn parse_featured(raw: &str) -> Option<Vec<String>> {
+    #[derive(Deserialize)]
+    struct FeaturedFile {
+        #[serde(default)]
+        works: Vec<String>,
+    }
+    let listed = basic_toml::from_str::<FeaturedFile>(raw).ok()?.works;
+    let mut seen = HashSet::new();
+    Some(
+        listed
+            .iter()
+            .map(|key| canonical_work_key(key))
+            .filter(|key| seen.insert(key.clone()))
+            .collect(),
+    )
+}
+
+// "works/2024/Title.md", "/works/2024/Title" → "/works/2024/Title"
+fn canonical_work_key(key: &str) -> String {
+    let key = key.trim().trim_start_matches('/');
+    let key = strip_work_ext(key).map_or(key, |(stem, _)| stem);
+    format!("/{}", key)
+}
+
+/// - Whether a work is an unpublished draft (`draft: true`).
+/// - Drafts are left out of the tree, creator and tag indexes, popular and
+///   featured lists; their page and JSON answer 404 unless previewed.
+pub fn is_draft(meta: &GameMeta) -> bool {
+    meta.draft == Some(true)
+}
+
+/// - Whether a `?preview=` token unlocks drafts: it must equal the configured
+///   secret (`Config::draft_preview_token`).
+/// - No secret, or an empty one, means drafts can't be previewed at all —
+///   an empty `?preview=` must never match an unset variable.
+pub fn draft_preview_ok(supplied: Option<&str>, secret: Option<&str>) -> bool {
+    match (supplied, secret) {
+        (Some(supplied), Some(secret)) => !secret.is_empty() && supplied == secret,
+        _ => false,
+    }
+}
+
+/// - Whether a work's page should carry `X-Robots-Tag: noindex, nofollow`.
+/// - True for `draft: true` (seen only when previewed) and for works scheduled
+///   ahead (`date_added` after `today`, both ISO) — reachable by a shared URL,
+///   but not yet for crawlers.
+pub fn is_noindex(meta: &GameMeta, today: &str) -> bool {
+    is_draft(meta)
+        || meta
+            .date_added
+            .as_deref()
+            .and_then(released_to_iso)
+            .is_some_and(|d| d.as_str() > today)
+}
+
+/// One entry in the Atom and JSON feeds.
 pub struct FeedEntry {
     pub title: String,
-    pub path: String,    // canonical path "/works/YYYY/title"
-    pub summary: String, // tagline (may be empty)
+    pub path: String,              // canonical path "/works/YYYY/title"
+    pub summary: String,           // tagline (may be empty)
+    pub excerpt: String, // plain-text opening of the body, `first_paragraph` (may be empty)
     pub updated: String, // ISO date "YYYY-MM-DD"
+    pub thumbnail: Option<String>, // card thumbnail: "/thumb/UUID/card" or an absolute URL
+    pub reading_minutes: u32, // ParsedGame::reading_minutes
+}
+
+/// - Characters of a work's opening paragraph kept as its feed excerpt.
+pub const FEED_EXCERPT_CHARS: usize = 300;
+
+// - A feed thumbnail as an absolute URL, plus its MIME type when known:
+//   proxied thumbnails are always encoded as JPEG; passthrough URLs could be
+//   anything, so they go without a type.
+fn feed_image(base: &str, thumb: &str) -> (String, Option<&'static str>) {
+    if thumb.starts_with('/') {
+        (format!("{}{}", base, thumb), Some("image/jpeg"))
+    } else {
+        (thumb.to_string(), None)
+    }
 }
 
 /// - Build an Atom 1.0 feed from entries already sorted newest-first.
 /// - `base_url` is scheme+host without a trailing slash.
 /// - Links are absolute (base + percent-encoded path); dates become RFC-3339
 ///   `{updated}T00:00:00Z`. Feed-level `<updated>` is the newest entry's date.
+/// - A thumbnail becomes a `rel="enclosure"` link, absolute like the rest.
 pub fn build_atom_feed(base_url: &str, entries: &[FeedEntry]) -> String {
     let base = base_url.trim_end_matches('/');
     let feed_updated = entries
@@ -573,6 +4090,17 @@ pub fn build_atom_feed(base_url: &str, entries: &[FeedEntry]) -> String {
             "    <updated>{}T00:00:00Z</updated>\n",
             html_escape(&entry.updated)
         ));
+        if let Some(thumb) = entry.thumbnail.as_deref() {
+            let (href, kind) = feed_image(base, thumb);
+            let kind = kind
+                .map(|k| format!(r#" type="{}""#, k))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "    <link rel=\"enclosure\"{} href=\"{}\"/>\n",
+                kind,
+                html_escape(&href)
+            ));
+        }
         if !entry.summary.is_empty() {
             out.push_str(&format!(
                 "    <summary>{}</summary>\n",
@@ -585,6 +4113,52 @@ pub fn build_atom_feed(base_url: &str, entries: &[FeedEntry]) -> String {
     out
 }
 
+/// - Build a JSON Feed 1.1 from entries already sorted newest-first — the
+///   same entries as `build_atom_feed`, so the two list the same works.
+/// - `base_url` is scheme+host without a trailing slash; ids and urls are
+///   absolute, `date_published` is RFC-3339 `{updated}T00:00:00Z`.
+/// - `content_text` is the excerpt, falling back to the tagline and then the
+///   title (the spec requires some content); the tagline is also `summary`.
+pub fn build_json_feed(base_url: &str, title: &str, entries: &[FeedEntry]) -> serde_json::Value {
+    let base = base_url.trim_end_matches('/');
+    let items: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| {
+            let url = format!("{}{}", base, encode_path(&entry.path));
+            let content = [&entry.excerpt, &entry.summary, &entry.title]
+                .into_iter()
+                .find(|s| !s.is_empty())
+                .cloned()
+                .unwrap_or_default();
+            let mut item = serde_json::json!({
+                "id": url,
+                "url": url,
+                "title": entry.title,
+                "content_text": content,
+                "date_published": format!("{}T00:00:00Z", entry.updated),
+            });
+            if !entry.summary.is_empty() {
+                item["summary"] = entry.summary.clone().into();
+            }
+            if let Some(thumb) = entry.thumbnail.as_deref() {
+                item["image"] = feed_image(base, thumb).0.into();
+            }
+            // - JSON Feed's extension convention: an `_`-prefixed object.
+            item["_lightvn"] = serde_json::json!({
+                "reading_time_minutes": entry.reading_minutes,
+            });
+            item
+        })
+        .collect();
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": title,
+        "home_page_url": format!("{}/", base),
+        "feed_url": format!("{}/feed.json", base),
+        "items": items,
+    })
+}
+
 /// - Compute the breadcrumb-back suffix and the forward-link suffix for a game
 ///   page.
 /// - Both propagate `lang`.
@@ -607,19 +4181,315 @@ pub fn game_page_suffixes(
     (back, fwd)
 }
 
-/// - A parsed markdown game file.
+/// - Breadcrumb steps for the directories between a nested work's year and
+///   its file: "Saga/Part 1/Chapter" → links for "Saga" and "Part 1".
+/// - Each links to the archive searched for that directory's path ("Saga/Part 1/"
+///   matches every work titled below it) and scrolled to `year`; `back_suffix`
+///   is the query the other breadcrumb links carry (`game_page_suffixes`).
+/// - Segments display as named on disk, like the title.
+pub fn breadcrumb_dirs_html(year: &str, title: &str, back_suffix: &str) -> String {
+    let Some((dirs, _)) = title.rsplit_once('/') else {
+        return String::new();
+    };
+    let joiner = if back_suffix.is_empty() { '?' } else { '&' };
+    let mut prefix = String::new();
+    let mut out = String::new();
+    for dir in dirs.split('/') {
+        prefix.push_str(dir);
+        prefix.push('/');
+        let href = format!(
+            "/{}{}search={}#{}",
+            back_suffix,
+            joiner,
+            encode_path(&prefix),
+            encode_path(year)
+        );
+        out.push_str(&format!(
+            r#"<span>/</span><a href="{}">{}</a>"#,
+            html_escape(&href),
+            html_escape(dir)
+        ));
+    }
+    out
+}
+
+/// - A parsed game file (markdown, or plaintext from a .txt).
 /// - Sole source of truth for game data in-memory.
 #[derive(Clone, Debug)]
 pub struct ParsedGame {
-    pub year: String,  // directory name
-    pub title: String, // file stem, no .md
-    pub path: String,  // "/works/YYYY/title", no .md
+    pub collection: Option<String>, // the `--collection` it's mounted under, if any
+    pub year: String,               // directory name
+    pub title: String,              // file stem, no .md; "Series/Chapter" when nested
+    pub path: String, // "/works/YYYY/title" ("/works/games/YYYY/title" in a collection), no .md
     pub meta: GameMeta,
     pub body_html: String, // pre-rendered markdown
     pub images: Vec<ImageInfo>,
     pub thumbnail: Option<String>, // card-size URL: "/thumb/UUID/card" or passthrough
     pub thumbnail_ribbon: Option<String>, // ribbon-size URL: "/thumb/UUID/ribbon" or passthrough
     pub thumbnail_composite: bool,
+    pub thumbnail_alt: Option<String>, // the thumbnail image's alt text (ImageInfo::alt)
+    pub links: Vec<String>,            // every link/image target in the body, as written
+    pub plaintext: bool,               // sourced from a .txt file; body_html is a <pre>
+    pub ext: &'static str,             // source file extension, one of WORK_EXTENSIONS
+    pub headings: Vec<Heading>,        // ids in body_html, for the table of contents and outline
+    pub math: bool,                    // body has TeX math; the page loads KaTeX
+    pub mermaid: bool,                 // body has a mermaid fence; the page loads Mermaid
+    pub reading_minutes: u32,          // `reading_time_minutes` of `word_count`
+    pub word_count: usize,             // `markdown_word_count` of the body (`word_count` for .txt)
+    pub char_count: usize,             // characters in the body, frontmatter excluded
+    pub excerpt: Option<String>,       // `work_excerpt`, for tree cards
+    pub lang: Option<String>,          // source's language suffix: "ja" for "Title.ja.md"
+    pub langs: Vec<String>, // every language the work has a variant in, sorted; empty for one unsuffixed file
+    pub mtime: Option<SystemTime>, // the source file's, when indexed; pages' Last-Modified
+}
+
+impl ParsedGame {
+    /// - The source file's name under works/YYYY/: "Title.md", "Title.markdown"
+    ///   or "Title.txt" ("Series/Chapter 1.md" for a nested work), with the
+    ///   language suffix of a variant ("Title.ja.md").
+    pub fn file_name(&self) -> String {
+        match &self.lang {
+            Some(lang) => format!("{}.{}.{}", self.title, lang, self.ext),
+            None => format!("{}.{}", self.title, self.ext),
+        }
+    }
+
+    /// - The work's name in the tree: `file_name` without a language suffix,
+    ///   so a work's variants share one node.
+    pub fn node_name(&self) -> String {
+        format!("{}.{}", self.title, self.ext)
+    }
+
+    /// - The work's year directory under `/works/` (see `year_dir`).
+    pub fn year_dir(&self) -> String {
+        year_dir(self.collection.as_deref(), &self.year)
+    }
+
+    /// - The directory holding the source file, relative to works/: "2024",
+    ///   or "2024/Series" for a nested work ("games/2024" in a collection).
+    pub fn dir(&self) -> String {
+        match self.title.rsplit_once('/') {
+            Some((sub, _)) => format!("{}/{}", self.year_dir(), sub),
+            None => self.year_dir(),
+        }
+    }
+
+    /// - The title's last segment: "Chapter 1" for "Series/Chapter 1".
+    pub fn name(&self) -> &str {
+        self.title.rsplit('/').next().unwrap_or(&self.title)
+    }
+}
+
+/// - A year's directory as work URLs name it: "2024", or "games/2024" for a
+///   work in the `games` collection.
+pub fn year_dir(collection: Option<&str>, year: &str) -> String {
+    match collection {
+        Some(collection) => format!("{}/{}", collection, year),
+        None => year.to_string(),
+    }
+}
+
+/// - A title folded for loose URL matching: lowercased, with spaces as
+///   hyphens, so "My Game", "my game" and "my-game" all fold to "my-game".
+pub fn fold_title(title: &str) -> String {
+    title.trim().to_lowercase().replace(' ', "-")
+}
+
+/// - The work in `year` whose title folds (`fold_title`) like `title`, for a
+///   URL that names it loosely. None when there's no such work, or more than
+///   one (the caller can't pick).
+/// - `year` and `title` are the URL's segments, so in a collection `year` is
+///   the collection's name and `title` starts with the year.
+/// - Drafts never match; a preview needs the exact URL.
+pub fn find_loose_title<'a>(
+    games: &'a HashMap<String, ParsedGame>,
+    year: &str,
+    title: &str,
+) -> Option<&'a ParsedGame> {
+    let rel = format!("{}/{}", year, title);
+    let loose_match = |g: &ParsedGame| {
+        rel.strip_prefix(&g.year_dir())
+            .and_then(|t| t.strip_prefix('/'))
+            .is_some_and(|t| fold_title(t) == fold_title(&g.title))
+    };
+    let mut matches = games
+        .values()
+        .filter(|g| !is_draft(&g.meta) && loose_match(g));
+    let game = matches.next()?;
+    matches.next().is_none().then_some(game)
+}
+
+/// - The works just before and after `current` among its siblings — works in
+///   the same directory (`ParsedGame::dir`), ordered by file name as in the
+///   tree. None at either end.
+/// - Drafts aren't siblings (they're not in the tree), except `current`
+///   itself when it's a previewed draft.
+pub fn sibling_works<'a>(
+    games: &'a HashMap<String, ParsedGame>,
+    current: &ParsedGame,
+) -> (Option<&'a ParsedGame>, Option<&'a ParsedGame>) {
+    let dir = current.dir();
+    let mut siblings: Vec<&ParsedGame> = games
+        .values()
+        .filter(|g| g.path == current.path || (!is_draft(&g.meta) && g.dir() == dir))
+        .collect();
+    siblings.sort_by_cached_key(|g| g.file_name());
+    let Some(pos) = siblings.iter().position(|g| g.path == current.path) else {
+        return (None, None);
+    };
+    let prev = pos.checked_sub(1).map(|i| siblings[i]);
+    (prev, siblings.get(pos + 1).copied())
+}
+
+/// - Extensions a work's source file may have, in order of preference: when
+///   two files share a URL ("Title.md" and "Title.markdown"), the first wins.
+/// - `.txt` works are plaintext; the others are markdown.
+pub const WORK_EXTENSIONS: [&str; 3] = ["md", "markdown", "txt"];
+
+/// - Split a work file name into its stem and extension (one of
+///   `WORK_EXTENSIONS`): "Title.markdown" → ("Title", "markdown").
+/// - None for any other file.
+pub fn strip_work_ext(name: &str) -> Option<(&str, &'static str)> {
+    WORK_EXTENSIONS.iter().find_map(|&ext| {
+        let stem = name.strip_suffix(ext)?.strip_suffix('.')?;
+        Some((stem, ext))
+    })
+}
+
+/// - Year and title of a work file from its path relative to works/:
+///   "2024/Title.md" → ("2024", "Title"); nested directories stay part of
+///   the title ("2024/Series/Chapter 1.md" → ("2024", "Series/Chapter 1")).
+/// - None for anything that isn't a work file (`WORK_EXTENSIONS`) inside a
+///   year directory.
+pub fn split_work_path(rel_path: &str) -> Option<(String, String)> {
+    let (year, rest) = rel_path.split_once('/')?;
+    let (title, _) = strip_work_ext(rest)?;
+    if year.is_empty() || title.is_empty() || title.ends_with('/') {
+        return None;
+    }
+    Some((year.to_string(), title.to_string()))
+}
+
+/// - ISO 639-1 codes recognised as a work file's language suffix
+///   ("Title.ja.md"). A closed list, so a title that merely has a dot in it
+///   ("Light.vn") stays whole.
+pub const WORK_LANGS: &[&str] = &[
+    "ar", "cs", "da", "de", "el", "en", "es", "fi", "fr", "he", "hi", "hu", "id", "it", "ja", "ko",
+    "ms", "nl", "no", "pl", "pt", "ro", "ru", "sv", "th", "tl", "tr", "uk", "vi", "zh",
+];
+
+/// - A work title (as from `split_work_path`) split into its base title and
+///   language suffix: "Chapter 1.ja" → ("Chapter 1", Some("ja")). Only codes
+///   in WORK_LANGS count; anything else is (title, None).
+pub fn split_lang_suffix(title: &str) -> (&str, Option<&str>) {
+    match title.rsplit_once('.') {
+        Some((base, lang))
+            if !base.is_empty() && !base.ends_with('/') && WORK_LANGS.contains(&lang) =>
+        {
+            (base, Some(lang))
+        }
+        _ => (title, None),
+    }
+}
+
+/// - Which of a work's language variants (`available`, see ParsedGame::langs)
+///   to serve: `lang_param` if there's a variant in it, else the first
+///   Accept-Language range (by q, then order) that has one, else `base`,
+///   else the first available.
+/// - Ranges match on their primary subtag ("ja-JP" → "ja"); `q=0` excludes.
+/// - None when the work has no variants.
+pub fn pick_work_lang<'a>(
+    available: &'a [String],
+    lang_param: Option<&str>,
+    accept_language: Option<&str>,
+    base: &str,
+) -> Option<&'a str> {
+    let find = |lang: &str| {
+        available
+            .iter()
+            .find(|l| l.eq_ignore_ascii_case(lang))
+            .map(String::as_str)
+    };
+    let mut ranges: Vec<(f32, &str)> = accept_language
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+            let primary = tag.split('-').next().unwrap_or(tag);
+            (q > 0.0 && !primary.is_empty()).then_some((q, primary))
+        })
+        .collect();
+    // - Stable, so equal q keep header order.
+    ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+    lang_param
+        .and_then(find)
+        .or_else(|| ranges.iter().find_map(|(_, lang)| find(lang)))
+        .or_else(|| find(base))
+        .or_else(|| available.first().map(String::as_str))
+}
+
+/// - The language selector on a work page: a link per variant (`?lang=`),
+///   the one shown (`current`) marked instead. Empty with fewer than two.
+pub fn work_langs_html(langs: &[String], current: &str) -> String {
+    if langs.len() < 2 {
+        return String::new();
+    }
+    let items: String = langs
+        .iter()
+        .map(|lang| {
+            let code = html_escape(&lang.to_uppercase());
+            if lang == current {
+                format!(r#"<span aria-current="true">{}</span>"#, code)
+            } else {
+                format!(
+                    r#"<a href="?lang={}" hreflang="{}">{}</a>"#,
+                    html_escape(lang),
+                    html_escape(lang),
+                    code
+                )
+            }
+        })
+        .collect();
+    format!(
+        r#"<nav class="work-langs" aria-label="Language">{}</nav>"#,
+        items
+    )
+}
+
+/// - Whether a work title (as from `split_work_path`) names a directory's
+///   `index.md` landing page ("index", "Series/index") rather than a work.
+pub fn is_dir_index(title: &str) -> bool {
+    title == "index" || title.ends_with("/index")
+}
+
+/// - The direct children of a works directory (`dir` relative to works/,
+///   "2023" or "2023/Series"): its subdirectory names and its published
+///   works, each sorted by name. Drafts are left out, and a subdirectory
+///   holding only drafts isn't listed.
+pub fn dir_children<'a>(
+    games: &'a HashMap<String, ParsedGame>,
+    dir: &str,
+) -> (Vec<String>, Vec<&'a ParsedGame>) {
+    let mut subdirs = BTreeSet::new();
+    let mut works = Vec::new();
+    for game in games.values().filter(|g| !is_draft(&g.meta)) {
+        let rel = format!("{}/{}", game.year_dir(), game.title);
+        let Some(rest) = rel.strip_prefix(dir).and_then(|r| r.strip_prefix('/')) else {
+            continue;
+        };
+        match rest.split_once('/') {
+            Some((sub, _)) => {
+                subdirs.insert(sub.to_string());
+            }
+            None => works.push(game),
+        }
+    }
+    works.sort_by(|a, b| a.title.cmp(&b.title));
+    (subdirs.into_iter().collect(), works)
 }
 
 /// - Size variant for the thumbnail proxy.
@@ -673,10 +4543,11 @@ pub fn split_creators(creator: &str) -> Vec<String> {
 /// - Build creator → paths index.
 /// - Paths are sorted by release date descending (unknown last).
 /// - Creators with commas are split into separate entries.
+/// - Drafts are skipped.
 pub fn build_creator_paths(games: &HashMap<String, ParsedGame>) -> HashMap<String, Vec<String>> {
     let mut index: HashMap<String, Vec<String>> = HashMap::new();
 
-    for game in games.values() {
+    for game in games.values().filter(|g| !is_draft(&g.meta)) {
         let creator = match game.meta.creator.as_deref() {
             Some(c) if !c.is_empty() => c,
             _ => continue,
@@ -901,10 +4772,75 @@ pub struct TagBarEntry {
     pub count: usize,
 }
 
+/// - Whether a work carries `tag`, compared case-insensitively.
+pub fn has_tag(meta: &GameMeta, tag: &str) -> bool {
+    let tag = tag.to_lowercase();
+    meta.tags
+        .as_deref()
+        .unwrap_or(&[])
+        .iter()
+        .any(|t| t.to_lowercase() == tag)
+}
+
+/// - Works per tag, keyed by the lowercased tag (what `has_tag` matches on),
+///   for `/api/tags`.
+/// - Unlike the tag bar, only tags actually in use appear and `r18` counts
+///   like any other; a work listing a tag twice counts once; drafts don't count.
+pub fn tag_counts(games: &HashMap<String, ParsedGame>) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for game in games.values().filter(|g| !is_draft(&g.meta)) {
+        let tags: HashSet<String> = game
+            .meta
+            .tags
+            .as_deref()
+            .unwrap_or(&[])
+            .iter()
+            .map(|t| t.to_lowercase())
+            .collect();
+        for tag in tags {
+            *counts.entry(tag).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// One `/api/years` entry: a top-level works folder and how many works it holds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct YearCount {
+    pub year: String,
+    pub count: usize,
+}
+
+/// - Works per year folder (series subfolders count toward their year, and
+///   every collection's works toward theirs), drafts left out, for `/api/years`.
+/// - Newest year first; folders not named like a year (all digits) follow,
+///   by name.
+pub fn year_counts(games: &HashMap<String, ParsedGame>) -> Vec<YearCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for game in games.values().filter(|g| !is_draft(&g.meta)) {
+        *counts.entry(game.year.as_str()).or_insert(0) += 1;
+    }
+    let mut years: Vec<YearCount> = counts
+        .into_iter()
+        .map(|(year, count)| YearCount {
+            year: year.to_string(),
+            count,
+        })
+        .collect();
+    let numeric = |y: &str| !y.is_empty() && y.bytes().all(|b| b.is_ascii_digit());
+    years.sort_by(|a, b| match (numeric(&a.year), numeric(&b.year)) {
+        (true, true) => (b.year.len(), &b.year).cmp(&(a.year.len(), &a.year)),
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        (false, false) => a.year.cmp(&b.year),
+    });
+    years
+}
+
 /// - Build the tag-filter bar entries: union of yaml-configured tags and tags
 ///   found in game frontmatter, deduped case-insensitively.
 /// - Counts are total games per tag (not affected by R18 toggle or current
-///   search).
+///   search); drafts aren't counted, as they aren't in the tree.
 /// - `r18` is excluded — already covered by the dedicated toggle.
 /// - Sort: count desc, then name asc (case-insensitive).
 /// - Configured tags use the yaml display casing; unconfigured (md-only) tags
@@ -934,7 +4870,7 @@ pub fn build_tag_index(
         );
     }
 
-    for game in games.values() {
+    for game in games.values().filter(|g| !is_draft(&g.meta)) {
         let tags = match &game.meta.tags {
             Some(t) => t,
             None => continue,
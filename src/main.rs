@@ -1,5 +1,6 @@
-use lightvn_works::app::build_app;
-use std::net::SocketAddr;
+use clap::Parser;
+use lightvn_works::app::{check_works, export_site, shutdown_signal, start_server};
+use lightvn_works::Config;
 
 #[tokio::main]
 async fn main() {
@@ -13,18 +14,44 @@ async fn main() {
         )
         .init();
 
-    let app = build_app();
+    // - --works-dir / --public-dir / --port / --bind, each falling back to its
+    //   env var (WORKS_DIR, PUBLIC_DIR, PORT, BIND) — see `lightvn-works --help`.
+    let config = Config::parse();
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], get_port()));
-    tracing::info!("Listening on http://{}", addr);
+    // - --export OUT_DIR: write the static site and exit without serving.
+    if let Some(out) = config.export.clone() {
+        match export_site(config, &out).await {
+            Ok(pages) => tracing::info!(pages, out = %out.display(), "exported"),
+            Err(e) => {
+                tracing::error!(out = %out.display(), error = %e, "export failed");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
+    // - --check: validate the works, print the report and exit without serving;
+    //   nonzero when anything is wrong, so CI can fail the build on it.
+    if config.check {
+        let report = check_works(&config);
+        print!("{}", report);
+        std::process::exit(report.exit_code());
+    }
 
-fn get_port() -> u16 {
-    std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(8080)
+    // - PORT=0 binds an ephemeral port; the log line names the real one.
+    // - The tree cache and thumbnails warm in the background once the app is
+    //   built, so connections are accepted while they do.
+    let requested = config.addr();
+    let (addr, server) = match start_server(config, shutdown_signal()).await {
+        Ok(started) => started,
+        Err(e) => {
+            tracing::error!(addr = %requested, error = %e, "bind failed");
+            std::process::exit(1);
+        }
+    };
+    tracing::info!("Listening on http://{}", addr);
+    if let Err(e) = server.await {
+        tracing::error!(error = %e, "server failed");
+        std::process::exit(1);
+    }
 }
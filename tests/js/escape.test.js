@@ -1,8 +1,8 @@
-// Tests for the escaping helpers in public/escape.js.
+// Tests for the escaping helpers in public/assets/escape.js.
 // Each test body has `// given:`, `// when:`, `// then:` sections.
 const { test } = require('node:test');
 const assert = require('node:assert');
-const { escapeHtml, escapeCssUrl } = require('../../public/escape.js');
+const { escapeHtml, escapeCssUrl } = require('../../public/assets/escape.js');
 
 test('escapeHtml escapes all five specials', () => {
   // given: a string with every HTML special character
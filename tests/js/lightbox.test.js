@@ -1,8 +1,8 @@
-// Tests for the wrap-around index helper in public/lightbox.js.
+// Tests for the wrap-around index helper in public/assets/lightbox.js.
 // Each test body has `// given:`, `// when:`, `// then:` sections.
 const { test } = require('node:test');
 const assert = require('node:assert');
-const { nextIndex } = require('../../public/lightbox.js');
+const { nextIndex } = require('../../public/assets/lightbox.js');
 
 test('next wraps past the last index to the first', () => {
   // given: sitting on the last of five images, moving forward
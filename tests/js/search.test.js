@@ -1,8 +1,8 @@
-// Tests for the home-page search matching in public/search.js.
+// Tests for the home-page search matching in public/assets/search.js.
 // Each test body has `// given:`, `// when:`, `// then:` sections.
 const { test } = require('node:test');
 const assert = require('node:assert');
-const { parseTagQuery, workMatchesSearch } = require('../../public/search.js');
+const { parseTagQuery, workMatchesSearch } = require('../../public/assets/search.js');
 
 test('parseTagQuery recognises the tag: prefix', () => {
   // given: queries that start with the tag: prefix, some quoted or padded
@@ -1,8 +1,8 @@
-// Tests for the home-page year-open cascade in public/view.js.
+// Tests for the home-page year-open cascade in public/assets/view.js.
 // Each test body has `// given:`, `// when:`, `// then:` sections.
 const { test } = require('node:test');
 const assert = require('node:assert');
-const { computeOpenYearFlags } = require('../../public/view.js');
+const { computeOpenYearFlags } = require('../../public/assets/view.js');
 
 test('a query opens every group', () => {
   // given: groups of various sizes and an active query
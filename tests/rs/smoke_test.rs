@@ -1,5 +1,12 @@
 use axum::http::{Request, StatusCode};
-use lightvn_works::app::build_app;
+use lightvn_works::app::{
+    build_app, build_app_with, check_works, export_site, request_limits, serve, start_server,
+    AppError,
+};
+use lightvn_works::{
+    extract_all_images, hashed_asset_name, http_date, parse_frontmatter, parse_http_date, Config,
+};
+use rstest::rstest;
 use tower::ServiceExt;
 
 #[tokio::test]
@@ -93,6 +100,28 @@ async fn game_page_rejects_dotdot_in_title() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
 
+#[rstest]
+#[case::encoded_slash("/works/2024/..%2f..%2fCargo.toml")]
+#[case::encoded_year("/works/..%2f..%2fsrc/lib.rs")]
+#[case::backslash("/works/2024/..%5c..%5cCargo.toml")]
+#[case::plain_backslash("/works/2024/a%5cb")]
+#[case::nul("/works/2024/a%00b")]
+#[case::nested_traversal("/works/2024/series/..%2f..%2fCargo.toml")]
+#[tokio::test]
+async fn game_page_rejects_decoded_traversal(#[case] uri: &str) {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting a work whose params decode to a traversal attempt
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: 400 — the guard sees the decoded `..`, `\` or control character
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn game_page_rejects_oversized_title() {
     // given: the app and a title over the 300-char limit
@@ -338,6 +367,88 @@ async fn feed_returns_atom() {
     assert!(text.contains("<entry>"));
 }
 
+#[tokio::test]
+async fn feed_entries_are_capped_and_newest_first() {
+    // given: the app, with far more than 30 dated works
+    let app = build_app();
+
+    // when: requesting the feed
+    let response = app
+        .oneshot(
+            Request::get("/feed.xml")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8_lossy(&body);
+
+    // then: 30 entries, each with a date no newer than the one before it
+    let dates: Vec<&str> = text
+        .split("<entry>")
+        .skip(1)
+        .map(|entry| {
+            let start = entry.find("<updated>").unwrap() + "<updated>".len();
+            &entry[start..start + 10]
+        })
+        .collect();
+    assert_eq!(dates.len(), 30);
+    assert!(dates.windows(2).all(|w| w[0] >= w[1]), "{:?}", dates);
+}
+
+#[tokio::test]
+async fn json_feed_matches_the_atom_feed() {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting both feeds
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/feed.json")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let (_, atom) = get_text(&app, "/feed.xml").await;
+
+    // then: a JSON Feed of the same works in the same (newest-first) order,
+    //   each item with its fields filled in
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/feed+json; charset=utf-8"
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let feed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+    let items = feed["items"].as_array().unwrap();
+    assert_eq!(items.len(), 30);
+    let atom_ids: Vec<&str> = atom
+        .split("<id>")
+        .skip(2)
+        .map(|rest| &rest[..rest.find("</id>").unwrap()])
+        .collect();
+    let json_ids: Vec<&str> = items.iter().map(|i| i["id"].as_str().unwrap()).collect();
+    assert_eq!(json_ids, atom_ids);
+    let dates: Vec<&str> = items
+        .iter()
+        .map(|i| i["date_published"].as_str().unwrap())
+        .collect();
+    assert!(dates.windows(2).all(|w| w[0] >= w[1]), "{:?}", dates);
+    for item in items {
+        assert_eq!(item["url"], item["id"]);
+        assert!(item["title"].as_str().is_some_and(|t| !t.is_empty()));
+        assert!(item["content_text"].as_str().is_some_and(|t| !t.is_empty()));
+    }
+}
+
 #[tokio::test]
 async fn home_advertises_feed() {
     // given: the app
@@ -349,13 +460,15 @@ async fn home_advertises_feed() {
         .await
         .unwrap();
 
-    // then: the head links to the Atom feed
+    // then: the head links to the Atom and JSON feeds
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
     let html = String::from_utf8_lossy(&body);
     assert!(html.contains("application/atom+xml"));
     assert!(html.contains("/feed.xml"));
+    assert!(html.contains("application/feed+json"));
+    assert!(html.contains("/feed.json"));
 }
 
 #[tokio::test]
@@ -494,6 +607,60 @@ async fn sitemap_includes_creator_urls() {
     assert!(text.contains("/creator/"));
 }
 
+#[tokio::test]
+async fn sitemap_skips_creators_credited_only_on_drafts() {
+    // given: a published work by one creator and a draft by another
+    let app = fixture_app(
+        "sitemap-draft-creator",
+        &[
+            ("2024/Alpha.md", "---\ncreator: Shown\n---\n\nAlpha body."),
+            (
+                "2024/Beta.md",
+                "---\ncreator: Hidden\ndraft: true\n---\n\nBeta body.",
+            ),
+        ],
+    );
+
+    // when: requesting the sitemap
+    let (status, text) = get_text(&app, "/sitemap.xml").await;
+
+    // then: only the published work's creator gets a URL
+    assert_eq!(status, StatusCode::OK);
+    assert!(text.contains("/creator/Shown"), "{}", text);
+    assert!(!text.contains("/creator/Hidden"), "{}", text);
+}
+
+#[tokio::test]
+async fn sitemap_lists_home_plus_every_work_with_lastmod() {
+    // given: the app and the number of markdown works on disk
+    let app = build_app();
+    let md_files = walkdir::WalkDir::new("works")
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+        .count();
+
+    // when: requesting the sitemap
+    let response = app
+        .oneshot(
+            Request::get("/sitemap.xml")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8_lossy(&body);
+
+    // then: one <loc> for / plus one per work, each work dated by its file
+    let home = text.matches("/</loc></url>").count();
+    let works = text.matches("/works/").count();
+    assert_eq!(home + works, md_files + 1);
+    assert_eq!(text.matches("</loc><lastmod>").count(), works);
+}
+
 #[tokio::test]
 async fn robots_points_to_sitemap() {
     // given: the app
@@ -509,12 +676,3978 @@ async fn robots_points_to_sitemap() {
         .await
         .unwrap();
 
-    // then: 200 and the body references the sitemap
+    // then: 200, the API is disallowed and the body references the sitemap
     assert_eq!(response.status(), StatusCode::OK);
     let body = axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
         .unwrap();
     let text = String::from_utf8_lossy(&body);
+    assert!(text.contains("Disallow: /api/"));
     assert!(text.contains("Sitemap:"));
     assert!(text.contains("/sitemap.xml"));
 }
+
+#[tokio::test]
+async fn robots_disallows_everything_when_crawlers_are_refused() {
+    // given: a staging app that refuses crawlers
+    let app = build_app_with(Config {
+        disallow_crawlers: true,
+        ..Config::default()
+    });
+
+    // when: requesting robots.txt
+    let (status, text) = get_text(&app, "/robots.txt").await;
+
+    // then: the whole site is disallowed and no sitemap is advertised
+    assert_eq!(status, StatusCode::OK);
+    assert!(text.contains("Disallow: /\n"));
+    assert!(!text.contains("Sitemap:"));
+}
+
+#[tokio::test]
+async fn rendering_a_work_counts_as_a_view() {
+    // given: the app (one Router, cloned per request so state is shared)
+    let app = build_app();
+
+    // when: the same game page is rendered twice, then /api/popular is read
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/works/2024/42%20Hallows%20Street")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app
+        .oneshot(
+            Request::get("/api/popular?limit=5")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: the work leads the list with both views counted
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let popular: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(popular[0]["path"], "/works/2024/42 Hallows Street");
+    assert_eq!(popular[0]["views"], 2);
+    assert_eq!(popular.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn published_work_has_no_robots_header() {
+    // given: the app and a published (non-draft, already-added) work
+    let app = build_app();
+
+    // when: requesting its page
+    let response = app
+        .oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: crawlers get no noindex directive
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("x-robots-tag").is_none());
+}
+
+#[rstest]
+#[case::previewed_draft("noindex-draft", "draft: true", "?preview=let-me-see")]
+#[case::scheduled("noindex-scheduled", "date_added: 2999/01/01", "")]
+#[tokio::test]
+async fn unpublished_work_is_served_noindex(
+    #[case] name: &str,
+    #[case] frontmatter: &str,
+    #[case] query: &str,
+) {
+    // given: a draft (opened with the preview token) or a work scheduled ahead
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            name,
+            &[(
+                "2024/Soon.md",
+                &format!("---\n{}\n---\n\nBody.", frontmatter),
+            )],
+        ),
+        draft_preview_token: Some("let-me-see".to_string()),
+        ..Config::default()
+    });
+
+    // when: requesting its page
+    let response = app
+        .oneshot(
+            Request::get(format!("/works/2024/Soon{}", query))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: it renders, but tells crawlers to keep it out of the index
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-robots-tag"], "noindex, nofollow");
+}
+
+#[tokio::test]
+async fn preview_param_does_not_affect_published_work() {
+    // given: the app and a published work
+    let app = build_app();
+
+    // when: requesting its page and raw source with a wrong preview token
+    let page = app
+        .clone()
+        .oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street?preview=wrong")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let raw = app
+        .oneshot(
+            Request::get("/raw/2024/42%20Hallows%20Street.md?preview=wrong")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: the token only gates drafts, so both are served as usual
+    assert_eq!(page.status(), StatusCode::OK);
+    assert_eq!(raw.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn date_permalink_resolves_to_work() {
+    // given: a permalink pattern, and 42 Hallows Street, released 2024/09/30
+    let app = build_app_with(Config {
+        permalink_pattern: Some("/:year/:month/:slug".to_string()),
+        ..Config::default()
+    });
+
+    // when: requesting the date-prefixed permalink and a wrong-month variant
+    let hit = app
+        .clone()
+        .oneshot(
+            Request::get("/2024/09/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let miss = app
+        .oneshot(
+            Request::get("/2024/10/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then:
+    // - the permalink renders the work, canonicalized to its /works/ URL
+    // - the wrong month is a 404
+    assert_eq!(hit.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(hit.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8_lossy(&body);
+    assert!(html.contains("<h1>42 Hallows Street</h1>"));
+    assert!(html.contains("/works/2024/42%20Hallows%20Street\""));
+    assert_eq!(miss.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn work_diff_rejects_bad_revision() {
+    // given: the app
+    let app = build_app();
+
+    // when: asking for a diff with a non-hex revision (an option-injection attempt)
+    let response = app
+        .oneshot(
+            Request::get("/api/works/2024/42%20Hallows%20Street/diff?from=--output%3D%2Ftmp%2Fx")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: 400, git is never invoked with it
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn work_diff_unknown_work_returns_404() {
+    // given: the app
+    let app = build_app();
+
+    // when: asking for a diff of a work that isn't in the catalog
+    let response = app
+        .oneshot(
+            Request::get("/api/works/2024/nonexistent/diff?from=HEAD")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: 404
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn work_diff_same_revision_is_empty() {
+    // given: a git checkout (skipped for source drops without .git) and a tracked work
+    if !std::path::Path::new(".git").exists() {
+        return;
+    }
+    let app = build_app();
+
+    // when: diffing HEAD against itself
+    let response = app
+        .oneshot(
+            Request::get("/api/works/2024/42%20Hallows%20Street/diff?from=HEAD")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: 200 JSON naming the file, with an empty diff
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let diff: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(diff["path"], "works/2024/42 Hallows Street.md");
+    assert_eq!(diff["to"], "HEAD");
+    assert_eq!(diff["diff"], "");
+}
+
+#[tokio::test]
+async fn work_diff_reads_the_works_own_checkout() {
+    // given: a works dir that is its own git checkout, with a work edited in
+    // a second commit (skipped where there's no git to run)
+    let dir = fixture_dir("diff-checkout", &[("2024/Edited.md", "First draft.\n")]);
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .current_dir(&dir)
+            .args(["-c", "user.name=T", "-c", "user.email=t@example.com"])
+            .args(args)
+            .output()
+    };
+    if git(&["init", "-q"]).is_err() {
+        return;
+    }
+    git(&["add", "."]).unwrap();
+    git(&["commit", "-qm", "first"]).unwrap();
+    let first = git(&["rev-parse", "HEAD"]).unwrap();
+    let first = String::from_utf8_lossy(&first.stdout).trim().to_string();
+    std::fs::write(dir.join("2024/Edited.md"), "Second draft.\n").unwrap();
+    git(&["commit", "-qam", "second"]).unwrap();
+    let app = build_app_with(Config {
+        works_dir: dir.clone(),
+        ..Config::default()
+    });
+
+    // when: diffing the work from the first commit to HEAD, twice
+    let uri = format!("/api/works/2024/Edited/diff?from={}", first);
+    let (status, body) = get_text(&app, &uri).await;
+    let (_, again) = get_text(&app, &uri).await;
+
+    // then: the edit, from the works' checkout rather than the server's own
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let diff: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let diff = diff["diff"].as_str().unwrap();
+    assert!(diff.contains("-First draft."), "{}", diff);
+    assert!(diff.contains("+Second draft."), "{}", diff);
+    assert_eq!(again, body);
+}
+
+#[tokio::test]
+async fn work_diff_is_rate_limited() {
+    // given: an app allowing one limited request a minute per client
+    let app = rate_limited_app(1, false);
+
+    // when: asking for two diffs in a row
+    let (first, _) = get_text(&app, "/api/works/2024/nonexistent/diff?from=HEAD").await;
+    let (second, _) = get_text(&app, "/api/works/2024/nonexistent/diff?from=HEAD").await;
+
+    // then: the second is refused before any git runs
+    assert_eq!(first, StatusCode::NOT_FOUND);
+    assert_eq!(second, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn tree_stream_reconstructs_to_api_tree() {
+    // given: the app, and the buffered tree as the reference
+    let app = build_app();
+    let tree_res = app
+        .clone()
+        .oneshot(
+            Request::get("/api/tree")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let tree: serde_json::Value = serde_json::from_slice(
+        &axum::body::to_bytes(tree_res.into_body(), usize::MAX)
+            .await
+            .unwrap(),
+    )
+    .unwrap();
+
+    // when: consuming /api/tree/stream and regrouping lines by `parent`
+    let response = app
+        .oneshot(
+            Request::get("/api/tree/stream")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok()),
+        Some("application/x-ndjson")
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut children: std::collections::HashMap<String, Vec<String>> = Default::default();
+    let mut roots = Vec::new();
+    for line in String::from_utf8_lossy(&body).lines() {
+        let node: serde_json::Value = serde_json::from_str(line).unwrap();
+        let path = node["path"].as_str().unwrap().to_string();
+        match node["parent"].as_str() {
+            Some(parent) => {
+                // parents always precede their children
+                assert!(parent == "/works" || children.contains_key(parent));
+                children
+                    .entry(parent.to_string())
+                    .or_default()
+                    .push(path.clone());
+            }
+            None => roots.push(path.clone()),
+        }
+        if node["is_dir"] == true {
+            children.entry(path).or_default();
+        }
+    }
+
+    // then: one root, and every directory's children match /api/tree in order
+    assert_eq!(roots, vec!["/works".to_string()]);
+    let years = tree["children"].as_array().unwrap();
+    let year_paths: Vec<String> = years
+        .iter()
+        .map(|y| y["path"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(children["/works"], year_paths);
+    for year in years {
+        let expected: Vec<String> = year["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| w["path"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(children[year["path"].as_str().unwrap()], expected);
+    }
+}
+
+#[tokio::test]
+async fn comment_counts_surface_on_tree_and_page() {
+    // given: an external comment-count file, with 3 comments on 42 Hallows Street
+    let file = std::env::temp_dir().join(format!("lightvn-comments-{}.json", std::process::id()));
+    std::fs::write(&file, r#"{"works/2024/42 Hallows Street.md": 3}"#).unwrap();
+    let app = build_app_with(Config {
+        comments_file: Some(file.clone()),
+        ..Config::default()
+    });
+    let get = |uri: &str| {
+        app.clone()
+            .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+    };
+
+    // when: reading the tree and the page, then again after the file changes
+    let tree = axum::body::to_bytes(get("/api/tree").await.unwrap().into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page = axum::body::to_bytes(
+        get("/works/2024/42%20Hallows%20Street")
+            .await
+            .unwrap()
+            .into_body(),
+        usize::MAX,
+    )
+    .await
+    .unwrap();
+    std::fs::write(&file, r#"{"/works/2024/42 Hallows Street": 1}"#).unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+    let refreshed = axum::body::to_bytes(
+        get("/works/2024/42%20Hallows%20Street")
+            .await
+            .unwrap()
+            .into_body(),
+        usize::MAX,
+    )
+    .await
+    .unwrap();
+
+    // then:
+    // - the tree node carries the count; works without an entry omit it
+    // - the page shows it, and picks up the rewrite without a restart (in
+    //   the singular for one)
+    let tree: serde_json::Value = serde_json::from_slice(&tree).unwrap();
+    let year = tree["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|y| y["name"] == "2024")
+        .unwrap();
+    let works = year["children"].as_array().unwrap();
+    let work = works
+        .iter()
+        .find(|w| w["name"] == "42 Hallows Street.md")
+        .unwrap();
+    assert_eq!(work["comment_count"], 3);
+    assert!(works
+        .iter()
+        .filter(|w| w["name"] != "42 Hallows Street.md")
+        .all(|w| w.get("comment_count").is_none()));
+    assert!(String::from_utf8_lossy(&page).contains("3 comments"));
+    assert!(String::from_utf8_lossy(&refreshed).contains(">1 comment<"));
+    let _ = std::fs::remove_file(&file);
+}
+
+#[tokio::test]
+async fn home_references_content_hashed_assets() {
+    // given: the app, and public/assets/style.css as it is on disk
+    let app = build_app();
+    let on_disk = std::fs::read("public/assets/style.css").unwrap();
+    let expected = format!("/assets/{}", hashed_asset_name("style.css", &on_disk));
+
+    // when: rendering the homepage and fetching the hashed URL it references
+    let home = app
+        .clone()
+        .oneshot(Request::get("/").body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let html = axum::body::to_bytes(home.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8_lossy(&html);
+    let asset = app
+        .oneshot(
+            Request::get(&expected)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then:
+    // - the template placeholder became the hashed URL of the on-disk file
+    // - that URL serves those exact bytes, cacheable forever
+    assert!(html.contains(&format!(r#"href="{}""#, expected)));
+    assert!(!html.contains("{{asset:"));
+    assert_eq!(asset.status(), StatusCode::OK);
+    assert_eq!(
+        asset
+            .headers()
+            .get("cache-control")
+            .and_then(|v| v.to_str().ok()),
+        Some("public, max-age=31536000, immutable")
+    );
+    let body = axum::body::to_bytes(asset.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.as_ref(), on_disk.as_slice());
+}
+
+#[tokio::test]
+async fn stale_asset_hash_returns_404() {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting style.css under a hash that isn't its current one
+    let response = app
+        .oneshot(
+            Request::get("/assets/style.0000000000000000.css")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: not found, rather than serving different bytes under that URL
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn assets_come_from_the_configured_public_dirs_assets() {
+    // given: a public dir with a stylesheet under assets/ and one beside it
+    let public_dir = fixture_dir(
+        "assets-scope",
+        &[("assets/site.css", "body{}"), ("stray.css", "p{}")],
+    );
+    let app = build_app_with(Config {
+        public_dir,
+        ..Config::default()
+    });
+    let hashed =
+        |name: &str, css: &str| format!("/assets/{}", hashed_asset_name(name, css.as_bytes()));
+
+    // when: requesting each under its hashed URL, and the first as named
+    let (site, body) = get_text(&app, &hashed("site.css", "body{}")).await;
+    let (stray, _) = get_text(&app, &hashed("stray.css", "p{}")).await;
+    let (plain, plain_body) = get_text(&app, "/assets/site.css").await;
+
+    // then: only assets/ is fingerprinted, read from the configured dir; its
+    // files also answer under their own names
+    assert_eq!(site, StatusCode::OK);
+    assert_eq!(body, "body{}");
+    assert_eq!(stray, StatusCode::NOT_FOUND);
+    assert_eq!(plain, StatusCode::OK);
+    assert_eq!(plain_body, "body{}");
+}
+
+#[tokio::test]
+async fn orphans_returns_report() {
+    // given: a work embedding one image, beside an image nothing references
+    let app = fixture_app(
+        "orphans",
+        &[
+            ("2024/Work.md", "Intro.\n\n![Cover](cover.png)\n"),
+            ("2024/cover.png", "png"),
+            ("2024/unused.png", "unused"),
+        ],
+    );
+
+    // when: requesting the orphaned-asset report
+    let (status, body) = get_text(&app, "/api/orphans").await;
+
+    // then: only the unreferenced image is listed, with its size in the total
+    assert_eq!(status, StatusCode::OK);
+    let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let orphans = report["orphans"].as_array().unwrap();
+    assert_eq!(orphans.len(), 1, "{}", body);
+    assert!(orphans[0]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("2024/unused.png"));
+    assert_eq!(orphans[0]["size"], 6);
+    assert_eq!(report["total_bytes"], 6);
+}
+
+#[rstest]
+#[case::doubled_slashes("/works//2024//42%20Hallows%20Street")]
+#[case::dot_segment("/works/2024/./42%20Hallows%20Street")]
+#[tokio::test]
+async fn sloppy_work_url_redirects_to_canonical(#[case] uri: &str) {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting a work through a sloppy path, with a query
+    let response = app
+        .oneshot(
+            Request::get(format!("{}?lang=ja", uri))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: a 301 to the clean path, query preserved
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(
+        response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok()),
+        Some("/works/2024/42%20Hallows%20Street?lang=ja")
+    );
+}
+
+#[tokio::test]
+async fn dotdot_segment_is_rejected() {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting a path with a `..` segment
+    let response = app
+        .oneshot(
+            Request::get("/works/2024/../2023/x")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: refused outright, not resolved
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn featured_lists_works_in_order_skipping_missing() {
+    // given: a featured list of two real works around an entry that isn't in
+    // the catalog
+    let file = std::env::temp_dir().join(format!("lightvn-featured-{}.toml", std::process::id()));
+    std::fs::write(
+        &file,
+        "works = [\n  \"works/2023/いちりんのはな.md\",\n  \"works/2099/Not A Work.md\",\n  \"/works/2024/42 Hallows Street\",\n]\n",
+    )
+    .unwrap();
+    let app = build_app_with(Config {
+        featured_file: Some(file.clone()),
+        ..Config::default()
+    });
+
+    // when: requesting the featured list and the homepage
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/featured")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let home = app
+        .oneshot(Request::get("/").body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let home = axum::body::to_bytes(home.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let _ = std::fs::remove_file(&file);
+
+    // then:
+    // - the two works, in file order, the missing entry skipped
+    // - the homepage renders them as a featured strip, in the same order
+    let featured: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let paths: Vec<&str> = featured
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| w["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/works/2023/いちりんのはな",
+            "/works/2024/42 Hallows Street"
+        ]
+    );
+    let home = String::from_utf8_lossy(&home);
+    let strip = &home[home.find(r#"<section class="featured">"#).unwrap()..];
+    let first = strip.find("いちりんのはな").unwrap();
+    let second = strip.find("42 Hallows Street").unwrap();
+    assert!(first < second);
+}
+
+#[tokio::test]
+async fn link_graph_returns_dot() {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting the link graph
+    let response = app
+        .oneshot(
+            Request::get("/api/linkgraph.dot")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: a GraphViz document covering the catalog
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok()),
+        Some("text/vnd.graphviz; charset=utf-8")
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let dot = String::from_utf8_lossy(&body);
+    assert!(dot.starts_with("digraph works {"));
+    assert!(dot.contains("\"/works/2024/42 Hallows Street\" [label=\"42 Hallows Street\"];"));
+}
+
+#[tokio::test]
+async fn yearless_unique_title_redirects() {
+    // given: 42 Hallows Street exists only under 2024
+    let app = build_app();
+
+    // when: requesting it without the year
+    let response = app
+        .oneshot(
+            Request::get("/works/42%20Hallows%20Street?lang=ja")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: a 302 to the full URL, query preserved
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok()),
+        Some("/works/2024/42%20Hallows%20Street?lang=ja")
+    );
+}
+
+#[tokio::test]
+async fn yearless_ambiguous_title_lists_candidates() {
+    // given: 幽霊少女室 exists under more than one year
+    let app = build_app();
+    let years: Vec<String> = std::fs::read_dir("works")
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().join("幽霊少女室.md").is_file())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    assert!(years.len() > 1);
+
+    // when: requesting it without the year
+    let response = app
+        .oneshot(
+            Request::get("/works/%E5%B9%BD%E9%9C%8A%E5%B0%91%E5%A5%B3%E5%AE%A4")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: a 300 page linking the work under each year
+    assert_eq!(response.status(), StatusCode::MULTIPLE_CHOICES);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8_lossy(&body);
+    for year in years {
+        assert!(html.contains(&format!("幽霊少女室 ({})", year)), "{}", year);
+    }
+}
+
+#[tokio::test]
+async fn yearless_unknown_title_returns_404() {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting a title no work has
+    let response = app
+        .oneshot(
+            Request::get("/works/No%20Such%20Work")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: not found
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn html_and_images_get_different_cache_policies() {
+    // given: the app with the default cache policy
+    let app = build_app();
+
+    // when: requesting a work page and a static image
+    let page = app
+        .clone()
+        .oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let image = app
+        .oneshot(
+            Request::get("/lvn_icon.webp")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: the page revalidates every time, the image is cached for a day
+    let cache_control = |r: &axum::response::Response| {
+        r.headers()
+            .get("cache-control")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    assert_eq!(cache_control(&page).as_deref(), Some("no-cache"));
+    assert_eq!(
+        cache_control(&image).as_deref(),
+        Some("public, max-age=86400")
+    );
+}
+
+#[rstest]
+#[case::all_rules("/api/quality", None)]
+#[case::no_rules("/api/quality?rules=", Some(true))]
+#[tokio::test]
+async fn quality_report_groups_by_rule(#[case] uri: &str, #[case] expected_pass: Option<bool>) {
+    // given: the app over the real catalog
+    let app = build_app();
+
+    // when: requesting the quality report
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then:
+    // - every rule key is present, and the exit hint agrees with `pass`
+    // - with no rules enabled, nothing can fail
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    for rule in [
+        "missing_thumbnail",
+        "missing_released",
+        "short_body",
+        "no_headings",
+    ] {
+        assert!(report["rules"][rule].is_array(), "{}", rule);
+    }
+    let pass = report["pass"].as_bool().unwrap();
+    assert_eq!(report["exit_code"], if pass { 0 } else { 1 });
+    if let Some(expected) = expected_pass {
+        assert_eq!(pass, expected);
+    }
+}
+
+#[rstest]
+#[case::multi_word("noah%20MANSION", true)]
+#[case::no_match("zzqx%20noah", false)]
+#[tokio::test]
+async fn search_matches_work_bodies(#[case] query: &str, #[case] expect_hit: bool) {
+    // given: the app, with 42 Hallows Street's synopsis mentioning Noah and a mansion
+    let app = build_app();
+
+    // when: searching
+    let response = app
+        .oneshot(
+            Request::get(format!("/api/search?q={}", query))
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    // then: every term must match, regardless of case, with a snippet from the body
+    assert_eq!(status, StatusCode::OK);
+    let hits: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let hit = hits
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|h| h["path"] == "/works/2024/42 Hallows Street");
+    assert_eq!(hit.is_some(), expect_hit, "{}", hits);
+    if let Some(hit) = hit {
+        assert_eq!(hit["year"], "2024");
+        assert!(hit["snippet"].as_str().unwrap().contains("Noah"));
+    }
+}
+
+#[rstest]
+#[case::exact_misses_intended("protagnist", false, &["/works/2024/Side"])]
+#[case::fuzzy_finds_typo("protagnist&fuzzy=true", true, &["/works/2024/Hero", "/works/2024/Side"])]
+#[case::fuzzy_skips_unrelated("zzqx&fuzzy=true", true, &[])]
+#[tokio::test]
+async fn search_fuzzy_mode_tolerates_typos(
+    #[case] query: &str,
+    #[case] fuzzy: bool,
+    #[case] expected: &[&str],
+) {
+    // given: a work about the protagonist, one mentioning a protagnist
+    // misspelt, and one unrelated
+    let name = format!("search-fuzzy-{}", query.len());
+    let app = fixture_app(
+        &name,
+        &[
+            ("2024/Hero.md", "The protagonist wakes."),
+            ("2024/Side.md", "A side story; the protagnist sleeps."),
+            ("2024/Other.md", "Nothing to see."),
+        ],
+    );
+
+    // when: searching for the misspelling
+    let (status, body) = get_text(&app, &format!("/api/search?q={}", query)).await;
+
+    // then: only fuzzy mode finds the intended work too, scored, best first
+    assert_eq!(status, StatusCode::OK);
+    let hits: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    let mut paths: Vec<&str> = hits.iter().map(|h| h["path"].as_str().unwrap()).collect();
+    paths.sort();
+    assert_eq!(paths, expected, "{}", body);
+    if fuzzy {
+        let scores: Vec<i64> = hits.iter().map(|h| h["score"].as_i64().unwrap()).collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]), "{:?}", scores);
+        if let Some(hero) = hits.iter().find(|h| h["path"] == "/works/2024/Hero") {
+            assert!(hero["snippet"].as_str().unwrap().contains("protagonist"));
+        }
+    } else {
+        assert!(hits[0].get("score").is_none());
+    }
+}
+
+#[tokio::test]
+async fn game_page_og_image_is_first_extracted_image() {
+    // given: the app and a known game's source file
+    let app = build_app();
+    let source = std::fs::read_to_string("works/2024/42 Hallows Street.md").unwrap();
+    let (_, body) = parse_frontmatter(&source);
+    let first = extract_all_images(body).first().unwrap().url.clone();
+
+    // when: requesting the game page
+    let response = app
+        .oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8_lossy(&body);
+
+    // then: og:image is that image, with a large-image Twitter card
+    assert!(html.contains(&format!(
+        r#"<meta property="og:image" content="{}" />"#,
+        first
+    )));
+    assert!(html.contains(r#"<meta name="twitter:card" content="summary_large_image" />"#));
+}
+
+#[rstest]
+#[case::unchanged_since(0, None, StatusCode::NOT_MODIFIED)]
+#[case::checked_later(3_600, None, StatusCode::NOT_MODIFIED)]
+#[case::modified_since(-3_600, None, StatusCode::OK)]
+#[case::etag_overrides_date(0, Some("W/\"stale\""), StatusCode::OK)]
+#[tokio::test]
+async fn game_page_revalidates_with_last_modified(
+    #[case] offset: i64,
+    #[case] if_none_match: Option<&str>,
+    #[case] expected: StatusCode,
+) {
+    // given: a work and a first request for its page
+    let name = format!("last-modified-{}-{}", offset, expected.as_u16());
+    let app = fixture_app(&name, &[("2024/A.md", "A body.")]);
+    let get = |headers: Vec<(&'static str, String)>| {
+        let mut request = Request::get("/works/2024/A");
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        app.clone()
+            .oneshot(request.body(axum::body::Body::empty()).unwrap())
+    };
+    let first = get(vec![]).await.unwrap();
+    let last_modified = first.headers()["last-modified"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let mtime = parse_http_date(&last_modified).unwrap();
+
+    // when: revalidating with a date `offset` seconds from it
+    let since = http_date(mtime.checked_add_signed(offset).unwrap());
+    let mut headers = vec![("if-modified-since", since)];
+    if let Some(tag) = if_none_match {
+        headers.push(("if-none-match", tag.to_string()));
+    }
+    let response = get(headers).await.unwrap();
+
+    // then: a 304 only when the page is unchanged since then and no ETag
+    // says otherwise, with both validators either way
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(response.status(), expected);
+    assert_eq!(response.headers()["last-modified"], last_modified.as_str());
+    assert!(response.headers().contains_key("etag"));
+}
+
+#[tokio::test]
+async fn game_page_revalidates_with_etag() {
+    // given: the app and a first, unconditional request for a game page
+    let app = build_app();
+    let first = app
+        .clone()
+        .oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let etag = first.headers().get("etag").unwrap().clone();
+
+    // when: revalidating with that tag, and with a stale one
+    let revalidate = |tag: axum::http::HeaderValue| {
+        app.clone().oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street")
+                .header("if-none-match", tag)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+    };
+    let matching = revalidate(etag.clone()).await.unwrap();
+    let stale = revalidate(axum::http::HeaderValue::from_static("W/\"stale\""))
+        .await
+        .unwrap();
+
+    // then:
+    // - the fresh response is a 200 with a tag
+    // - a matching tag gets an empty 304 carrying the same tag
+    // - a stale tag gets the full page again
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(matching.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(matching.headers().get("etag"), Some(&etag));
+    let body = axum::body::to_bytes(matching.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+    assert_eq!(stale.status(), StatusCode::OK);
+}
+
+#[rstest]
+#[case::three_segments("/works/2023/series-name/chapter-1")]
+#[case::four_segments("/works/2023/saga/part-1/chapter-1")]
+#[tokio::test]
+async fn nested_work_paths_reach_the_work_route(#[case] uri: &str) {
+    // given: the app (with no nested works in the catalog)
+    let app = build_app();
+
+    // when: requesting a nested work path
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    // then: the work handler's own 404, naming the full nested path
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let html = String::from_utf8_lossy(&body);
+    let rest = uri.trim_start_matches("/works/");
+    assert!(html.contains(&format!("{}.md", rest)), "{}", html);
+}
+
+#[rstest]
+#[case::gzip("gzip")]
+#[case::brotli("br")]
+#[tokio::test]
+async fn tree_is_compressed_when_accepted(#[case] encoding: &str) {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting the tree with an Accept-Encoding
+    let response = app
+        .oneshot(
+            Request::get("/api/tree")
+                .header("accept-encoding", encoding)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: the body comes back in that encoding
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-encoding")
+            .and_then(|v| v.to_str().ok()),
+        Some(encoding)
+    );
+}
+
+#[tokio::test]
+async fn images_are_not_recompressed() {
+    // given: the app and an already-compressed static image
+    let app = build_app();
+
+    // when: requesting it with gzip accepted
+    let response = app
+        .oneshot(
+            Request::get("/lvn_icon.webp")
+                .header("accept-encoding", "gzip")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: sent as-is — image/* is outside the compression predicate
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+}
+
+// Captures formatted log output for one scope.
+#[derive(Clone, Default)]
+struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn index_build_logs_at_debug() {
+    // given: a debug-level subscriber writing into a buffer (scoped to this
+    // thread, so other tests keep running with no subscriber at all)
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    // when: building the app under it
+    let app = tracing::subscriber::with_default(subscriber, build_app);
+
+    // then: one debug summary of the index, no per-node trace lines, and the
+    // app serves as usual
+    let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("DEBUG"), "{}", output);
+    assert!(output.contains("works indexed"), "{}", output);
+    assert!(!output.contains("tree node"), "{}", output);
+    let response = app
+        .oneshot(
+            Request::get("/api/tree")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn work_json_returns_metadata() {
+    // given: the app and a work with frontmatter
+    let app = build_app();
+
+    // when: requesting its JSON
+    let response = app
+        .oneshot(
+            Request::get("/api/work/2024/42%20Hallows%20Street")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    // then: identity, parsed frontmatter, proxied thumbnail and body stats
+    assert_eq!(status, StatusCode::OK);
+    let work: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(work["title"], "42 Hallows Street");
+    assert_eq!(work["year"], "2024");
+    assert_eq!(work["path"], "/works/2024/42 Hallows Street");
+    assert_eq!(work["frontmatter"]["creator"], "OldPat");
+    assert_eq!(work["frontmatter"]["tags"][0], "Spooktober");
+    assert!(work["thumbnail"].as_str().unwrap().starts_with("/thumb/"));
+    assert!(work["word_count"].as_u64().unwrap() > 20);
+    assert!(work["reading_time_minutes"].as_u64().unwrap() >= 1);
+    assert!(work["headings"].is_array());
+}
+
+#[rstest]
+#[case::absent("/api/work/2024/No%20Such%20Work", StatusCode::NOT_FOUND)]
+#[case::traversal("/api/work/2024/..%2f..%2fCargo.toml", StatusCode::BAD_REQUEST)]
+#[tokio::test]
+async fn work_json_errors_are_json(#[case] uri: &str, #[case] expected: StatusCode) {
+    // given: the app
+    let app = build_app();
+
+    // when: requesting a work that isn't there, or a traversal attempt
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    // then: the status with a JSON error body, not an HTML page
+    assert_eq!(status, expected);
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(error["error"].is_string());
+}
+
+#[tokio::test]
+async fn work_page_shows_reading_time() {
+    // given: the app
+    let app = build_app();
+
+    // when: rendering a work page in English
+    let response = app
+        .oneshot(
+            Request::get("/works/2024/42%20Hallows%20Street?lang=en")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8_lossy(&body);
+
+    // then: the estimate sits in the meta row
+    assert!(html.contains(r#"<span class="meta-item">~1 min read</span>"#));
+}
+
+#[tokio::test]
+async fn reading_time_is_the_same_on_page_tree_and_feed() {
+    // given: a work of 441 words, just over two minutes at 220 wpm
+    let body = "word ".repeat(441);
+    let app = fixture_app("reading-time", &[("2020/Long.md", &body)]);
+
+    // when: fetching its page, its JSON, the tree and the JSON feed
+    let (_, page) = get_text(&app, "/works/2020/Long?lang=en").await;
+    let (_, work) = get_text(&app, "/api/work/2020/Long").await;
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (_, feed) = get_text(&app, "/feed.json").await;
+
+    // then: every view carries the same rounded-up estimate
+    assert!(page.contains(r#"<span class="meta-item">~3 min read</span>"#));
+    let work: serde_json::Value = serde_json::from_str(&work).unwrap();
+    assert_eq!(work["reading_time_minutes"], 3);
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    assert_eq!(
+        tree["children"][0]["children"][0]["reading_time_minutes"],
+        3
+    );
+    assert!(tree["children"][0].get("reading_time_minutes").is_none());
+    let feed: serde_json::Value = serde_json::from_str(&feed).unwrap();
+    assert_eq!(feed["items"][0]["_lightvn"]["reading_time_minutes"], 3);
+}
+
+/// - App over a scratch works dir holding `files` (relative path, contents),
+///   keyed by test name + pid like works_test's `scratch_dir`.
+fn fixture_app(name: &str, files: &[(&str, &str)]) -> axum::Router {
+    build_app_with(Config {
+        works_dir: fixture_dir(name, files),
+        ..Config::default()
+    })
+}
+
+/// - A fresh works dir holding `files`, for apps needing more config than
+///   fixture_app sets.
+fn fixture_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("lightvn-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, contents) in files {
+        let file = dir.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, contents).unwrap();
+    }
+    dir
+}
+
+async fn get_text(app: &axum::Router, uri: &str) -> (StatusCode, String) {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+const DRAFT_FIXTURE: &[(&str, &str)] = &[
+    ("2024/Alpha.md", "---\ncreator: Tester\n---\n\nAlpha body."),
+    (
+        "2024/Beta.md",
+        "---\ncreator: Tester\ndraft: true\n---\n\nBeta body.",
+    ),
+    ("2023/Gamma.md", "---\ndraft: true\n---\n\nGamma body."),
+];
+
+#[tokio::test]
+async fn custom_works_dir_serves_tree_and_pages() {
+    // given: an app pointed at a scratch works dir with one work
+    let app = fixture_app(
+        "custom-works",
+        &[(
+            "2020/Solo.md",
+            "---\ncreator: Tester\n---\n\nSolo body text.",
+        )],
+    );
+
+    // when: fetching the tree and the work's page
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (status, page) = get_text(&app, "/works/2020/Solo").await;
+
+    // then: both come from the scratch dir, not ./works
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    let years = tree["children"].as_array().unwrap();
+    assert_eq!(years.len(), 1);
+    assert_eq!(years[0]["children"][0]["path"], "/works/2020/Solo.md");
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("Solo body text."));
+}
+
+#[tokio::test]
+async fn plaintext_work_escapes_markup() {
+    // given: a .txt work holding a script tag, markup and an entity
+    let app = fixture_app(
+        "plaintext-escape",
+        &[(
+            "2020/Notes.txt",
+            "Line <b>one</b> & \"two\"\n<script>alert(1)</script>\n",
+        )],
+    );
+
+    // when: rendering its page
+    let (status, page) = get_text(&app, "/works/2020/Notes").await;
+
+    // then: the text sits escaped inside the <pre>, so nothing in it runs or renders
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains(
+        r#"<pre class="plaintext">Line &lt;b&gt;one&lt;/b&gt; &amp; &quot;two&quot;
+&lt;script&gt;alert(1)&lt;/script&gt;
+</pre>"#
+    ));
+    assert!(!page.contains("<script>alert(1)"));
+    assert!(!page.contains("<b>one</b>"));
+}
+
+#[rstest]
+#[case::on(true, r#"alt="My Cover""#)]
+#[case::off(false, r#"alt="""#)]
+#[tokio::test]
+async fn auto_alt_follows_config(#[case] auto_alt: bool, #[case] expected: &str) {
+    // given: a work with an image lacking alt text
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            &format!("auto-alt-{}", auto_alt),
+            &[("2020/Pics.md", "![](my-cover.png)")],
+        ),
+        auto_alt,
+        ..Config::default()
+    });
+
+    // when: rendering its body
+    let response = app
+        .oneshot(
+            Request::get("/works/2020/Pics")
+                .header("accept", "application/json")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let work: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    // then: alt text from the file name only when the setting is on
+    let html = work["html"].as_str().unwrap();
+    assert!(html.contains(expected), "{}", html);
+}
+
+#[rstest]
+#[case::listed_host(&["cdn.example.com"], true)]
+#[case::unlisted_host(&[], false)]
+#[tokio::test]
+async fn remote_media_embeds_only_from_configured_hosts(
+    #[case] hosts: &[&str],
+    #[case] embedded: bool,
+) {
+    // given: a work linking a remote trailer, and the hosts media may come from
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            &format!("media-hosts-{}", hosts.len()),
+            &[("2020/Clip.md", "[Trailer](https://cdn.example.com/t.mp4)")],
+        ),
+        media_hosts: hosts.iter().map(|h| h.to_string()).collect(),
+        ..Config::default()
+    });
+
+    // when: rendering its page
+    let (_, page) = get_text(&app, "/works/2020/Clip").await;
+
+    // then: a player for a listed host, a plain link otherwise
+    assert_eq!(page.contains("<video"), embedded, "{}", page);
+}
+
+#[rstest]
+#[case::none(&[], "media-src 'self';")]
+#[case::hosts(&["cdn.example.com", "media.example.org:8443"], "media-src 'self' cdn.example.com media.example.org:8443;")]
+#[case::injection_dropped(&["cdn.example.com; script-src *"], "media-src 'self';")]
+#[tokio::test]
+async fn csp_allows_media_from_configured_hosts(#[case] hosts: &[&str], #[case] expected: &str) {
+    // given: the app with some media hosts configured
+    let app = build_app_with(Config {
+        media_hosts: hosts.iter().map(|h| h.to_string()).collect(),
+        ..Config::default()
+    });
+
+    // when: requesting any page
+    let response = app
+        .oneshot(Request::get("/").body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: the CSP's media-src lists them (and only well-formed ones) beside 'self'
+    let csp = response.headers()["content-security-policy"]
+        .to_str()
+        .unwrap();
+    assert!(csp.contains(expected), "{}", csp);
+}
+
+#[tokio::test]
+async fn draft_is_hidden_from_tree() {
+    // given: a published work, a draft beside it, and a year holding only a draft
+    let app = fixture_app("draft-tree", DRAFT_FIXTURE);
+
+    // when: fetching the tree
+    let (_, tree) = get_text(&app, "/api/tree").await;
+
+    // then: only the published work is listed, and 2023 has no empty branch
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    let years = tree["children"].as_array().unwrap();
+    assert_eq!(years.len(), 1);
+    assert_eq!(years[0]["path"], "/works/2024");
+    let works: Vec<&str> = years[0]["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| w["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(works, vec!["Alpha.md"]);
+}
+
+#[tokio::test]
+async fn draft_is_404_without_preview_token() {
+    // given: a draft work
+    let app = fixture_app("draft-404", DRAFT_FIXTURE);
+
+    // when: requesting its page, JSON and raw source directly
+    let (page, _) = get_text(&app, "/works/2024/Beta").await;
+    let (json, _) = get_text(&app, "/api/work/2024/Beta").await;
+    let (raw, _) = get_text(&app, "/raw/2024/Beta.md").await;
+
+    // then: all three answer as if it didn't exist
+    assert_eq!(page, StatusCode::NOT_FOUND);
+    assert_eq!(json, StatusCode::NOT_FOUND);
+    assert_eq!(raw, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn draft_is_visible_with_preview_token() {
+    // given: a preview secret, and a draft work
+    let app = build_app_with(Config {
+        works_dir: fixture_dir("draft-preview", DRAFT_FIXTURE),
+        draft_preview_token: Some("let-me-see".to_string()),
+        ..Config::default()
+    });
+
+    // when: requesting it with the right and a wrong token
+    let (ok, page) = get_text(&app, "/works/2024/Beta?preview=let-me-see").await;
+    let (wrong, _) = get_text(&app, "/works/2024/Beta?preview=nope").await;
+
+    // then: the right token renders it; a wrong one still 404s
+    assert_eq!(ok, StatusCode::OK);
+    assert!(page.contains("Beta body."));
+    assert_eq!(wrong, StatusCode::NOT_FOUND);
+}
+
+#[rstest]
+#[case::accessible(true)]
+#[case::missing(false)]
+#[tokio::test]
+async fn healthz_reports_works_dir_access(#[case] exists: bool) {
+    // given: an app whose works dir exists, or one pointed at a missing dir
+    let app = if exists {
+        fixture_app("healthz", &[("2020/Solo.md", "Body.")])
+    } else {
+        build_app_with(Config {
+            works_dir: std::env::temp_dir().join("lightvn-healthz-missing-dir"),
+            ..Config::default()
+        })
+    };
+
+    // when: probing /healthz
+    let (status, body) = get_text(&app, "/healthz").await;
+
+    // then: 200 either way, with the dir's accessibility reported
+    assert_eq!(status, StatusCode::OK);
+    let health: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(health["status"], "ok");
+    assert_eq!(health["works_dir_accessible"], exists);
+}
+
+#[tokio::test]
+async fn raw_media_serves_byte_ranges_uncompressed() {
+    // given: a 4 KiB video next to a work
+    let works_dir = fixture_dir("raw-range", &[("2024/Clip.md", "Body.")]);
+    let media: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(works_dir.join("2024/clip.mp4"), &media).unwrap();
+    let app = build_app_with(Config {
+        works_dir,
+        ..Config::default()
+    });
+    let get = |range: Option<&str>| {
+        let mut request = Request::get("/raw/2024/clip.mp4").header("accept-encoding", "gzip, br");
+        if let Some(range) = range {
+            request = request.header("range", range);
+        }
+        app.clone()
+            .oneshot(request.body(axum::body::Body::empty()).unwrap())
+    };
+
+    // when: requesting the first KiB, and the whole file
+    let ranged = get(Some("bytes=0-1023")).await.unwrap();
+    let full = get(None).await.unwrap();
+
+    // then:
+    // - the range is a 206 with the right Content-Range and bytes
+    // - neither response is compressed, and the full one still offers ranges
+    assert_eq!(ranged.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(ranged.headers()["content-range"], "bytes 0-1023/4096");
+    assert!(ranged.headers().get("content-encoding").is_none());
+    let body = axum::body::to_bytes(ranged.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], &media[..1024]);
+    assert_eq!(full.status(), StatusCode::OK);
+    assert!(full.headers().get("content-encoding").is_none());
+    assert_eq!(full.headers()["accept-ranges"], "bytes");
+}
+
+#[test]
+fn check_passes_a_clean_works_dir() {
+    // given: works linking to each other and to a local image, with an include
+    let works_dir = fixture_dir(
+        "check-clean",
+        &[
+            (
+                "2024/Alpha.md",
+                "---\ncreator: A\n---\n\nSee [Beta](Beta) and ![](cover.png).",
+            ),
+            (
+                "2024/Beta.md",
+                "{{ include: ../_shared/credits.md }}\n\nBack to [Alpha](/works/2024/Alpha).",
+            ),
+            ("2024/cover.png", "png"),
+            ("_shared/credits.md", "Credits."),
+        ],
+    );
+
+    // when: checking it
+    let report = check_works(&Config {
+        works_dir,
+        ..Config::default()
+    });
+
+    // then: no problems, exit status 0
+    assert_eq!(report.works, 2);
+    assert!(report.problems.is_empty(), "{}", report);
+    assert_eq!(report.exit_code(), 0);
+    assert!(report.to_string().contains("2 works checked, no problems"));
+}
+
+#[test]
+fn check_reports_broken_links_frontmatter_and_includes() {
+    // given: a broken link, unparsable frontmatter and a missing include
+    let works_dir = fixture_dir(
+        "check-broken",
+        &[
+            ("2024/Alpha.md", "See [Gone](/works/2024/Gone)."),
+            ("2024/Beta.md", "---\ntags: 5\n---\n\nBody."),
+            ("2024/Gamma.md", "{{ include: missing.md }}"),
+        ],
+    );
+
+    // when: checking it
+    let report = check_works(&Config {
+        works_dir,
+        ..Config::default()
+    });
+
+    // then: each problem is listed against its file, and the exit status is nonzero
+    let text = report.to_string();
+    assert_eq!(report.exit_code(), 1);
+    assert_eq!(report.problems.len(), 3, "{}", text);
+    assert!(
+        text.contains("2024/Alpha.md: broken link: /works/2024/Gone"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("2024/Beta.md: invalid frontmatter"),
+        "{}",
+        text
+    );
+    assert!(text.contains("2024/Gamma.md: include failed"), "{}", text);
+    assert!(text.ends_with("3 works checked, 3 problems\n"), "{}", text);
+}
+
+#[tokio::test]
+async fn serve_returns_after_shutdown_signal() {
+    // given: a server on an ephemeral port, answering requests
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (trigger, signal) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(serve(listener, build_app(), async {
+        let _ = signal.await;
+    }));
+    let health = reqwest::get(format!("http://{}/healthz", addr))
+        .await
+        .unwrap();
+    assert_eq!(health.status(), reqwest::StatusCode::OK);
+
+    // when: the shutdown signal fires
+    trigger.send(()).unwrap();
+
+    // then: the server future resolves cleanly instead of running forever
+    let result = tokio::time::timeout(std::time::Duration::from_secs(10), server)
+        .await
+        .expect("server did not shut down");
+    assert!(result.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn start_server_on_port_zero_reports_the_bound_port() {
+    // given: a config asking for an ephemeral port on loopback
+    let config = Config {
+        bind: "127.0.0.1".parse().unwrap(),
+        port: 0,
+        ..Config::default()
+    };
+    let (trigger, signal) = tokio::sync::oneshot::channel::<()>();
+
+    // when: starting the server
+    let (addr, server) = start_server(config, async {
+        let _ = signal.await;
+    })
+    .await
+    .unwrap();
+    let server = tokio::spawn(server);
+
+    // then: the returned address is the real one, and it answers /healthz
+    assert_ne!(addr.port(), 0);
+    let health = reqwest::get(format!("http://{}/healthz", addr))
+        .await
+        .unwrap();
+    assert_eq!(health.status(), reqwest::StatusCode::OK);
+    trigger.send(()).unwrap();
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn start_server_flushes_view_counts_on_shutdown() {
+    // given: a server persisting view counts, and a work viewed twice
+    let works_dir = fixture_dir("stats-flush", &[("2024/Seen.md", "Body.")]);
+    let stats_file = works_dir.join("_stats.json");
+    let config = Config {
+        works_dir,
+        stats_file: Some(stats_file.clone()),
+        bind: "127.0.0.1".parse().unwrap(),
+        port: 0,
+        ..Config::default()
+    };
+    let (trigger, signal) = tokio::sync::oneshot::channel::<()>();
+    let (addr, server) = start_server(config, async {
+        let _ = signal.await;
+    })
+    .await
+    .unwrap();
+    let server = tokio::spawn(server);
+    for _ in 0..2 {
+        let page = reqwest::get(format!("http://{}/works/2024/Seen", addr))
+            .await
+            .unwrap();
+        assert_eq!(page.status(), reqwest::StatusCode::OK);
+    }
+
+    // when: shutting down, well before the periodic flush
+    trigger.send(()).unwrap();
+    server.await.unwrap().unwrap();
+
+    // then: the counts are on disk
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&stats_file).unwrap()).unwrap();
+    assert_eq!(saved["/works/2024/Seen"], 2);
+}
+
+#[tokio::test]
+async fn start_server_warms_the_tree_cache_in_the_background() {
+    // given: a server started on an ephemeral port
+    let config = Config {
+        works_dir: fixture_dir("tree-warmup", &[("2024/Warm.md", "Body.")]),
+        bind: "127.0.0.1".parse().unwrap(),
+        port: 0,
+        ..Config::default()
+    };
+    let (trigger, signal) = tokio::sync::oneshot::channel::<()>();
+    let (addr, server) = start_server(config, async {
+        let _ = signal.await;
+    })
+    .await
+    .unwrap();
+    let server = tokio::spawn(server);
+
+    // when: waiting without requesting the tree or the homepage
+    let mut warm = false;
+    for _ in 0..50 {
+        let body = reqwest::get(format!("http://{}/healthz", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let health: serde_json::Value = serde_json::from_str(&body).unwrap();
+        warm = health["tree_cache_warm"] == true;
+        if warm {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    // then: the tree cache fills on its own
+    assert!(warm, "tree cache not warmed within 5s");
+    trigger.send(()).unwrap();
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn series_chapter_links_to_previous_and_next() {
+    // given: a three-chapter series
+    let app = fixture_app(
+        "work-nav",
+        &[
+            ("2023/Series/Chapter 1.md", "One."),
+            ("2023/Series/Chapter 2.md", "Two."),
+            ("2023/Series/Chapter 3.md", "Three."),
+        ],
+    );
+
+    // when: rendering the first and the middle chapter
+    let (_, first) = get_text(&app, "/works/2023/Series/Chapter%201?lang=en").await;
+    let (_, middle) = get_text(&app, "/works/2023/Series/Chapter%202?lang=en").await;
+
+    // then:
+    // - the first chapter only links forward
+    // - the middle one links both ways, keeping ?lang
+    assert!(!first.contains(r#"rel="prev""#));
+    assert!(first.contains(r#"href="/works/2023/Series/Chapter%202?lang=en""#));
+    assert!(middle.contains(
+        r#"<a href="/works/2023/Series/Chapter%201?lang=en" class="work-nav-prev" rel="prev">"#
+    ));
+    assert!(middle.contains(
+        r#"<a href="/works/2023/Series/Chapter%203?lang=en" class="work-nav-next" rel="next">"#
+    ));
+}
+
+const TAGGED_FIXTURE: &[(&str, &str)] = &[
+    ("2023/Old Flame.md", "---\ntags: [Romance]\n---\n\nBody."),
+    (
+        "2024/Hearts.md",
+        "---\ntags: [romance, comedy]\n---\n\nBody.",
+    ),
+    ("2024/Ghosts.md", "---\ntags: [horror]\n---\n\nBody."),
+    ("2025/Plain.md", "Body."),
+];
+
+#[tokio::test]
+async fn tags_endpoint_counts_works_per_tag() {
+    // given: a small tagged catalog
+    let app = fixture_app("api-tags", TAGGED_FIXTURE);
+
+    // when: requesting /api/tags
+    let (status, body) = get_text(&app, "/api/tags").await;
+
+    // then: one lowercased entry per tag, counting works across casings
+    assert_eq!(status, StatusCode::OK);
+    let counts: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        counts,
+        serde_json::json!({ "comedy": 1, "horror": 1, "romance": 2 })
+    );
+}
+
+#[tokio::test]
+async fn tree_filters_by_tag_and_prunes_empty_years() {
+    // given: a small tagged catalog
+    let app = fixture_app("tree-tag", TAGGED_FIXTURE);
+
+    // when: requesting the tree for a tag, in a different case than written
+    let (_, body) = get_text(&app, "/api/tree?tag=ROMANCE").await;
+
+    // then: only the romance works, under the two years that have one
+    let tree: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let works: Vec<&str> = tree["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|year| year["children"].as_array().unwrap())
+        .map(|w| w["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        works,
+        vec!["/works/2023/Old Flame.md", "/works/2024/Hearts.md"]
+    );
+}
+
+#[tokio::test]
+async fn katex_loads_only_on_pages_with_math() {
+    // given: one work with math and one without
+    let app = fixture_app(
+        "katex",
+        &[
+            ("2024/Proof.md", "Let $x = 1$."),
+            ("2024/Prose.md", "It costs \\$5."),
+        ],
+    );
+
+    // when: rendering both
+    let (_, proof) = get_text(&app, "/works/2024/Proof").await;
+    let (_, prose) = get_text(&app, "/works/2024/Prose").await;
+
+    // then: KaTeX + auto-render are injected for the math page only
+    assert!(proof.contains(r#"<span class="math inline">\(x = 1\)</span>"#));
+    assert!(proof.contains("katex.min.css"));
+    assert!(proof.contains("contrib/auto-render.min.js"));
+    assert!(!prose.contains("katex"));
+}
+
+#[tokio::test]
+async fn mermaid_loads_only_on_pages_with_diagrams() {
+    // given: one work with a mermaid fence and one with plain code
+    let app = fixture_app(
+        "mermaid",
+        &[
+            ("2024/Flow.md", "```mermaid\ngraph TD\n  A-->B\n```\n"),
+            ("2024/Code.md", "```text\nA-->B\n```\n"),
+        ],
+    );
+
+    // when: rendering both
+    let (_, flow) = get_text(&app, "/works/2024/Flow").await;
+    let (_, code) = get_text(&app, "/works/2024/Code").await;
+
+    // then: the diagram block and the Mermaid script are on the diagram page only
+    assert!(flow.contains(r#"<pre class="mermaid">"#));
+    assert!(flow.contains("mermaid.min.js"));
+    assert!(!code.contains("mermaid"));
+}
+
+#[tokio::test]
+async fn markdown_extension_works_are_listed_and_served() {
+    // given: a .markdown work, and a .md/.markdown pair sharing a URL
+    let app = fixture_app(
+        "markdown-ext",
+        &[
+            ("2024/Long Form.markdown", "# Long\n\nWritten *long*."),
+            ("2024/Both.markdown", "From the long file."),
+            ("2024/Both.md", "From the short file."),
+        ],
+    );
+
+    // when: listing the tree and rendering both works
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (status, long_form) = get_text(&app, "/works/2024/Long%20Form").await;
+    let (_, both) = get_text(&app, "/works/2024/Both").await;
+
+    // then:
+    // - the tree keeps the source extension, as it does for .md
+    // - the page renders the markdown
+    // - when both exist, the .md is served
+    assert!(
+        tree.contains(r#""path":"/works/2024/Long Form.markdown""#),
+        "{tree}"
+    );
+    assert_eq!(status, StatusCode::OK);
+    assert!(long_form.contains("Written <em>long</em>."));
+    assert!(both.contains("From the short file."));
+    assert!(!both.contains("From the long file."));
+}
+
+#[tokio::test]
+async fn nested_work_breadcrumb_links_each_segment_in_order() {
+    // given: a work two levels below its year
+    let app = fixture_app("breadcrumb", &[("2023/Series/Chapter.md", "Body.")]);
+
+    // when: rendering it
+    let (_, page) = get_text(&app, "/works/2023/Series/Chapter").await;
+
+    // then: Home / 2023 / Series are links, in that order; the title is not
+    let nav = page
+        .split(r#"<nav class="breadcrumb">"#)
+        .nth(1)
+        .and_then(|rest| rest.split("</nav>").next())
+        .unwrap();
+    let hrefs: Vec<&str> = nav
+        .split(r#"href=""#)
+        .skip(1)
+        .filter_map(|s| s.split('"').next())
+        .collect();
+    assert_eq!(hrefs, vec!["/", "/#2023", "/?search=Series/#2023"]);
+    assert!(nav.trim_end().ends_with("Chapter"));
+}
+
+#[rstest]
+#[case::full(None, StatusCode::OK, "0123456789")]
+#[case::range(Some("bytes=2-5"), StatusCode::PARTIAL_CONTENT, "2345")]
+#[tokio::test]
+async fn raw_assets_are_cached_for_a_day(
+    #[case] range: Option<&str>,
+    #[case] status: StatusCode,
+    #[case] body: &str,
+) {
+    // given: a work with a non-image asset beside it
+    let app = fixture_app(
+        "raw-cache",
+        &[("2024/Song.md", "Body."), ("2024/theme.mp3", "0123456789")],
+    );
+
+    // when: fetching the asset, whole or by range
+    let mut request = Request::get("/raw/2024/theme.mp3");
+    if let Some(range) = range {
+        request = request.header("range", range);
+    }
+    let response = app
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: ServeDir's range handling is intact, and the asset is cacheable
+    assert_eq!(response.status(), status);
+    assert_eq!(
+        response.headers().get("cache-control").unwrap(),
+        "public, max-age=86400"
+    );
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&bytes[..], body.as_bytes());
+}
+
+#[rstest]
+#[case::source("/raw/2024/Song.md")]
+#[case::missing_asset("/raw/2024/missing.png")]
+#[tokio::test]
+async fn raw_sources_and_misses_revalidate(#[case] uri: &str) {
+    // given: a work
+    let app = fixture_app("raw-no-cache", &[("2024/Song.md", "Body.")]);
+
+    // when: fetching its source, or an asset that isn't there
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: neither is pinned in browser caches
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache");
+}
+
+#[rstest]
+#[case::default(None, "no-cache")]
+#[case::override_html(Some("text/html=60"), "public, max-age=60")]
+#[tokio::test]
+async fn cache_policy_follows_config(#[case] policy: Option<&str>, #[case] expected: &str) {
+    // given: a work, served with or without a cache policy override
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            &format!("cache-policy-{}", policy.is_some()),
+            &[("2024/Song.md", "Body.")],
+        ),
+        cache_policy: policy.map(str::to_string),
+        ..Config::default()
+    });
+
+    // when: fetching its page
+    let response = app
+        .oneshot(
+            Request::get("/works/2024/Song")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: HTML gets the configured max-age, or revalidates by default
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("cache-control").unwrap(), expected);
+}
+
+#[tokio::test]
+async fn export_writes_an_index_html_per_work() {
+    // given: a works dir with flat, nested, .markdown and draft works
+    let works = std::env::temp_dir().join(format!("lightvn-export-src-{}", std::process::id()));
+    let out = std::env::temp_dir().join(format!("lightvn-export-out-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&works);
+    let _ = std::fs::remove_dir_all(&out);
+    for (path, body) in [
+        ("2023/First Light.md", "One."),
+        ("2024/Series/Chapter 1.md", "Two."),
+        ("2024/Long.markdown", "Three."),
+        ("2024/Secret.md", "---\ndraft: true\n---\n\nHidden."),
+    ] {
+        let file = works.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, body).unwrap();
+    }
+
+    // when: exporting the site
+    let config = Config {
+        works_dir: works.clone(),
+        ..Config::default()
+    };
+    let pages = export_site(config, &out).await.unwrap();
+
+    // then:
+    // - every listed work has a page, with links relative to it; drafts don't
+    // - the home page, the tree, public/ and works/ (as raw/) are there too
+    for work in ["2023/First Light", "2024/Series/Chapter 1", "2024/Long"] {
+        let page = out.join("works").join(work).join("index.html");
+        assert!(page.is_file(), "no page for {work}");
+    }
+    assert!(!out.join("works/2024/Secret").exists());
+    assert!(!out.join("raw/2024/Secret.md").exists());
+    assert!(out.join("raw/2024/Long.markdown").is_file());
+    assert!(out.join("index.html").is_file());
+    assert!(out.join("api/tree.json").is_file());
+    assert!(out.join("lvn_icon.webp").is_file());
+    assert!(pages >= 4);
+    let chapter =
+        std::fs::read_to_string(out.join("works/2024/Series/Chapter 1/index.html")).unwrap();
+    assert!(chapter.contains(r#"href="../../../../#2024""#), "{chapter}");
+    assert!(!chapter.contains(r#"href="/"#));
+    let stylesheet = chapter
+        .split(r#"<link rel="stylesheet" href=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    let stylesheet = out.join("works/2024/Series/Chapter 1").join(stylesheet);
+    assert!(stylesheet.is_file(), "{}", stylesheet.display());
+}
+
+#[rstest]
+#[case::casing("/works/2023/my%20GAME", "/works/2023/My%20Game")]
+#[case::space_for_hyphen("/works/2023/ghost%20story?lang=ja", "/works/2023/ghost-story?lang=ja")]
+#[tokio::test]
+async fn loosely_typed_titles_redirect_to_the_work(#[case] uri: &str, #[case] location: &str) {
+    // given: works named with spaces and with hyphens
+    let app = fixture_app(
+        "loose-title",
+        &[("2023/My Game.md", "One."), ("2023/ghost-story.md", "Two.")],
+    );
+
+    // when: requesting one with different casing or spacing
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: a redirect to the real URL, query kept
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers().get("location").unwrap(), location);
+}
+
+#[tokio::test]
+async fn unmatched_loose_title_is_the_plain_404() {
+    // given: a work, and a draft that only folds alike
+    let app = fixture_app(
+        "loose-title-404",
+        &[
+            ("2023/My Game.md", "One."),
+            ("2023/secret-plan.md", "---\ndraft: true\n---\n\nHidden."),
+        ],
+    );
+
+    // when: requesting a title that matches nothing, and the draft loosely
+    let (missing_status, missing) = get_text(&app, "/works/2023/Nope").await;
+    let (draft_status, draft) = get_text(&app, "/works/2023/Secret%20Plan").await;
+
+    // then: both get the ordinary not-found page, naming what was asked for
+    assert_eq!(missing_status, StatusCode::NOT_FOUND);
+    assert_eq!(draft_status, StatusCode::NOT_FOUND);
+    assert_eq!(draft, missing.replace("Nope", "Secret Plan"));
+}
+
+#[tokio::test]
+async fn work_theme_overrides_page_colours() {
+    // given: a work with a theme in its frontmatter, one value an injection attempt
+    let app = fixture_app(
+        "work-theme",
+        &[
+            (
+                "2023/Themed.md",
+                "---\ntheme:\n  bg: \"#102030\"\n  text: \"red</style><script>x()</script>\"\n  accent: gold\n---\n\nBody.",
+            ),
+            ("2023/Plain.md", "Body."),
+        ],
+    );
+
+    // when: rendering it and a work without a theme
+    let (_, themed) = get_text(&app, "/works/2023/Themed").await;
+    let (_, plain) = get_text(&app, "/works/2023/Plain").await;
+
+    // then: the valid colours override :root, the unsafe one is dropped
+    assert!(themed.contains("<style>:root{--bg:#102030;--accent:gold;}</style>"));
+    assert!(!themed.contains("x()"));
+    assert!(!plain.contains("<style>:root"));
+}
+
+#[tokio::test]
+async fn api_tree_without_works_dir_is_503_json() {
+    // given: an app pointed at a works dir that doesn't exist
+    let app = fixture_app("tree-missing-dir", &[]);
+
+    // when: requesting /api/tree
+    let (status, body) = get_text(&app, "/api/tree").await;
+
+    // then: 503 with a JSON error naming the dir, not a fake empty tree
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(error["error"].as_str().is_some_and(|e| !e.is_empty()));
+    assert!(error["works_dir"]
+        .as_str()
+        .is_some_and(|d| d.ends_with(&format!("lightvn-tree-missing-dir-{}", std::process::id()))));
+    assert!(error.get("children").is_none());
+}
+
+#[tokio::test]
+async fn api_tree_with_empty_works_dir_is_empty_root() {
+    // given: an app pointed at an existing but empty works dir
+    let app = fixture_app("tree-empty-dir", &[]);
+    let dir = std::env::temp_dir().join(format!("lightvn-tree-empty-dir-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // when: requesting /api/tree
+    let (status, body) = get_text(&app, "/api/tree").await;
+
+    // then: 200 with a root that has no years
+    assert_eq!(status, StatusCode::OK);
+    let tree: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(tree["path"], "/works");
+    assert!(tree["children"].as_array().is_none_or(|c| c.is_empty()));
+}
+
+const DATED_FIXTURE: &[(&str, &str)] = &[
+    ("2023/Old.md", "---\nreleased: 2023/02/01\n---\n\nOld."),
+    ("2024/Alpha.md", "---\nreleased: 2024/01/10\n---\n\nAlpha."),
+    ("2024/Beta.md", "---\nreleased: 2024/03/05\n---\n\nBeta."),
+    (
+        "2024/Gamma.md",
+        "---\ndate_added: 2024/12/24\n---\n\nGamma.",
+    ),
+];
+
+fn tree_order(tree: &serde_json::Value) -> Vec<String> {
+    let mut out = vec![];
+    for year in tree["children"].as_array().unwrap() {
+        out.push(year["name"].as_str().unwrap().to_string());
+        for work in year["children"].as_array().unwrap() {
+            out.push(work["name"].as_str().unwrap().to_string());
+        }
+    }
+    out
+}
+
+#[rstest]
+#[case::name("/api/tree?sort=name", &["2023", "Old.md", "2024", "Alpha.md", "Beta.md", "Gamma.md"])]
+#[case::name_desc("/api/tree?sort=name&order=desc", &["2024", "Gamma.md", "Beta.md", "Alpha.md", "2023", "Old.md"])]
+#[case::date_asc("/api/tree?sort=date", &["2023", "Old.md", "2024", "Alpha.md", "Beta.md", "Gamma.md"])]
+#[case::date_desc("/api/tree?sort=date&order=desc", &["2024", "Gamma.md", "Beta.md", "Alpha.md", "2023", "Old.md"])]
+#[tokio::test]
+async fn api_tree_sort_orders_each_level(#[case] uri: &str, #[case] expected: &[&str]) {
+    // given: works dated by released/date_added, named in date order
+    let app = fixture_app("tree-sort", DATED_FIXTURE);
+
+    // when: requesting the tree sorted, and unsorted
+    let (status, sorted) = get_text(&app, uri).await;
+    let (_, default) = get_text(&app, "/api/tree").await;
+
+    // then: each level is in the requested order, and sort=name is today's tree
+    assert_eq!(status, StatusCode::OK);
+    let sorted: serde_json::Value = serde_json::from_str(&sorted).unwrap();
+    assert_eq!(tree_order(&sorted), expected);
+    if uri.ends_with("sort=name") {
+        assert_eq!(
+            sorted,
+            serde_json::from_str::<serde_json::Value>(&default).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_tree_date_sort_reverses_dated_works() {
+    // given: works whose names run against their dates
+    let app = fixture_app(
+        "tree-sort-dates",
+        &[
+            ("2024/A.md", "---\nreleased: 2024/09/01\n---\n\nNewest."),
+            ("2024/B.md", "---\nreleased: 2024/05/01\n---\n\nMiddle."),
+            ("2024/C.md", "---\nreleased: 2024/01/01\n---\n\nOldest."),
+        ],
+    );
+
+    // when: sorting by date both ways
+    let (_, asc) = get_text(&app, "/api/tree?sort=date").await;
+    let (_, desc) = get_text(&app, "/api/tree?sort=date&order=desc").await;
+
+    // then: ascending is oldest first, descending its exact reverse
+    let asc = tree_order(&serde_json::from_str(&asc).unwrap());
+    let desc = tree_order(&serde_json::from_str(&desc).unwrap());
+    assert_eq!(asc, vec!["2024", "C.md", "B.md", "A.md"]);
+    assert_eq!(desc, vec!["2024", "A.md", "B.md", "C.md"]);
+}
+
+#[tokio::test]
+async fn api_tree_unknown_sort_is_400() {
+    // given: the app
+    let app = fixture_app("tree-sort-bad", DATED_FIXTURE);
+
+    // when: asking for a sort key that doesn't exist
+    let (status, body) = get_text(&app, "/api/tree?sort=size").await;
+
+    // then: 400 naming the bad value
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("unknown sort: size"));
+}
+
+#[tokio::test]
+async fn directory_with_index_md_shows_it_above_the_listing() {
+    // given: a year with an index.md, two works and a series folder
+    let app = fixture_app(
+        "dir-index",
+        &[
+            (
+                "2023/index.md",
+                "---\ntitle: Jam 2023\n---\n\nEntries from the **2023** jam.",
+            ),
+            ("2023/Alpha.md", "Alpha."),
+            ("2023/Beta.md", "Beta."),
+            ("2023/Saga/Part 1.md", "Part one."),
+        ],
+    );
+
+    // when: browsing to the year, and fetching the tree
+    let (status, page) = get_text(&app, "/works/2023").await;
+    let (_, tree) = get_text(&app, "/api/tree").await;
+
+    // then: the index content, its title, the subfolder and a card per work
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("<h1>Jam 2023</h1>"));
+    assert!(page.contains("<strong>2023</strong> jam."));
+    assert!(page.contains(r#"href="/works/2023/Saga""#));
+    assert!(page.contains(r#"href="/works/2023/Alpha""#));
+    assert!(page.contains(r#"href="/works/2023/Beta""#));
+    // - The index is the directory's page, not a work of its own.
+    assert!(!tree.contains("index.md"));
+    let (index_status, _) = get_text(&app, "/works/2023/index").await;
+    assert_eq!(index_status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn directory_without_index_md_gets_a_generated_listing() {
+    // given: a series folder without an index.md
+    let app = fixture_app(
+        "dir-listing",
+        &[
+            ("2023/Saga/Part 1.md", "Part one."),
+            ("2023/Saga/Part 2.md", "Part two."),
+        ],
+    );
+
+    // when: browsing to the folder, and to one that doesn't exist
+    let (status, page) = get_text(&app, "/works/2023/Saga").await;
+    let (missing_status, _) = get_text(&app, "/works/2023/Nothing").await;
+
+    // then: a listing named after the folder instead of a 404; unknown paths still 404
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("<h1>Saga</h1>"));
+    assert!(page.contains("2 works"));
+    assert!(page.contains(r#"href="/works/2023/Saga/Part%201""#));
+    assert!(!page.contains(r#"class="synopsis""#));
+    assert_eq!(missing_status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn api_tree_nodes_carry_word_and_char_counts() {
+    // given: a work with frontmatter, prose and a code fence
+    let source =
+        "---\ncreator: Tester\n---\n\nOne two three.\n\n```\nnot counted\n```\n\nFour five.\n";
+    let app = fixture_app("tree-counts", &[("2024/Counted.md", source)]);
+
+    // when: fetching the tree, and the work on its own
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (_, detail) = get_text(&app, "/api/work/2024/Counted").await;
+
+    // then: the work node counts prose words only (as /api/work does), and every body character
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    let year = &tree["children"][0];
+    let work = &year["children"][0];
+    let (_, body) = parse_frontmatter(source);
+    assert_eq!(work["word_count"], 5);
+    let detail: serde_json::Value = serde_json::from_str(&detail).unwrap();
+    assert_eq!(detail["word_count"], work["word_count"]);
+    assert_eq!(work["char_count"], body.chars().count());
+    assert_eq!(work["char_count"], 48);
+    assert!(year.get("word_count").is_none());
+}
+
+#[tokio::test]
+async fn api_tree_nodes_carry_an_excerpt() {
+    // given: one work with a description and one without
+    let app = fixture_app(
+        "tree-excerpt",
+        &[
+            (
+                "2024/Described.md",
+                "---\ndescription: Hand-written summary.\n---\n\nBody text.\n",
+            ),
+            ("2024/Plain.md", "# Plain\n\nOpening paragraph.\n"),
+        ],
+    );
+
+    // when: fetching the tree
+    let (_, tree) = get_text(&app, "/api/tree").await;
+
+    // then: each work node carries its excerpt; year nodes don't
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    let year = &tree["children"][0];
+    assert_eq!(year["children"][0]["excerpt"], "Hand-written summary.");
+    assert_eq!(year["children"][1]["excerpt"], "Opening paragraph.");
+    assert!(year.get("excerpt").is_none());
+}
+
+#[tokio::test]
+async fn linkcheck_reports_only_broken_internal_links() {
+    // given: a work linking to a real work, a missing one, and an external site
+    let app = fixture_app(
+        "linkcheck",
+        &[
+            (
+                "2024/Links.md",
+                "See [Other](/works/2024/Other), [Gone](/works/2024/Gone) and [site](https://example.com/).",
+            ),
+            ("2024/Other.md", "Other."),
+        ],
+    );
+
+    // when: requesting the link report
+    let (status, body) = get_text(&app, "/api/linkcheck").await;
+
+    // then: only the missing work is broken; the external link is listed, not checked
+    assert_eq!(status, StatusCode::OK);
+    let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        report["broken"],
+        serde_json::json!([{ "work": "/works/2024/Links", "target": "/works/2024/Gone" }])
+    );
+    assert_eq!(
+        report["external"],
+        serde_json::json!([{ "work": "/works/2024/Links", "target": "https://example.com/" }])
+    );
+    assert_eq!(report["checked"], 2);
+}
+
+#[tokio::test]
+async fn linkcheck_and_link_graph_leave_out_drafts() {
+    // given: a draft with a broken link, linked from a published work
+    let app = fixture_app(
+        "linkcheck-drafts",
+        &[
+            ("2024/Public.md", "See [Secret](/works/2024/Secret)."),
+            (
+                "2024/Secret.md",
+                "---\ndraft: true\n---\nSee [Gone](/works/2024/Gone).",
+            ),
+        ],
+    );
+
+    // when: requesting the link report and the link graph
+    let (_, report) = get_text(&app, "/api/linkcheck").await;
+    let (_, dot) = get_text(&app, "/api/linkgraph.dot").await;
+
+    // then: neither names the draft
+    assert!(!report.contains("Secret"), "{}", report);
+    assert!(!dot.contains("Secret"), "{}", dot);
+    assert!(dot.contains("/works/2024/Public"));
+}
+
+#[rstest]
+#[case::first_page("page=1&per_page=2", &["/works/2023/Old.md", "/works/2024/Alpha.md"])]
+#[case::last_partial_page("page=2&per_page=3", &["/works/2024/Gamma.md"])]
+#[case::out_of_range("page=9&per_page=2", &[])]
+#[case::defaults("", &["/works/2023/Old.md", "/works/2024/Alpha.md", "/works/2024/Beta.md", "/works/2024/Gamma.md"])]
+#[tokio::test]
+async fn api_tree_flat_pages_through_works(#[case] query: &str, #[case] expected: &[&str]) {
+    // given: four works across two years
+    let app = fixture_app("tree-flat", DATED_FIXTURE);
+
+    // when: requesting a page of the flat listing
+    let (status, body) = get_text(&app, &format!("/api/tree?flat=true&{}", query)).await;
+
+    // then: that slice of works in tree order, unnested, with the full total
+    assert_eq!(status, StatusCode::OK);
+    let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let paths: Vec<&str> = page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, expected);
+    assert_eq!(page["total"], 4);
+    assert!(page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|item| item["is_dir"] == false));
+}
+
+#[rstest]
+#[case::not_found(AppError::NotFound("2023/Nope.md".into()), StatusCode::NOT_FOUND, "not found: 2023/Nope.md")]
+#[case::bad_request(AppError::BadRequest("unknown sort: x".into()), StatusCode::BAD_REQUEST, "unknown sort: x")]
+#[case::io(
+    AppError::Io {
+        path: "/srv/works/2024/A.md".into(),
+        source: std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+    },
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "internal error"
+)]
+#[case::works_dir_unavailable(
+    AppError::WorksDirUnavailable {
+        works_dir: "/srv/works".into(),
+        source: std::io::Error::from(std::io::ErrorKind::NotFound),
+    },
+    StatusCode::SERVICE_UNAVAILABLE,
+    "works directory unavailable: entity not found"
+)]
+#[case::internal(AppError::Internal("template exploded".into()), StatusCode::INTERNAL_SERVER_ERROR, "internal error")]
+#[case::too_large(AppError::TooLarge("2024/Dump".into()), StatusCode::PAYLOAD_TOO_LARGE, "too large to render: 2024/Dump")]
+#[tokio::test]
+async fn app_error_maps_to_status_and_json_body(
+    #[case] error: AppError,
+    #[case] status: StatusCode,
+    #[case] message: &str,
+) {
+    // given: an AppError variant
+    let works_dir = matches!(error, AppError::WorksDirUnavailable { .. });
+
+    // when: turning it into a response
+    let response = axum::response::IntoResponse::into_response(error);
+
+    // then: its status, and a JSON body with the message (WorksDirUnavailable
+    // adds the dir); an Io error's path and cause stay in the log
+    assert_eq!(response.status(), status);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"], message);
+    assert_eq!(json.get("works_dir").is_some(), works_dir);
+    assert!(json.get("path").is_none());
+    assert!(!String::from_utf8_lossy(&body).contains("A.md"));
+}
+
+#[rstest]
+#[case::not_found(AppError::NotFound("2023/<b>Nope</b>.md".into()), "<code>2023/&#60;b&#62;Nope&#60;/b&#62;.md</code>")]
+#[case::bad_request(AppError::BadRequest("Invalid year or title".into()), "<h1>400 Bad Request</h1>")]
+#[case::internal(AppError::Internal("template exploded".into()), "<h1>500 Internal Server Error</h1>")]
+fn app_error_html_page_names_the_error(#[case] error: AppError, #[case] expected: &str) {
+    // when: rendering the HTML alternative
+    let page = error.html_page();
+
+    // then: a full page naming the error; internal details stay out
+    assert!(page.contains("<!DOCTYPE html>"));
+    assert!(page.contains(expected), "{}", page);
+    assert!(!page.contains("exploded"));
+}
+
+#[rstest]
+#[case::browser("text/html,application/xhtml+xml,*/*;q=0.8", "text/html")]
+#[case::no_accept("", "text/html")]
+#[case::json_client("application/json", "application/json")]
+#[case::json_preferred("text/html;q=0.5, application/json", "application/json")]
+#[tokio::test]
+async fn missing_work_error_is_negotiated_by_accept(#[case] accept: &str, #[case] expected: &str) {
+    // given: an app with one work
+    let app = fixture_app("error-negotiation", &[("2023/Real.md", "Body.")]);
+
+    // when: requesting a work that doesn't exist
+    let mut request = Request::get("/works/2023/Nope");
+    if !accept.is_empty() {
+        request = request.header("accept", accept);
+    }
+    let response = app
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: a 404 in the preferred format, varying on Accept
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let content_type = response.headers()["content-type"].to_str().unwrap();
+    assert!(content_type.starts_with(expected), "{}", content_type);
+    assert_eq!(response.headers()["vary"], "accept");
+}
+
+#[tokio::test]
+async fn api_errors_stay_json_for_browsers() {
+    // given: an app with one work
+    let app = fixture_app("error-api-json", &[("2023/Real.md", "Body.")]);
+
+    // when: a browser asks /api/tree for an unknown sort
+    let response = app
+        .oneshot(
+            Request::get("/api/tree?sort=bogus")
+                .header("accept", "text/html")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: 400 with the JSON error shape
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"], "unknown sort: bogus");
+}
+
+/// - App over a scratch works dir with a 1200×600 PNG at `path`, caching
+///   resized thumbs in its own scratch dir (returned alongside).
+fn local_thumb_app(name: &str, path: &str) -> (axum::Router, std::path::PathBuf) {
+    let dir = std::env::temp_dir().join(format!("lightvn-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let file = dir.join("works").join(path);
+    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+    image::RgbImage::new(1200, 600).save(file).unwrap();
+    let cache = dir.join("cache");
+    let app = build_app_with(Config {
+        works_dir: dir.join("works"),
+        thumb_cache_dir: cache.clone(),
+        ..Config::default()
+    });
+    (app, cache)
+}
+
+async fn get_bytes(app: &axum::Router, uri: &str) -> (StatusCode, String, Vec<u8>) {
+    let response = app
+        .clone()
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .map(|v| v.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, content_type, body.to_vec())
+}
+
+#[rstest]
+#[case::nested("local-thumb-nested", "2023/Series/cover.png", 320)]
+#[case::two_segments("local-thumb-flat", "2023/cover.png", 320)]
+#[case::snapped_up("local-thumb-snapped", "2023/cover.png", 300)]
+#[tokio::test]
+async fn local_thumb_resizes_to_requested_width(
+    #[case] name: &str,
+    #[case] path: &str,
+    #[case] w: u32,
+) {
+    // given: a 1200×600 PNG under the works dir
+    let (app, _) = local_thumb_app(name, path);
+
+    // when: asking the proxy for it 320 wide (or a width that snaps to 320)
+    let (status, content_type, body) = get_bytes(&app, &format!("/thumb/{}?w={}", path, w)).await;
+
+    // then: a PNG scaled to 320×160
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "image/png");
+    let img = image::load_from_memory(&body).unwrap();
+    assert_eq!((img.width(), img.height()), (320, 160));
+}
+
+#[tokio::test]
+async fn local_thumb_serves_cached_copy() {
+    // given: an image already resized once
+    let (app, cache) = local_thumb_app("local-thumb-cache", "2023/Series/cover.png");
+    let (_, _, first) = get_bytes(&app, "/thumb/2023/Series/cover.png?w=320").await;
+    let entries: Vec<_> = std::fs::read_dir(&cache)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(std::fs::read(&entries[0]).unwrap(), first);
+
+    // when: the cached entry is swapped out and the same thumb is asked for again
+    std::fs::write(&entries[0], b"cached bytes").unwrap();
+    let (status, _, second) = get_bytes(&app, "/thumb/2023/Series/cover.png?w=320").await;
+
+    // then: the cache entry is served, not a fresh resize
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second, b"cached bytes");
+}
+
+#[rstest]
+#[case::oversized("w=4096", StatusCode::BAD_REQUEST)]
+#[case::zero("w=0", StatusCode::BAD_REQUEST)]
+#[case::not_a_number("w=big", StatusCode::BAD_REQUEST)]
+#[tokio::test]
+async fn local_thumb_rejects_bad_widths(#[case] query: &str, #[case] expected: StatusCode) {
+    // given: a local image
+    let (app, cache) = local_thumb_app("local-thumb-bad-width", "2023/Series/cover.png");
+
+    // when: asking for an out-of-range width
+    let (status, _, _) = get_bytes(&app, &format!("/thumb/2023/Series/cover.png?{}", query)).await;
+
+    // then: rejected before any resize, so nothing is cached
+    assert_eq!(status, expected);
+    assert!(!cache.exists());
+}
+
+#[tokio::test]
+async fn tree_thumbnails_for_local_images_use_the_resize_proxy() {
+    // given: a work whose first image is a local PNG
+    let app = fixture_app(
+        "local-thumb-tree",
+        &[("2023/Shots.md", "![Cover](cover.png)\n\nBody.")],
+    );
+
+    // when: fetching the tree
+    let (_, body) = get_text(&app, "/api/tree").await;
+
+    // then: card and ribbon point at the proxy, not the full-size /raw/ file
+    let tree: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let work = &tree["children"][0]["children"][0];
+    assert_eq!(work["thumbnail"], "/thumb/2023/cover.png?w=600");
+    assert_eq!(work["thumbnail_ribbon"], "/thumb/2023/cover.png?w=600");
+}
+
+#[tokio::test]
+async fn work_json_outline_lists_headings_with_anchor_slugs() {
+    // given: a work with an h1, repeated h2s and an h3
+    let app = fixture_app(
+        "work-outline",
+        &[(
+            "2023/Outlined.md",
+            "# Prologue\n\n## Intro\n\n### Route A\n\n## Intro\n\nEnd.",
+        )],
+    );
+
+    // when: fetching its JSON and its page
+    let (_, body) = get_text(&app, "/api/work/2023/Outlined").await;
+    let (_, page) = get_text(&app, "/works/2023/Outlined").await;
+
+    // then: a flat outline with levels and unique slugs, each an id on the page
+    let work: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let outline: Vec<(u64, &str, &str)> = work["headings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| {
+            (
+                h["level"].as_u64().unwrap(),
+                h["text"].as_str().unwrap(),
+                h["slug"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        outline,
+        vec![
+            (1, "Prologue", "prologue"),
+            (2, "Intro", "intro"),
+            (3, "Route A", "route-a"),
+            (2, "Intro", "intro-2"),
+        ]
+    );
+    for (_, _, slug) in &outline {
+        assert!(page.contains(&format!(r#"id="{}""#, slug)), "{}", slug);
+    }
+}
+
+#[rstest]
+#[case::plain("---\ncreator: Tester\nreleased: 2024/01/02\n---\n\nOnly the body.")]
+#[case::crlf("---\r\ncreator: Tester\r\nreleased: 2024/01/02\r\n---\r\n\r\nOnly the body.")]
+#[case::bom("\u{feff}---\ncreator: Tester\nreleased: 2024/01/02\n---\n\nOnly the body.")]
+#[tokio::test]
+async fn frontmatter_stays_out_of_the_rendered_body(#[case] source: &str) {
+    // given: a work whose file starts with a frontmatter block
+    let app = fixture_app("frontmatter-body", &[("2024/Fronted.md", source)]);
+
+    // when: rendering its page and fetching its raw source
+    let (_, page) = get_text(&app, "/works/2024/Fronted?lang=en").await;
+    let (_, raw) = get_text(&app, "/raw/2024/Fronted.md").await;
+
+    // then: the body has no stray rule or YAML keys; /raw keeps the file as committed
+    assert!(page.contains("Only the body."));
+    assert!(!page.contains("<hr"));
+    assert!(!page.contains("creator: Tester"));
+    assert!(!page.contains("released: 2024/01/02"));
+    assert_eq!(raw, source);
+}
+
+#[tokio::test]
+async fn slow_handler_times_out_with_408() {
+    // given: a route slower than the request timeout
+    let app = axum::Router::new()
+        .route(
+            "/slow",
+            axum::routing::get(|| async {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                "done"
+            }),
+        )
+        .layer(request_limits(std::time::Duration::from_millis(50)));
+
+    // when: requesting it
+    let (status, _) = get_text(&app, "/slow").await;
+
+    // then: the timeout answers instead of the handler
+    assert_eq!(status, StatusCode::REQUEST_TIMEOUT);
+}
+
+#[rstest]
+#[case::small(512, StatusCode::NO_CONTENT)]
+#[case::oversized(1024 * 1024, StatusCode::PAYLOAD_TOO_LARGE)]
+#[tokio::test]
+async fn request_bodies_over_the_limit_get_413(#[case] size: usize, #[case] expected: StatusCode) {
+    // given: the app
+    let app = build_app();
+
+    // when: posting a CSP report of the given size
+    let response = app
+        .oneshot(
+            Request::post("/api/csp-report")
+                .header("content-type", "application/csp-report")
+                .body(axum::body::Body::from(vec![b'x'; size]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: small reports are accepted, oversized ones refused
+    assert_eq!(response.status(), expected);
+}
+
+#[tokio::test]
+async fn api_years_counts_works_per_year_newest_first() {
+    // given: works across three years, one in a series folder
+    let app = fixture_app(
+        "api-years",
+        &[
+            ("2021/One.md", "One."),
+            ("2023/Two.md", "Two."),
+            ("2023/Series/Three.md", "Three."),
+            ("2022/Four.md", "Four."),
+            ("2022/Five.md", "Five."),
+            ("2022/Six.md", "Six."),
+        ],
+    );
+
+    // when: requesting /api/years
+    let (status, body) = get_text(&app, "/api/years").await;
+
+    // then: one entry per year, descending, with its work count
+    assert_eq!(status, StatusCode::OK);
+    let years: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        years,
+        serde_json::json!([
+            {"year": "2023", "count": 2},
+            {"year": "2022", "count": 3},
+            {"year": "2021", "count": 1},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn tree_thumbnail_skips_images_from_unlisted_sources() {
+    // given: a work opening with a remote pixel, then a local screenshot
+    let app = fixture_app(
+        "thumb-sources",
+        &[
+            (
+                "2023/Tracked.md",
+                "![](https://tracker.example/p.gif)\n\n![shot](shot.gif)",
+            ),
+            ("2023/Remote.md", "![](https://tracker.example/only.png)"),
+        ],
+    );
+
+    // when: fetching the tree
+    let (_, body) = get_text(&app, "/api/tree").await;
+
+    // then: the local image is the thumbnail; a work with none allowed gets none
+    let tree: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let works = tree["children"][0]["children"].as_array().unwrap();
+    let tracked = works.iter().find(|w| w["name"] == "Tracked.md").unwrap();
+    let remote = works.iter().find(|w| w["name"] == "Remote.md").unwrap();
+    assert_eq!(tracked["thumbnail_alt"], "shot");
+    assert!(tracked["thumbnail"]
+        .as_str()
+        .unwrap()
+        .contains("2023/shot.gif"));
+    assert!(remote.get("thumbnail").is_none());
+}
+
+#[tokio::test]
+async fn tree_thumbnail_carries_alt_text_when_given() {
+    // given: one work whose thumbnail has alt text and one whose doesn't
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            "thumb-alt",
+            &[
+                (
+                    "2023/Described.md",
+                    "![Night rooftop](https://example.com/a.png)",
+                ),
+                ("2023/Bare.md", "![](https://example.com/b.png)"),
+            ],
+        ),
+        thumbnail_sources: vec!["https://example.com/".to_string()],
+        ..Config::default()
+    });
+
+    // when: fetching the tree
+    let (_, body) = get_text(&app, "/api/tree").await;
+
+    // then: the URL stays a string, with the alt alongside only when present
+    let tree: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let works = tree["children"][0]["children"].as_array().unwrap();
+    let bare = works.iter().find(|w| w["name"] == "Bare.md").unwrap();
+    let described = works.iter().find(|w| w["name"] == "Described.md").unwrap();
+    assert_eq!(described["thumbnail"], "https://example.com/a.png");
+    assert_eq!(described["thumbnail_alt"], "Night rooftop");
+    assert_eq!(bare["thumbnail"], "https://example.com/b.png");
+    assert!(bare.get("thumbnail_alt").is_none());
+}
+
+/// - App whose /api/* allows `origins` cross-origin (see CORS_ORIGINS).
+fn cors_app(origins: &[&str]) -> axum::Router {
+    build_app_with(Config {
+        cors_origins: origins.iter().map(|o| o.to_string()).collect(),
+        ..Config::default()
+    })
+}
+
+async fn allow_origin(app: axum::Router, request: Request<axum::body::Body>) -> Option<String> {
+    let response = app.oneshot(request).await.unwrap();
+    response
+        .headers()
+        .get("access-control-allow-origin")
+        .map(|v| v.to_str().unwrap().to_string())
+}
+
+#[rstest]
+#[case::allowed(&["https://front.example"], "/api/tree", "https://front.example", Some("https://front.example"))]
+#[case::disallowed(&["https://front.example"], "/api/tree", "https://evil.example", None)]
+#[case::not_configured(&[], "/api/tree", "https://front.example", None)]
+#[case::wildcard(&["*"], "/api/tree", "https://anyone.example", Some("*"))]
+#[case::pages_stay_same_origin(&["*"], "/robots.txt", "https://anyone.example", None)]
+#[tokio::test]
+async fn cors_headers_only_for_allowed_origins_on_api(
+    #[case] origins: &[&str],
+    #[case] uri: &str,
+    #[case] origin: &str,
+    #[case] expected: Option<&str>,
+) {
+    // given: an app with the configured CORS origins
+    let app = cors_app(origins);
+
+    // when: a cross-origin GET arrives
+    let request = Request::get(uri)
+        .header("origin", origin)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let allowed = allow_origin(app, request).await;
+
+    // then: Access-Control-Allow-Origin only when the origin is allowed on /api/*
+    assert_eq!(allowed.as_deref(), expected);
+}
+
+#[tokio::test]
+async fn cors_preflight_is_answered() {
+    // given: an app allowing one origin
+    let app = cors_app(&["https://front.example"]);
+
+    // when: the browser sends a preflight for /api/tree
+    let response = app
+        .oneshot(
+            Request::options("/api/tree")
+                .header("origin", "https://front.example")
+                .header("access-control-request-method", "GET")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: a successful preflight naming the origin and allowed methods
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://front.example"
+    );
+    let methods = response.headers()["access-control-allow-methods"]
+        .to_str()
+        .unwrap();
+    assert!(methods.contains("GET"), "{}", methods);
+}
+
+/// - App allowing `per_minute` requests per client to the limited endpoints.
+fn rate_limited_app(per_minute: u32, trust_forwarded_for: bool) -> axum::Router {
+    build_app_with(Config {
+        rate_limit: per_minute,
+        trust_forwarded_for,
+        ..Config::default()
+    })
+}
+
+async fn search_as(app: &axum::Router, forwarded_for: Option<&str>) -> axum::response::Response {
+    let mut request = Request::get("/api/search?q=a");
+    if let Some(ip) = forwarded_for {
+        request = request.header("x-forwarded-for", ip);
+    }
+    app.clone()
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn rate_limit_refuses_the_request_past_the_budget() {
+    // given: an app allowing 3 searches a minute per client
+    let app = rate_limited_app(3, false);
+
+    // when: the same client searches 4 times in a row
+    let mut statuses = Vec::new();
+    let mut last = None;
+    for _ in 0..4 {
+        let response = search_as(&app, None).await;
+        statuses.push(response.status());
+        last = Some(response);
+    }
+
+    // then: the 4th is a 429 telling the client when to come back
+    assert_eq!(
+        statuses,
+        [
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::TOO_MANY_REQUESTS
+        ]
+    );
+    let retry_after: u64 = last.unwrap().headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=20).contains(&retry_after), "{}", retry_after);
+}
+
+#[rstest]
+#[case::trusted(true, StatusCode::OK)]
+#[case::untrusted(false, StatusCode::TOO_MANY_REQUESTS)]
+#[tokio::test]
+async fn rate_limit_keys_on_forwarded_for_only_when_trusted(
+    #[case] trust: bool,
+    #[case] expected: StatusCode,
+) {
+    // given: an app allowing one search a minute, already spent by one client
+    let app = rate_limited_app(1, trust);
+    search_as(&app, Some("203.0.113.7")).await;
+
+    // when: a request forwarded for a different client arrives
+    let response = search_as(&app, Some("198.51.100.2")).await;
+
+    // then: it has its own budget only if X-Forwarded-For is trusted
+    assert_eq!(response.status(), expected);
+}
+
+#[tokio::test]
+async fn rate_limit_leaves_other_routes_alone() {
+    // given: an app allowing one request a minute, already spent
+    let app = rate_limited_app(1, false);
+    search_as(&app, None).await;
+
+    // when: the same client fetches a cheap endpoint
+    let response = app
+        .oneshot(
+            Request::get("/api/tags")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: it isn't limited
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn site_title_brands_pages_and_tree() {
+    // given: an app with a site title and logo configured
+    let dir = fixture_dir("site-title", &[("2024/Aoi.md", "# Aoi\n")]);
+    let app = build_app_with(Config {
+        works_dir: dir,
+        site_title: "Night & Day VNs".to_string(),
+        site_logo_url: Some("/logo.webp".to_string()),
+        ..Config::default()
+    });
+
+    // when: fetching a work page, the homepage and the tree
+    let (_, work) = get_text(&app, "/works/2024/Aoi").await;
+    let (_, home) = get_text(&app, "/").await;
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (_, flat) = get_text(&app, "/api/tree?flat=true").await;
+
+    // then: the title suffix, header bar and tree metadata all carry it
+    assert!(
+        work.contains("<title>Aoi (2024) — Night &#38; Day VNs</title>"),
+        "{work}"
+    );
+    assert!(work.contains(r#"<img class="site-logo" src="/logo.webp" alt="">"#));
+    assert!(
+        home.contains("<title>Night &amp; Day VNs</title>"),
+        "{home}"
+    );
+    assert!(!home.contains("Light.vn Works"));
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    assert_eq!(tree["site_title"], "Night & Day VNs");
+    assert!(tree["children"][0].get("site_title").is_none());
+    let flat: serde_json::Value = serde_json::from_str(&flat).unwrap();
+    assert_eq!(flat["site_title"], "Night & Day VNs");
+}
+
+#[tokio::test]
+async fn year_page_lists_every_work_with_thumbnail_and_title() {
+    // given: a year with two works, one with an image
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            "year-listing",
+            &[
+                (
+                    "2022/Aoi.md",
+                    "![cover](https://example.com/aoi.png)\n\nAoi.",
+                ),
+                ("2022/Beni.md", "Beni."),
+            ],
+        ),
+        thumbnail_sources: vec!["https://example.com/".to_string()],
+        ..Config::default()
+    });
+
+    // when: browsing to the year
+    let (status, page) = get_text(&app, "/works/2022").await;
+
+    // then: a themed listing with a card per work, linking to it
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("<h1>2022</h1>"), "{page}");
+    assert!(page.contains("2 works"));
+    assert!(page.contains(r#"href="/works/2022/Aoi" class="more-creator-card""#));
+    assert!(page.contains(r#"href="/works/2022/Beni" class="more-creator-card""#));
+    assert!(page.contains(r#"<img src="https://example.com/aoi.png""#));
+    assert!(page.contains(r#"<span class="more-creator-title">Beni</span>"#));
+}
+
+#[rstest]
+#[case::nonexistent("year-missing", &[("2022/Aoi.md", "Aoi.")])]
+#[case::empty("year-empty", &[("2019/notes.txt.bak", "")])]
+#[tokio::test]
+async fn year_without_works_is_a_themed_404(#[case] name: &str, #[case] files: &[(&str, &str)]) {
+    // given: no works in 2019 (no directory, or one without works)
+    let app = fixture_app(name, files);
+
+    // when: a browser asks for the year
+    let response = app
+        .oneshot(
+            Request::get("/works/2019")
+                .header("accept", "text/html")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: the themed not-found page, naming what was asked for
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page = String::from_utf8_lossy(&body);
+    assert!(page.contains("background:#0d0b12"), "{page}");
+    assert!(page.contains("<code>works/2019</code>"), "{page}");
+}
+
+#[tokio::test]
+async fn metrics_counts_requests_and_cache_lookups() {
+    // given: an app with metrics on, after some traffic
+    let app = build_app_with(Config {
+        metrics: true,
+        ..Config::default()
+    });
+    get_text(&app, "/api/tree").await;
+    get_text(&app, "/api/tree?sort=date").await;
+    get_text(&app, "/works/2024/does-not-exist").await;
+
+    // when: scraping /metrics
+    let (status, text) = get_text(&app, "/metrics").await;
+
+    // then: Prometheus text with per-route/status counters and both caches
+    assert_eq!(status, StatusCode::OK);
+    for name in [
+        "# TYPE lightvn_http_requests_total counter",
+        "lightvn_tree_cache_hits_total 1",
+        "lightvn_tree_cache_misses_total 1",
+        "lightvn_render_cache_hits_total",
+        "lightvn_render_cache_misses_total",
+    ] {
+        assert!(text.contains(name), "missing {name:?} in\n{text}");
+    }
+    assert!(
+        text.contains(r#"lightvn_http_requests_total{route="/api/tree",status="2xx"} 2"#),
+        "{text}"
+    );
+    assert!(
+        text.contains(
+            r#"lightvn_http_requests_total{route="/works/{year}/{*title}",status="4xx"} 1"#
+        ),
+        "{text}"
+    );
+}
+
+#[tokio::test]
+async fn metrics_are_off_by_default() {
+    // given: the default config
+    let app = build_app();
+
+    // when: scraping /metrics
+    let (status, _) = get_text(&app, "/metrics").await;
+
+    // then: there's no such endpoint
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn collections_are_mounted_side_by_side() {
+    // given: two collections, each with a work in 2024 and one a work of the
+    // same name as the other's
+    let games = fixture_dir(
+        "collection-games",
+        &[
+            ("2024/Shared.md", "---\ncreator: A\n---\n\nGames copy."),
+            ("2024/Quest.md", "---\ncreator: A\n---\n\nQuest body."),
+        ],
+    );
+    let novels = fixture_dir(
+        "collection-novels",
+        &[("2024/Shared.md", "---\ncreator: B\n---\n\nNovels copy.")],
+    );
+    let app = build_app_with(Config {
+        collections: vec![
+            format!("games={}", games.display()).parse().unwrap(),
+            format!("novels={}", novels.display()).parse().unwrap(),
+        ],
+        ..Config::default()
+    });
+
+    // when: listing the collections and each one's tree
+    let (status, list) = get_text(&app, "/api/collections").await;
+    let (_, games_tree) = get_text(&app, "/api/tree/games").await;
+    let (_, novels_tree) = get_text(&app, "/api/tree/novels").await;
+
+    // then: both are listed, and each tree holds only its own works
+    assert_eq!(status, StatusCode::OK);
+    let list: serde_json::Value = serde_json::from_str(&list).unwrap();
+    assert_eq!(list["collections"][0]["name"], "games");
+    assert_eq!(list["collections"][0]["works"], 2);
+    assert_eq!(list["collections"][1]["name"], "novels");
+    assert_eq!(list["collections"][1]["tree"], "/api/tree/novels");
+    let games_tree: serde_json::Value = serde_json::from_str(&games_tree).unwrap();
+    assert_eq!(games_tree["path"], "/works/games");
+    assert_eq!(games_tree["children"][0]["path"], "/works/games/2024");
+    let paths: Vec<&str> = games_tree["children"][0]["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| w["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        ["/works/games/2024/Quest.md", "/works/games/2024/Shared.md"]
+    );
+    assert!(!novels_tree.contains("/works/games/"), "{}", novels_tree);
+    assert!(novels_tree.contains("/works/novels/2024/Shared.md"));
+
+    // then: pages and raw files come from their own collection's dir
+    let (status, page) = get_text(&app, "/works/novels/2024/Shared").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("Novels copy."));
+    let (_, raw) = get_text(&app, "/raw/games/2024/Shared.md").await;
+    assert!(raw.contains("Games copy."));
+    for missing in [
+        "/works/novels/2024/Quest",
+        "/raw/novels/2024/Quest.md",
+        "/raw/2024/Quest.md",
+        "/api/tree/comics",
+    ] {
+        let (status, _) = get_text(&app, missing).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", missing);
+    }
+}
+
+#[tokio::test]
+async fn collection_work_keeps_its_year_and_title() {
+    // given: two collections with works in 2023 and 2024, one nested in a series
+    let games = fixture_dir(
+        "collection-years-games",
+        &[
+            ("2024/Quest.md", "Quest body."),
+            ("2023/Saga/Part 1.md", "Part body."),
+        ],
+    );
+    let novels = fixture_dir("collection-years-novels", &[("2024/Tale.md", "Tale body.")]);
+    let app = build_app_with(Config {
+        collections: vec![
+            format!("games={}", games.display()).parse().unwrap(),
+            format!("novels={}", novels.display()).parse().unwrap(),
+        ],
+        ..Config::default()
+    });
+
+    // when: rendering a collection's works and listing the years
+    let (status, page) = get_text(&app, "/works/games/2024/Quest").await;
+    let (_, nested) = get_text(&app, "/works/games/2023/Saga/Part%201").await;
+    let (_, years) = get_text(&app, "/api/years").await;
+
+    // then: the page is titled with its year, and its breadcrumb says the year
+    // and links to the collection's year section
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("<title>Quest (2024)"), "{}", page);
+    assert!(page.contains(r#"href="/#games/2024">2024</a>"#), "{}", page);
+    assert!(nested.contains("<title>Part 1 (2023)"), "{}", nested);
+    assert!(nested.contains(">Saga</a>"), "{}", nested);
+
+    // then: years count works across collections, not the collections
+    let years: serde_json::Value = serde_json::from_str(&years).unwrap();
+    assert_eq!(
+        years,
+        serde_json::json!([
+            {"year": "2024", "count": 2},
+            {"year": "2023", "count": 1},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn collections_list_is_empty_for_a_single_works_dir() {
+    // given: the default, single works dir
+    let app = fixture_app("no-collections", &[("2024/A.md", "A body.")]);
+
+    // when: listing the collections
+    let (status, body) = get_text(&app, "/api/collections").await;
+
+    // then: there are none, and the plain tree is unchanged
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, r#"{"collections":[]}"#);
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    assert!(tree.contains(r#""path":"/works/2024/A.md""#), "{}", tree);
+}
+
+#[tokio::test]
+async fn print_mode_drops_nav_chrome_and_goes_light() {
+    // given: a work with a neighbour, so the page has prev/next navigation
+    let app = fixture_app(
+        "print-mode",
+        &[("2024/A.md", "A body."), ("2024/B.md", "B body.")],
+    );
+
+    // when: requesting the page normally and with ?print=1
+    let (_, normal) = get_text(&app, "/works/2024/A").await;
+    let (status, print) = get_text(&app, "/works/2024/A?print=1").await;
+
+    // then:
+    // - the normal page has the chrome, with print.css for printing only
+    // - the print page leaves out the header bar, breadcrumbs and prev/next,
+    //   applies print.css to every medium and starts from a light background
+    assert!(normal.contains(r#"class="breadcrumb""#));
+    assert!(normal.contains(r#"class="work-nav""#));
+    assert!(normal.contains(r#"media="print""#));
+    assert_eq!(status, StatusCode::OK);
+    assert!(print.contains("A body."));
+    for chrome in [
+        r#"class="site-bar""#,
+        r#"class="breadcrumb""#,
+        r#"class="work-nav""#,
+        r#"class="lang-toggle""#,
+        r#"class="share-btn""#,
+    ] {
+        assert!(!print.contains(chrome), "{} in {}", chrome, print);
+    }
+    assert!(print.contains(r#"<body class="print">"#));
+    assert!(print.contains("html,body{background:#ffffff"));
+    assert!(!print.contains(r#"media="print""#));
+    assert!(print.contains("/assets/print."));
+}
+
+#[rstest]
+#[case::markdown("2024/A.md", "/works/2024/A?format=md", "text/markdown; charset=utf-8")]
+#[case::nested(
+    "2024/Series/B.md",
+    "/works/2024/Series/B?format=md",
+    "text/markdown; charset=utf-8"
+)]
+#[case::plaintext("2024/C.txt", "/works/2024/C?format=md", "text/plain; charset=utf-8")]
+#[tokio::test]
+async fn work_source_is_served_as_written(
+    #[case] file: &str,
+    #[case] uri: &str,
+    #[case] content_type: &str,
+) {
+    // given: a work whose source has frontmatter and an include directive
+    let source = "---\ncreator: Tester\n---\n\n# Notes\n\n{{ include: missing.md }}\n";
+    let name = format!("work-source-{}", file.len());
+    let app = fixture_app(&name, &[(file, source)]);
+
+    // when: asking for the work's source
+    let response = app
+        .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: the file byte for byte, with a UTF-8 text content type
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], content_type);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body, source.as_bytes());
+}
+
+#[tokio::test]
+async fn work_source_hides_drafts_like_the_page() {
+    // given: a draft work
+    let app = fixture_app(
+        "work-source-draft",
+        &[("2024/Draft.md", "---\ndraft: true\n---\n\nSecret.")],
+    );
+
+    // when: asking for its source
+    let (status, body) = get_text(&app, "/works/2024/Draft?format=md").await;
+
+    // then: the same 404 as its page
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(!body.contains("Secret."));
+}
+
+#[rstest]
+#[case::json(Some("application/json"), true)]
+#[case::html(Some("text/html"), false)]
+#[case::browser(Some("text/html,application/xhtml+xml,*/*;q=0.8"), false)]
+#[case::html_preferred(Some("application/json;q=0.5, text/html"), false)]
+#[case::no_accept(None, false)]
+#[tokio::test]
+async fn work_url_negotiates_html_or_json(#[case] accept: Option<&str>, #[case] json: bool) {
+    // given: a work
+    let name = format!("work-negotiation-{}", accept.map_or(0, str::len));
+    let app = fixture_app(
+        &name,
+        &[(
+            "2024/A.md",
+            "---\ncreator: Tester\n---\n\n## Intro\n\nA *body*.",
+        )],
+    );
+
+    // when: requesting its page URL with that Accept
+    let mut request = Request::get("/works/2024/A");
+    if let Some(accept) = accept {
+        request = request.header("accept", accept);
+    }
+    let response = app
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: JSON with the metadata and rendered body, or the full page;
+    // either way varying on Accept
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["vary"], "accept");
+    let content_type = response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    if json {
+        assert_eq!(content_type, "application/json");
+        let work: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(work["path"], "/works/2024/A");
+        assert_eq!(work["frontmatter"]["creator"], "Tester");
+        assert_eq!(work["headings"][0]["text"], "Intro");
+        assert!(work["html"].as_str().unwrap().contains("A <em>body</em>."));
+    } else {
+        assert!(content_type.starts_with("text/html"), "{}", content_type);
+        let page = String::from_utf8_lossy(&body);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("A <em>body</em>."));
+    }
+}
+
+#[tokio::test]
+async fn missing_work_uses_the_deployments_404_template() {
+    // given: a public dir with a 404_work.html template
+    let public_dir = fixture_dir(
+        "work-404-public",
+        &[(
+            "404_work.html",
+            "<html><body><h1>Custom: {{title}} ({{year}})</h1></body></html>",
+        )],
+    );
+    let app = build_app_with(Config {
+        works_dir: fixture_dir("work-404-works", &[("2024/A.md", "A body.")]),
+        public_dir,
+        ..Config::default()
+    });
+
+    // when: a browser asks for a missing work, with markup in the title
+    let response = app
+        .oneshot(
+            Request::get("/works/2024/%3Cscript%3Ex%3C%2Fscript%3E")
+                .header("accept", "text/html")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: a 404 rendered from the template, the title escaped
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page = String::from_utf8_lossy(&body);
+    assert!(
+        page.contains("<h1>Custom: &lt;script&gt;x&lt;/script&gt; (2024)</h1>"),
+        "{}",
+        page
+    );
+}
+
+#[rstest]
+#[case::yearless("/works/Lost", "<h1>Custom: Lost ()</h1>")]
+#[case::permalink("/2024/10/Lost", "<h1>Custom: Lost (2024)</h1>")]
+#[tokio::test]
+async fn every_missing_work_route_uses_the_404_template(#[case] uri: &str, #[case] expected: &str) {
+    // given: a public dir with a 404_work.html template, and a permalink pattern
+    let public_dir = fixture_dir(
+        &format!("work-404-routes-{}", uri.len()),
+        &[(
+            "404_work.html",
+            "<html><body><h1>Custom: {{title}} ({{year}})</h1></body></html>",
+        )],
+    );
+    let app = build_app_with(Config {
+        works_dir: fixture_dir(
+            &format!("work-404-routes-works-{}", uri.len()),
+            &[("2024/A.md", "A body.")],
+        ),
+        public_dir,
+        permalink_pattern: Some("/:year/:month/:slug".to_string()),
+        ..Config::default()
+    });
+
+    // when: a browser, then an API client, asks for a work that isn't there
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get(uri)
+                .header("accept", "text/html")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let json = app
+        .oneshot(
+            Request::get(uri)
+                .header("accept", "application/json")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // then: the same 404 as /works/{year}/{title} — the template for the
+    // browser, a JSON error for the client
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page = String::from_utf8_lossy(&body);
+    assert!(page.contains(expected), "{}", page);
+    assert_eq!(json.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(json.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("Lost"));
+}
+
+#[tokio::test]
+async fn work_over_the_size_limit_is_refused_with_413() {
+    // given: a 100-byte limit, a work under it and one over it
+    let big = format!("# Dump\n\n{}", "x".repeat(200));
+    let works_dir = fixture_dir(
+        "max-work-bytes",
+        &[("2024/Small.md", "Small *body*."), ("2024/Big.md", &big)],
+    );
+    let app = build_app_with(Config {
+        works_dir,
+        max_work_bytes: 100,
+        ..Config::default()
+    });
+
+    // when: requesting both pages, and the raw big file
+    let (small_status, small) = get_text(&app, "/works/2024/Small").await;
+    let (big_status, body) = get_text(&app, "/works/2024/Big").await;
+    let (raw_status, _) = get_text(&app, "/raw/2024/Big.md").await;
+    let (_, tree) = get_text(&app, "/api/tree").await;
+
+    // then:
+    // - the small work renders as always
+    // - the big one is a 413, never read into the index or the tree
+    // - its raw file still streams from disk
+    assert_eq!(small_status, StatusCode::OK);
+    assert!(small.contains("Small <em>body</em>."));
+    assert_eq!(big_status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(body.contains("too large to render: 2024/Big"), "{}", body);
+    assert!(!tree.contains("Big.md"), "{}", tree);
+    assert_eq!(raw_status, StatusCode::OK);
+}
+
+// - A work in English ("Tale.md", the base language) and Japanese ("Tale.ja.md").
+fn language_variants_app(name: &str) -> axum::Router {
+    fixture_app(
+        name,
+        &[
+            ("2024/Tale.md", "Once upon a time."),
+            ("2024/Tale.ja.md", "むかしむかし。"),
+        ],
+    )
+}
+
+#[rstest]
+#[case::lang_param("/works/2024/Tale?lang=ja", None, "むかしむかし。")]
+#[case::accept_language("/works/2024/Tale", Some("ja-JP,ja;q=0.9"), "むかしむかし。")]
+#[case::param_over_header("/works/2024/Tale?lang=en", Some("ja"), "Once upon a time.")]
+#[case::missing_lang_falls_back("/works/2024/Tale?lang=fr", None, "Once upon a time.")]
+#[case::no_preference("/works/2024/Tale", None, "Once upon a time.")]
+#[tokio::test]
+async fn work_page_serves_the_requested_language(
+    #[case] uri: &str,
+    #[case] accept_language: Option<&str>,
+    #[case] expected: &str,
+) {
+    // given: a work in English and Japanese
+    let case: String = format!("{}{}", uri, accept_language.unwrap_or(""))
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    let app = language_variants_app(&format!("work-langs-{}", case));
+
+    // when: requesting it, maybe with an Accept-Language
+    let mut request = Request::get(uri);
+    if let Some(value) = accept_language {
+        request = request.header("accept-language", value);
+    }
+    let response = app
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // then: that variant's text, the response varying on the header
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["vary"].to_str().unwrap(),
+        "accept, accept-language"
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page = String::from_utf8_lossy(&body);
+    assert!(page.contains(expected), "{}", page);
+}
+
+#[tokio::test]
+async fn language_variants_share_one_tree_node_and_a_selector() {
+    // given: a work in English and Japanese
+    let app = language_variants_app("work-langs-tree");
+
+    // when: fetching the tree and the Japanese page
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (_, page) = get_text(&app, "/works/2024/Tale?lang=ja").await;
+
+    // then:
+    // - one node for the work, listing both languages
+    // - the page links the other language and marks the one shown
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    let works = tree["children"][0]["children"].as_array().unwrap();
+    assert_eq!(works.len(), 1, "{:?}", works);
+    assert_eq!(works[0]["name"], "Tale.md");
+    assert_eq!(works[0]["langs"], serde_json::json!(["en", "ja"]));
+    assert!(page.contains(r#"<nav class="work-langs""#), "{}", page);
+    assert!(page.contains(r#"<a href="?lang=en" hreflang="en">EN</a>"#));
+    assert!(page.contains(r#"<span aria-current="true">JA</span>"#));
+}
+
+#[tokio::test]
+async fn openapi_document_lists_the_api_paths() {
+    // given: the app
+    let app = fixture_app("openapi-paths", &[("2024/A.md", "A body.")]);
+
+    // when: fetching the OpenAPI document
+    let (status, body) = get_text(&app, "/api/openapi.json").await;
+
+    // then: valid JSON, OpenAPI 3, describing the API routes and their schemas
+    assert_eq!(status, StatusCode::OK);
+    let doc: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(doc["openapi"].as_str().unwrap().starts_with("3.0"));
+    for path in [
+        "/api/tree",
+        "/api/tree/{collection}",
+        "/api/search",
+        "/api/work/{year}/{title}",
+        "/api/tags",
+        "/api/years",
+        "/api/collections",
+    ] {
+        assert!(doc["paths"][path]["get"].is_object(), "missing {}", path);
+    }
+    for schema in ["Node", "SearchHit", "WorkDetail", "GameMeta"] {
+        assert!(
+            doc["components"]["schemas"][schema].is_object(),
+            "missing {}",
+            schema
+        );
+    }
+}
+
+#[tokio::test]
+async fn openapi_schemas_match_responses() {
+    // given: a work using every optional field the API can return
+    let work = "---\ntitle: Shown\ncreator: A\nreleased: 2024-01-02\ndate_added: 2024-01-03\n\
+                link_label: Play\nlink_url: https://example.com\ntagline: T\ndescription: D\n\
+                extra_links: [{label: L, url: https://example.com/l}]\ntags: [demo]\n\
+                thumbnail_index: 0\ntheme: {accent: '#ff0000'}\n---\n# Heading\n\n\
+                Needle ![s](/raw/2024/s.png)";
+    let app = fixture_app(
+        "openapi-sync",
+        &[("2024/A.md", work), ("2024/A.ja.md", "Needle ja")],
+    );
+
+    // when: fetching the document and the responses it describes
+    let (_, doc) = get_text(&app, "/api/openapi.json").await;
+    let doc: serde_json::Value = serde_json::from_str(&doc).unwrap();
+    let (_, tree) = get_text(&app, "/api/tree").await;
+    let (_, hits) = get_text(&app, "/api/search?q=needle&fuzzy=true").await;
+    let (_, detail) = get_text(&app, "/api/work/2024/A").await;
+    let json = |body: &str| serde_json::from_str::<serde_json::Value>(body).unwrap();
+    let (tree, hits, detail) = (json(&tree), json(&hits), json(&detail));
+    let work = &tree["children"][0]["children"][0];
+
+    // then: every field they carry is declared in its schema
+    let declared = |schema: &str, value: &serde_json::Value| {
+        let properties = &doc["components"]["schemas"][schema]["properties"];
+        for key in value.as_object().unwrap().keys() {
+            assert!(properties[key].is_object(), "{}.{} undeclared", schema, key);
+        }
+    };
+    declared("Node", &tree);
+    declared("Node", work);
+    declared("GameMeta", &work["meta"]);
+    declared("SearchHit", &hits[0]);
+    declared("WorkDetail", &detail);
+    declared("Heading", &detail["headings"][0]);
+}
+
+#[tokio::test]
+async fn openapi_paths_match_the_router() {
+    // given: the `/api/*` routes build_app_with registers, with their methods
+    // (a `{*rest}` wildcard is documented as a plain `{rest}` parameter)
+    let source = include_str!("../../src/app.rs");
+    // (rustfmt may wrap a long `.route(` call, so each is read up to its `))`)
+    let routed: std::collections::BTreeSet<(String, String)> = source
+        .split(".route(")
+        .skip(1)
+        .filter_map(|call| {
+            let call = call.split("))").next().unwrap().trim_start();
+            let path = call.strip_prefix('"')?.split('"').next()?;
+            let method = if call.contains("post(") {
+                "post"
+            } else {
+                "get"
+            };
+            path.starts_with("/api/")
+                .then(|| (path.replace("{*", "{"), method.to_string()))
+        })
+        .collect();
+    let app = fixture_app("openapi-routes", &[("2024/A.md", "A body.")]);
+
+    // when: fetching the OpenAPI document
+    let (_, doc) = get_text(&app, "/api/openapi.json").await;
+    let doc: serde_json::Value = serde_json::from_str(&doc).unwrap();
+
+    // then: it documents exactly those routes and methods
+    let documented: std::collections::BTreeSet<(String, String)> = doc["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (path.clone(), method.clone()))
+        })
+        .collect();
+    assert!(!routed.is_empty());
+    assert_eq!(documented, routed);
+}
+
+#[tokio::test]
+async fn home_embeds_the_tree_served_at_api_tree() {
+    // given: works, one with markup in its text
+    let app = fixture_app(
+        "home-embedded-tree",
+        &[
+            ("2024/A.md", "---\ntagline: \"</script><b>x\"\n---\nA body."),
+            ("2023/B.md", "B body."),
+        ],
+    );
+
+    // when: fetching the homepage and the tree
+    let (_, home) = get_text(&app, "/").await;
+    let (_, tree) = get_text(&app, "/api/tree").await;
+
+    // then: the page carries the same tree inline, so it renders without a
+    //   fetch, and the markup can't end the script early
+    let embedded = home
+        .split_once("var TREE_DATA = ")
+        .and_then(|(_, rest)| rest.split_once(";</script>"))
+        .map(|(json, _)| json)
+        .expect("TREE_DATA in the homepage");
+    assert!(!embedded.contains("</script>"));
+    let embedded: serde_json::Value = serde_json::from_str(embedded).unwrap();
+    let tree: serde_json::Value = serde_json::from_str(&tree).unwrap();
+    assert_eq!(embedded, tree);
+    assert_eq!(embedded["children"].as_array().unwrap().len(), 2);
+}
+
+#[rstest]
+#[case::lang_param("/works/2024/Tale?lang=ja", None)]
+#[case::accept_language("/works/2024/Tale", Some("ja"))]
+#[tokio::test]
+async fn draft_language_variant_is_not_served(
+    #[case] uri: &str,
+    #[case] accept_language: Option<&str>,
+) {
+    // given: a published work whose Japanese variant is a draft
+    let app = fixture_app(
+        &format!("work-langs-draft-{}", accept_language.is_some()),
+        &[
+            ("2024/Tale.md", "Once upon a time."),
+            ("2024/Tale.ja.md", "---\ndraft: true\n---\nひみつ。"),
+        ],
+    );
+
+    // when: asking for Japanese, and for the tree
+    let mut request = Request::get(uri);
+    if let Some(value) = accept_language {
+        request = request.header("accept-language", value);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let page = String::from_utf8_lossy(&body);
+    let (_, tree) = get_text(&app, "/api/tree").await;
+
+    // then: the published English page, with no selector or langs advertising the draft
+    assert!(page.contains("Once upon a time."), "{}", page);
+    assert!(!page.contains("ひみつ"));
+    assert!(!page.contains("work-langs"));
+    assert!(!tree.contains("\"langs\""), "{}", tree);
+}
+
+#[tokio::test]
+async fn raw_draft_language_variant_is_hidden() {
+    // given: a published work whose Japanese variant is a draft
+    let app = fixture_app(
+        "raw-langs-draft",
+        &[
+            ("2024/Tale.md", "Once upon a time."),
+            ("2024/Tale.ja.md", "---\ndraft: true\n---\nひみつ。"),
+        ],
+    );
+
+    // when: fetching both source files
+    let (published, _) = get_text(&app, "/raw/2024/Tale.md").await;
+    let (draft, body) = get_text(&app, "/raw/2024/Tale.ja.md").await;
+
+    // then: only the published one is served
+    assert_eq!(published, StatusCode::OK);
+    assert_eq!(draft, StatusCode::NOT_FOUND);
+    assert!(!body.contains("ひみつ"));
+}
+
+#[tokio::test]
+async fn render_cache_is_not_keyed_on_the_host() {
+    // given: an app with metrics on and one work
+    let app = build_app_with(Config {
+        works_dir: fixture_dir("render-cache-host", &[("2024/A.md", "A body.")]),
+        metrics: true,
+        ..Config::default()
+    });
+
+    // when: requesting the page under two Host headers
+    let mut pages = Vec::new();
+    for host in ["a.example", "b.example"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/works/2024/A")
+                    .header("host", host)
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        pages.push(String::from_utf8_lossy(&body).into_owned());
+    }
+    let (_, metrics) = get_text(&app, "/metrics").await;
+
+    // then: each page names its own host, but the second is a cache hit
+    assert!(pages[0].contains(r#"href="https://a.example/works/2024/A""#));
+    assert!(pages[1].contains(r#"href="https://b.example/works/2024/A""#));
+    assert!(!pages[1].contains("a.example"));
+    assert!(
+        metrics.contains("lightvn_render_cache_hits_total 1"),
+        "{metrics}"
+    );
+}
+
+#[tokio::test]
+async fn edited_work_is_reindexed_without_restart() {
+    // given: an app over a work, its tree and page already built
+    let dir = fixture_dir(
+        "reindex",
+        &[("2024/A.md", "---\ntitle: Before Title\n---\n\nBody.")],
+    );
+    let app = build_app_with(Config {
+        works_dir: dir.clone(),
+        ..Config::default()
+    });
+    assert!(get_text(&app, "/api/tree").await.1.contains("Before Title"));
+    assert!(get_text(&app, "/works/2024/A")
+        .await
+        .1
+        .contains("Before Title"));
+
+    // when: the work's title is edited on disk
+    std::fs::write(
+        dir.join("2024/A.md"),
+        "---\ntitle: After Title\n---\n\nBody.",
+    )
+    .unwrap();
+
+    // then: the tree picks up the new title once the watcher settles, and so
+    // does the (previously cached) page
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let mut tree = String::new();
+    while std::time::Instant::now() < deadline {
+        tree = get_text(&app, "/api/tree").await.1;
+        if tree.contains("After Title") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(tree.contains("After Title"), "{tree}");
+    assert!(!tree.contains("Before Title"));
+    let (_, page) = get_text(&app, "/works/2024/A").await;
+    assert!(page.contains("After Title"));
+    assert!(!page.contains("Before Title"));
+}
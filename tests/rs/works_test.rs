@@ -3,20 +3,51 @@
 //! - Non-parameterized tests use plain `#[test]`.
 //! - Common test data is built via `#[fixture]`s (e.g. `cfg`); per-call data uses plain helper fns.
 
+use askama::Template;
+use clap::Parser;
 use lightvn_works::{
-    aggregate_creator_links, build_atom_feed, build_creator_paths, build_query, build_sitemap,
-    build_tag_index, build_tags_line, creator_work_key, detect_lang, encode_path, escape_css_url,
-    extract_all_images, extract_user_attachment_uuid, feed_date, first_offsite_image, gallery_rows,
-    game_page_suffixes, get_lang, get_related_paths, html_escape, is_canonical_released,
-    is_composite_dimensions, json_script_escape, load_aliases, load_tag_config, parse_frontmatter,
-    pick_priority_tag, released_to_iso, resize_thumbnail, split_creators, strip_img_tags,
-    ExtraLink, FeedEntry, GameMeta, ParsedGame, TagInfo, ThumbSize, RELEASED_UNKNOWN,
+    aggregate_creator_links, alt_from_filename, bare_urls, breadcrumb_dirs_html, build_atom_feed,
+    build_creator_paths, build_json_feed, build_query, build_sitemap, build_tag_index,
+    build_tags_line, check_link, creator_work_key, detect_lang, dir_children, draft_preview_ok,
+    encode_path, escape_css_url, etag_matches, expand_emoji_shortcodes, expand_includes,
+    expand_includes_with_notes, extract_all_images, extract_headings, extract_link_targets,
+    extract_user_attachment_uuid, feed_date, find_loose_title, find_orphans, first_offsite_image,
+    first_paragraph, frontmatter_error, fuzzy_search, gallery_rows, game_page_suffixes, get_lang,
+    get_related_paths, has_math, has_mermaid, has_tag, hashed_asset_name, html_escape,
+    html_to_text, http_date, is_canonical_released, is_composite_dimensions, is_dir_index,
+    is_noindex, is_safe_url, iso_date_from_unix, json_script_escape, link_graph_dot, load_aliases,
+    load_tag_config, local_asset_path, local_thumb_cache_name, local_thumb_url, local_thumb_width,
+    markdown_to_html, markdown_to_html_with, markdown_word_count, media_element, missing_work,
+    normalize_path, not_modified_since, parallel_map, parse_comment_counts, parse_featured,
+    parse_frontmatter, parse_http_date, permalink_for, permalink_route, pick_priority_tag,
+    pick_thumbnail, pick_work_lang, plaintext_to_html, quality_failures, reading_time_minutes,
+    relativize_root_links, released_to_iso, render_toc, resize_thumbnail, resize_to_width,
+    resolve_asset_refs, resolve_image_url, resolve_relative, resolves_within, robots_txt,
+    search_snippet, sibling_works, site_header_html, slugify, split_creators, split_lang_suffix,
+    split_work_path, spoiler_summary, strip_img_tags, sweep_thumb_cache, tag_counts, theme_css,
+    truncate_at_word, watch_works, weak_etag, word_count, work_excerpt, work_langs_html,
+    work_link_target, work_not_found_html, year_counts, CachePolicy, Collection, Config, ExtraLink,
+    FeedEntry, FsLimiter, GameMeta, ImageInfo, IncludeError, LinkCheck, NotFoundTemplate,
+    PageTemplate, ParsedGame, PathNormalization, QualityRules, RateLimiter, Reloading, RenderCache,
+    RenderKey, RenderOptions, SitemapEntry, TagInfo, ThumbSize, WorkTheme, DEFAULT_BASE_LANG,
+    DEFAULT_FS_CONCURRENCY, DEFAULT_MIN_WORDS, DEFAULT_RATE_LIMIT, DEFAULT_RENDER_CACHE_SIZE,
+    DEFAULT_REQUEST_TIMEOUT, DEFAULT_SITE_TITLE, DEFAULT_THUMBNAIL_SOURCES, EXCERPT_CHARS,
+    MAX_INCLUDE_DEPTH, RELEASED_UNKNOWN, SEARCH_SNIPPET_CHARS,
 };
 use rstest::{fixture, rstest};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+/// - Fresh, empty scratch directory under the system temp dir for on-disk cases.
+/// - Keyed by test name + pid so parallel tests and reruns never collide.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lightvn-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
 #[test]
 fn build_query_empty_input() {
     // given: no key/value pairs
@@ -81,13 +112,19 @@ fn build_query_filters_empty_values(#[case] pairs: &[(&str, &str)], #[case] expe
 fn build_sitemap_lists_home_and_encoded_game_urls() {
     // given: a base URL and canonical game paths, one with spaces
     let base = "https://example.com";
-    let paths = vec![
-        "/works/2024/42 Hallows Street".to_string(),
-        "/works/2016/KONKON".to_string(),
+    let entries = vec![
+        SitemapEntry {
+            path: "/works/2024/42 Hallows Street".to_string(),
+            lastmod: None,
+        },
+        SitemapEntry {
+            path: "/works/2016/KONKON".to_string(),
+            lastmod: None,
+        },
     ];
 
     // when: building the sitemap
-    let xml = build_sitemap(base, &paths);
+    let xml = build_sitemap(base, &entries);
 
     // then:
     // - well-formed XML header + urlset wrapper
@@ -112,6 +149,31 @@ fn build_sitemap_trims_trailing_slash_from_base() {
     assert!(!xml.contains("com//"));
 }
 
+#[test]
+fn build_sitemap_emits_lastmod_only_when_known() {
+    // given: one dated work and one undated creator page
+    let entries = vec![
+        SitemapEntry {
+            path: "/works/2016/KONKON".to_string(),
+            lastmod: Some("2025-01-02".to_string()),
+        },
+        SitemapEntry {
+            path: "/creator/someone".to_string(),
+            lastmod: None,
+        },
+    ];
+
+    // when: building the sitemap
+    let xml = build_sitemap("https://example.com", &entries);
+
+    // then: the dated entry carries <lastmod>, the other (and home) don't
+    assert!(xml.contains(
+        "<url><loc>https://example.com/works/2016/KONKON</loc><lastmod>2025-01-02</lastmod></url>"
+    ));
+    assert!(xml.contains("<url><loc>https://example.com/creator/someone</loc></url>"));
+    assert_eq!(xml.matches("<lastmod>").count(), 1);
+}
+
 #[rstest]
 #[case::full("2024/03/15", Some("2024-03-15"))]
 #[case::zero_pads("2024/3/5", Some("2024-03-05"))]
@@ -337,13 +399,19 @@ fn build_atom_feed_emits_entries_newest_first() {
             title: "New Game".into(),
             path: "/works/2024/New Game".into(),
             summary: "a tagline".into(),
+            excerpt: "It begins.".into(),
             updated: "2024-03-15".into(),
+            thumbnail: Some("/thumb/abc123/card".into()),
+            reading_minutes: 2,
         },
         FeedEntry {
             title: "Old Game".into(),
             path: "/works/2016/Old".into(),
             summary: String::new(),
+            excerpt: String::new(),
             updated: "2016-01-01".into(),
+            thumbnail: None,
+            reading_minutes: 1,
         },
     ];
 
@@ -353,14 +421,70 @@ fn build_atom_feed_emits_entries_newest_first() {
     // then:
     // - Atom envelope, feed <updated> = newest entry, RFC-3339 timestamps
     // - absolute, percent-encoded entry links; empty summary omitted; newest first
+    // - the thumbnail, made absolute, as an enclosure
     assert!(xml.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
     assert!(xml.contains("<updated>2024-03-15T00:00:00Z</updated>"));
     assert!(xml.contains("<link href=\"https://example.com/works/2024/New%20Game\"/>"));
     assert!(xml.contains("<summary>a tagline</summary>"));
     assert_eq!(xml.matches("<summary>").count(), 1);
+    assert!(xml.contains(
+        r#"<link rel="enclosure" type="image/jpeg" href="https://example.com/thumb/abc123/card"/>"#
+    ));
+    assert_eq!(xml.matches("rel=\"enclosure\"").count(), 1);
     assert!(xml.find("New Game").unwrap() < xml.find("Old Game").unwrap());
 }
 
+#[test]
+fn build_json_feed_emits_items_newest_first() {
+    // given: two feed entries, already newest-first; the older has no text
+    let entries = vec![
+        FeedEntry {
+            title: "New Game".into(),
+            path: "/works/2024/New Game".into(),
+            summary: "a tagline".into(),
+            excerpt: "It begins.".into(),
+            updated: "2024-03-15".into(),
+            thumbnail: Some("/thumb/abc123/card".into()),
+            reading_minutes: 2,
+        },
+        FeedEntry {
+            title: "Old Game".into(),
+            path: "/works/2016/Old".into(),
+            summary: String::new(),
+            excerpt: String::new(),
+            updated: "2016-01-01".into(),
+            thumbnail: Some("https://example.org/old.png".into()),
+            reading_minutes: 1,
+        },
+    ];
+
+    // when: building the JSON feed
+    let feed = build_json_feed("https://example.com/", "Works", &entries);
+
+    // then:
+    // - JSON Feed 1.1 envelope with absolute home and feed URLs
+    // - items in the given order, absolute percent-encoded ids/urls, RFC-3339 dates
+    // - content_text is the excerpt, else the title; images absolute
+    // - the reading time under the `_lightvn` extension
+    assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+    assert_eq!(feed["title"], "Works");
+    assert_eq!(feed["home_page_url"], "https://example.com/");
+    assert_eq!(feed["feed_url"], "https://example.com/feed.json");
+    let items = feed["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["id"], "https://example.com/works/2024/New%20Game");
+    assert_eq!(items[0]["url"], items[0]["id"]);
+    assert_eq!(items[0]["title"], "New Game");
+    assert_eq!(items[0]["date_published"], "2024-03-15T00:00:00Z");
+    assert_eq!(items[0]["content_text"], "It begins.");
+    assert_eq!(items[0]["summary"], "a tagline");
+    assert_eq!(items[0]["image"], "https://example.com/thumb/abc123/card");
+    assert_eq!(items[1]["content_text"], "Old Game");
+    assert!(items[1].get("summary").is_none());
+    assert_eq!(items[1]["image"], "https://example.org/old.png");
+    assert_eq!(items[0]["_lightvn"]["reading_time_minutes"], 2);
+}
+
 #[test]
 fn game_page_suffixes_non_r18_no_params() {
     // given: a non-R18 game with no incoming lang or r18 params
@@ -407,6 +531,77 @@ fn game_page_suffixes_combines_lang_and_r18() {
     assert_eq!(fwd, "?lang=ja");
 }
 
+#[rstest]
+#[case::flat("Title", "", "")]
+#[case::nested(
+    "Series/Chapter 1",
+    "",
+    r#"<span>/</span><a href="/?search=Series/#2023">Series</a>"#
+)]
+#[case::deeper_keeps_suffix(
+    "Saga/Part 1/Chapter",
+    "?lang=ja",
+    concat!(
+        r#"<span>/</span><a href="/?lang=ja&amp;search=Saga/#2023">Saga</a>"#,
+        r#"<span>/</span><a href="/?lang=ja&amp;search=Saga/Part%201/#2023">Part 1</a>"#,
+    )
+)]
+fn breadcrumb_dirs_html_cases(#[case] title: &str, #[case] back: &str, #[case] expected: &str) {
+    // given: a 2023 work's title, possibly nested, and the page's back suffix
+
+    // when: building the directory breadcrumb steps
+    let html = breadcrumb_dirs_html("2023", title, back);
+
+    // then: one archive link per directory, searched by its cumulative path
+    assert_eq!(html, expected);
+}
+
+#[rstest]
+#[case::asset_at_root(r#"<link href="/style.css">"#, 0, r#"<link href="./style.css">"#)]
+#[case::asset_nested(
+    r#"<img src="/raw/2024/a.png">"#,
+    3,
+    r#"<img src="../../../raw/2024/a.png">"#
+)]
+#[case::page_becomes_dir(
+    r#"<a href="/works/2024/My%20Game?lang=ja">"#,
+    3,
+    r#"<a href="../../../works/2024/My%20Game/?lang=ja">"#
+)]
+#[case::home_keeps_fragment(r#"<a href="/?r18=0#2024">"#, 2, r#"<a href="../../?r18=0#2024">"#)]
+#[case::protocol_relative_kept(
+    r#"<script src="//gc.zgo.at/count.js">"#,
+    1,
+    r#"<script src="//gc.zgo.at/count.js">"#
+)]
+#[case::absolute_and_relative_kept(
+    r#"<a href="https://x.test/a"><img src="cover.png">"#,
+    1,
+    r#"<a href="https://x.test/a"><img src="cover.png">"#
+)]
+fn relativize_root_links_cases(#[case] html: &str, #[case] depth: usize, #[case] expected: &str) {
+    // given: page HTML exported `depth` directories below the site root
+
+    // when: rewriting its root-relative links
+    let (out, _) = relativize_root_links(html, depth);
+
+    // then: they climb back to the root; everything else is untouched
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn relativize_root_links_reports_targets() {
+    // given: a page linking a work, an asset and an external site
+    let html =
+        r#"<a href="/works/2024/A?lang=ja#top"><img src="/assets/x.1.css"><a href="//cdn.test/">"#;
+
+    // when: relativizing it
+    let (_, found) = relativize_root_links(html, 1);
+
+    // then: the root-relative paths, without query or fragment
+    assert_eq!(found, vec!["/works/2024/A", "/assets/x.1.css"]);
+}
+
 #[test]
 fn extract_uuid_from_user_attachment_url() {
     // given: a real-shape GitHub user-attachment URL
@@ -512,6 +707,92 @@ fn resize_thumbnail_preserves_composite_card_no_upscale() {
     assert_eq!(resized.height(), 216);
 }
 
+#[rstest]
+#[case::narrower_source(200, 100, 200, 100)]
+#[case::wider_source(1200, 300, 600, 150)]
+fn resize_to_width_keeps_aspect_and_never_upscales(
+    #[case] w: u32,
+    #[case] h: u32,
+    #[case] out_w: u32,
+    #[case] out_h: u32,
+) {
+    // given: a source image
+    let img = image::DynamicImage::new_rgb8(w, h);
+
+    // when: resized to 600 wide
+    let resized = resize_to_width(&img, 600);
+
+    // then: scaled down with its aspect kept, or left as-is when already narrower
+    assert_eq!((resized.width(), resized.height()), (out_w, out_h));
+}
+
+#[rstest]
+#[case::png("/raw/2023/cover.png", Some("/thumb/2023/cover.png?w=320"))]
+#[case::jpeg_upper("/raw/2023/Series/Shot.JPG", Some("/thumb/2023/Series/Shot.JPG?w=320"))]
+#[case::gif("/raw/2023/anim.gif", None)]
+#[case::query("/raw/2023/cover.png?v=2", None)]
+#[case::remote("https://example.com/cover.png", None)]
+fn local_thumb_url_proxies_local_png_and_jpeg(#[case] url: &str, #[case] expected: Option<&str>) {
+    // when: mapping an image URL to its resize-proxy form
+    let thumb = local_thumb_url(url, 320);
+
+    // then: only local PNG/JPEG paths without a query are proxied
+    assert_eq!(thumb.as_deref(), expected);
+}
+
+#[test]
+fn local_thumb_cache_name_changes_with_width_and_mtime() {
+    // given: one source at two mtimes
+    let source = Path::new("/srv/works/2023/cover.png");
+    let then = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    let later = then + std::time::Duration::from_secs(1);
+
+    // when: naming cache entries
+    let base = local_thumb_cache_name(source, 320, then);
+
+    // then: stable for the same inputs, distinct for another width or mtime, extension kept
+    assert_eq!(base, local_thumb_cache_name(source, 320, then));
+    assert_ne!(base, local_thumb_cache_name(source, 640, then));
+    assert_ne!(base, local_thumb_cache_name(source, 320, later));
+    assert!(base.ends_with("-320.png"), "{base}");
+}
+
+#[rstest]
+#[case::zero(0, None)]
+#[case::smallest(1, Some(160))]
+#[case::exact(320, Some(320))]
+#[case::between(321, Some(600))]
+#[case::widest(1024, Some(1024))]
+#[case::too_wide(1025, None)]
+fn local_thumb_width_snaps_up_to_a_served_width(#[case] w: u32, #[case] expected: Option<u32>) {
+    // given/when: a requested width
+    let width = local_thumb_width(w);
+
+    // then: the narrowest served width covering it, none out of range
+    assert_eq!(width, expected);
+}
+
+#[test]
+fn sweep_thumb_cache_keeps_newest_entries() {
+    // given: three cache entries written a second apart
+    let dir = scratch_dir("thumb-sweep");
+    let epoch = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    for (i, name) in ["old.png", "mid.png", "new.png"].into_iter().enumerate() {
+        let file = std::fs::File::create(dir.join(name)).unwrap();
+        file.set_modified(epoch + std::time::Duration::from_secs(i as u64))
+            .unwrap();
+    }
+
+    // when: sweeping down to two, then sweeping again
+    let swept = sweep_thumb_cache(&dir, 2);
+    let again = sweep_thumb_cache(&dir, 2);
+
+    // then: only the oldest went, and a dir within bounds is left alone
+    assert_eq!((swept, again), (1, 0));
+    assert!(!dir.join("old.png").exists());
+    assert!(dir.join("mid.png").exists() && dir.join("new.png").exists());
+}
+
 #[test]
 fn resize_thumbnail_shrinks_composite_for_ribbon() {
     // given: 1170x216 composite, ribbon target (900, 400)
@@ -553,6 +834,7 @@ fn encode_path_handles_reserved_chars(#[case] input: &str, #[case] expected: &st
 
 fn make_game(year: &str, title: &str, creator: &str, released: &str) -> ParsedGame {
     ParsedGame {
+        collection: None,
         year: year.to_string(),
         title: title.to_string(),
         path: format!("/works/{}/{}", year, title),
@@ -566,6 +848,20 @@ fn make_game(year: &str, title: &str, creator: &str, released: &str) -> ParsedGa
         thumbnail: None,
         thumbnail_ribbon: None,
         thumbnail_composite: false,
+        thumbnail_alt: None,
+        links: vec![],
+        plaintext: false,
+        ext: "md",
+        headings: vec![],
+        reading_minutes: 1,
+        word_count: 1,
+        char_count: 1,
+        math: false,
+        mermaid: false,
+        excerpt: None,
+        lang: None,
+        langs: vec![],
+        mtime: None,
     }
 }
 
@@ -694,6 +990,103 @@ fn composite_image_detected() {
     assert!(images[0].is_composite());
 }
 
+#[test]
+fn markdown_only_images_extracted() {
+    // given: a body using only markdown image syntax
+    let md = "![cover](https://github.com/user-attachments/assets/md1)\n\nText.\n\n![](https://github.com/user-attachments/assets/md2)";
+
+    // when: extracting images
+    let images = extract_all_images(md);
+
+    // then: both are found in order, without dimensions
+    let urls: Vec<&str> = images.iter().map(|img| img.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            "https://github.com/user-attachments/assets/md1",
+            "https://github.com/user-attachments/assets/md2",
+        ]
+    );
+    assert_eq!(images[0].width, None);
+    assert!(!images[0].is_composite());
+}
+
+#[test]
+fn mixed_images_keep_document_order() {
+    // given: a markdown image before a raw HTML image
+    let md = r#"![](https://github.com/user-attachments/assets/first)
+
+<img width="384" height="216" alt="image" src="https://github.com/user-attachments/assets/second" />"#;
+
+    // when: extracting images
+    let images = extract_all_images(md);
+
+    // then: the markdown image comes first and becomes the thumbnail
+    assert_eq!(images.len(), 2);
+    assert_eq!(
+        images[0].url,
+        "https://github.com/user-attachments/assets/first"
+    );
+    assert_eq!(
+        images[1].url,
+        "https://github.com/user-attachments/assets/second"
+    );
+    assert_eq!(images[1].width, Some(384));
+}
+
+#[rstest]
+#[case::markdown_alt("![Title *screen*](shot.png)", Some("Title screen"))]
+#[case::markdown_no_alt("![](shot.png)", None)]
+#[case::html_alt(
+    "<img width=\"384\" height=\"216\" alt=\"Rooftop CG\" src=\"https://github.com/user-attachments/assets/a\" />",
+    Some("Rooftop CG")
+)]
+#[case::github_placeholder(
+    "<img width=\"384\" height=\"216\" alt=\"image\" src=\"https://github.com/user-attachments/assets/a\" />",
+    None
+)]
+#[case::html_no_alt(
+    "<img data-alt=\"x\" src=\"https://github.com/user-attachments/assets/a\" />",
+    None
+)]
+fn extract_images_captures_alt_text(#[case] md: &str, #[case] expected: Option<&str>) {
+    // when: extracting images
+    let images = extract_all_images(md);
+
+    // then: the alt text is kept when it says something
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].alt.as_deref(), expected);
+}
+
+#[test]
+fn frontmatter_malformed_yaml_falls_back_to_whole_file() {
+    // given: a frontmatter block that is not valid YAML for GameMeta
+    let input = "---\ntags: [unclosed\n---\n\nBody.";
+
+    // when: parsing frontmatter
+    let (meta, body) = parse_frontmatter(input);
+
+    // then: meta is empty and the file is rendered as-is rather than dropped
+    assert!(meta.tags.is_none());
+    assert_eq!(body, input);
+}
+
+#[test]
+fn frontmatter_title_and_draft_parsed() {
+    // given: a complete block including a display title
+    let input = "---\ntitle: \"Hallows: Director's Cut\"\ncreator: Test\nreleased: 2024/01/01\ntags: [horror]\ndraft: true\n---\n\nBody.";
+
+    // when: parsing frontmatter
+    let (meta, body) = parse_frontmatter(input);
+
+    // then: every field is populated and the body excludes the block
+    assert_eq!(meta.title.as_deref(), Some("Hallows: Director's Cut"));
+    assert_eq!(meta.released.as_deref(), Some("2024/01/01"));
+    assert_eq!(meta.tags, Some(vec!["horror".to_string()]));
+    assert_eq!(meta.draft, Some(true));
+    assert_eq!(body, "Body.");
+}
+
 #[test]
 fn frontmatter_missing_og_fields_defaults_gracefully() {
     // given: minimal frontmatter with no tagline
@@ -854,7 +1247,12 @@ fn validate_all_markdown_files() {
     // when: checking each file for valid frontmatter and images
     let mut errors = Vec::new();
 
-    for entry in WalkDir::new(works_dir).into_iter().filter_map(|e| e.ok()) {
+    for entry in WalkDir::new(works_dir)
+        .into_iter()
+        // `_`-prefixed entries are support files (include snippets), not works.
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('_'))
+        .filter_map(|e| e.ok())
+    {
         let path = entry.path();
         if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some("md") {
             continue;
@@ -1021,6 +1419,189 @@ fn creator_index_groups_by_creator() {
     assert_eq!(index.get("bob").unwrap().len(), 1);
 }
 
+#[test]
+fn creator_index_skips_drafts() {
+    // given: a creator with one published work and one draft
+    let mut draft = make_game("2024", "Game B", "Alice", "2024/06/01");
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game("2024", "Game A", "Alice", "2024/01/01"),
+        draft,
+    ]);
+
+    // when: building the creator paths index
+    let index = build_creator_paths(&games);
+
+    // then: only the published work is listed
+    assert_eq!(index["alice"], vec!["/works/2024/Game A".to_string()]);
+}
+
+#[rstest]
+#[case::first("Chapter 1", None, Some("Chapter 2"))]
+#[case::middle("Chapter 2", Some("Chapter 1"), Some("Chapter 3"))]
+#[case::last("Chapter 3", Some("Chapter 2"), None)]
+fn sibling_works_finds_neighbours_in_tree_order(
+    #[case] current: &str,
+    #[case] prev: Option<&str>,
+    #[case] next: Option<&str>,
+) {
+    // given:
+    // - three chapters of a series, inserted out of order
+    // - a work beside the series folder and one in another series, neither a sibling
+    // - a draft chapter, left out as it is from the tree
+    let mut draft = make_game("2023", "Series/Chapter 2b", "A", "2023/01/01");
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game("2023", "Series/Chapter 3", "A", "2023/01/01"),
+        make_game("2023", "Series/Chapter 1", "A", "2023/01/01"),
+        make_game("2023", "Series/Chapter 2", "A", "2023/01/01"),
+        make_game("2023", "Standalone", "A", "2023/01/01"),
+        make_game("2023", "Other/Chapter 1", "A", "2023/01/01"),
+        draft,
+    ]);
+    let current = &games[&format!("/works/2023/Series/{}", current)];
+
+    // when: looking up its neighbours
+    let (p, n) = sibling_works(&games, current);
+
+    // then: the adjacent chapters, none past either end
+    assert_eq!(p.map(|g| g.name()), prev);
+    assert_eq!(n.map(|g| g.name()), next);
+}
+
+#[rstest]
+#[case::casing("2024", "MY-GAME", Some("my-game"))]
+#[case::space_for_hyphen("2024", "My Game", Some("my-game"))]
+#[case::hyphen_for_space("2024", "ghost-story", Some("Ghost Story"))]
+#[case::nested("2024", "series/chapter-1", Some("Series/Chapter 1"))]
+#[case::other_year("2023", "my-game", None)]
+#[case::ambiguous("2024", "twin", None)]
+#[case::draft("2024", "secret", None)]
+#[case::unknown("2024", "nothing", None)]
+fn find_loose_title_cases(#[case] year: &str, #[case] title: &str, #[case] expected: Option<&str>) {
+    // given:
+    // - works whose titles differ from the request in case or space/hyphen
+    // - two titles that fold alike, and a draft
+    let mut draft = make_game("2024", "Secret", "A", "2024/01/01");
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game("2024", "my-game", "A", "2024/01/01"),
+        make_game("2024", "Ghost Story", "A", "2024/01/01"),
+        make_game("2024", "Series/Chapter 1", "A", "2024/01/01"),
+        make_game("2024", "Twin", "A", "2024/01/01"),
+        make_game("2024", "twin", "A", "2024/01/01"),
+        draft,
+    ]);
+
+    // when: looking the requested title up loosely
+    let found = find_loose_title(&games, year, title);
+
+    // then: only a single, published match in that year resolves
+    assert_eq!(found.map(|g| g.title.as_str()), expected);
+}
+
+#[rstest]
+#[case::all_valid(
+    Some("#111"),
+    Some("ivory"),
+    Some("#ff8800cc"),
+    ":root{--bg:#111;--text:ivory;--accent:#ff8800cc;}"
+)]
+#[case::named_any_case(None, None, Some("RebeccaPurple"), ":root{--accent:RebeccaPurple;}")]
+#[case::invalid_dropped(
+    Some("#12345"),
+    Some("red;}body{display:none"),
+    Some("teal"),
+    ":root{--accent:teal;}"
+)]
+#[case::unknown_name(Some("blurple"), None, None, "")]
+#[case::none(None, None, None, "")]
+fn theme_css_keeps_only_safe_colours(
+    #[case] bg: Option<&str>,
+    #[case] text: Option<&str>,
+    #[case] accent: Option<&str>,
+    #[case] expected: &str,
+) {
+    // given: a work theme mixing valid and invalid colours
+    let theme = WorkTheme {
+        bg: bg.map(String::from),
+        text: text.map(String::from),
+        accent: accent.map(String::from),
+    };
+
+    // when: building its CSS
+    let css = theme_css(&theme);
+
+    // then: a :root rule with only the safe values, or nothing
+    assert_eq!(css, expected);
+}
+
+#[rstest]
+#[case::year("2024", &["Series"], &["Ghost Story", "My Game"])]
+#[case::nested("2024/Series", &[], &["Series/Chapter 1", "Series/Chapter 2"])]
+#[case::drafts_only("2023", &[], &[])]
+#[case::unknown("1999", &[], &[])]
+fn dir_children_cases(#[case] dir: &str, #[case] subdirs: &[&str], #[case] works: &[&str]) {
+    // given: a year with works, a series folder, and a year holding only a draft
+    let mut draft = make_game("2023", "Hidden/Secret", "A", "2023/01/01");
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game("2024", "My Game", "A", "2024/01/01"),
+        make_game("2024", "Ghost Story", "A", "2024/01/01"),
+        make_game("2024", "Series/Chapter 2", "A", "2024/01/01"),
+        make_game("2024", "Series/Chapter 1", "A", "2024/01/01"),
+        draft,
+    ]);
+
+    // when: listing the directory
+    let (found_dirs, found_works) = dir_children(&games, dir);
+
+    // then: direct subdirectories and published works, each by name
+    assert_eq!(found_dirs, subdirs);
+    let titles: Vec<&str> = found_works.iter().map(|g| g.title.as_str()).collect();
+    assert_eq!(titles, works);
+}
+
+#[rstest]
+#[case::year_index("index", true)]
+#[case::nested_index("Series/index", true)]
+#[case::suffix_only("Reindex", false)]
+#[case::index_folder("index/Chapter", false)]
+fn is_dir_index_cases(#[case] title: &str, #[case] expected: bool) {
+    // given: a work title as split from its path
+
+    // when / then: only a file named index is a landing page
+    assert_eq!(is_dir_index(title), expected);
+}
+
+#[rstest]
+#[case::external_https("https://example.com/a", Some(LinkCheck::External))]
+#[case::protocol_relative("//cdn.example.com/x.png", Some(LinkCheck::External))]
+#[case::work_absolute("/works/2024/Other", Some(LinkCheck::Ok))]
+#[case::work_relative("Other.md", Some(LinkCheck::Ok))]
+#[case::work_missing("/works/2024/Gone", Some(LinkCheck::Broken))]
+#[case::directory("/works/2024/Series", Some(LinkCheck::Ok))]
+#[case::raw_file("/raw/2024/cover.png", Some(LinkCheck::Ok))]
+#[case::relative_file("./cover.png?v=2", Some(LinkCheck::Ok))]
+#[case::relative_missing("shots/none.png", Some(LinkCheck::Broken))]
+#[case::anchor("#credits", None)]
+#[case::mailto("mailto:a@example.com", None)]
+#[case::site_route("/creator/Alice", None)]
+fn check_link_cases(#[case] target: &str, #[case] expected: Option<LinkCheck>) {
+    // given: works in 2024 (one nested) and a single file on disk
+    let games = games_map(vec![
+        make_game("2024", "Other", "A", "2024/01/01"),
+        make_game("2024", "Series/Chapter 1", "A", "2024/01/01"),
+    ]);
+    let file_exists = |f: &str| f == "2024/cover.png";
+
+    // when: checking a target linked from a 2024 work
+    let result = check_link(&games, "2024", target, file_exists);
+
+    // then: internal targets resolve or not; external and other links aren't checked
+    assert_eq!(result, expected);
+}
+
 #[test]
 fn creator_index_excludes_current_game() {
     // given: creator with 3 games
@@ -1334,6 +1915,85 @@ fn language_tag_propagates_yaml_colour_to_bar(cfg: HashMap<String, TagInfo>, #[c
     assert_eq!(row.colour.as_deref(), Some(info.colour.as_str()));
 }
 
+#[test]
+fn tag_index_skips_drafts() {
+    // given: two works tagged "horror", one of them a draft
+    let mut draft = make_game_with_tags("2024", "b", vec!["horror"]);
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game_with_tags("2024", "a", vec!["horror"]),
+        draft,
+    ]);
+
+    // when: building the tag bar
+    let bar = build_tag_index(&games, &HashMap::new());
+
+    // then: only the published work is counted
+    let row = bar
+        .iter()
+        .find(|e| e.name == "horror")
+        .expect("row present");
+    assert_eq!(row.count, 1);
+}
+
+#[test]
+fn tag_counts_merge_case_and_skip_drafts() {
+    // given: works tagged in mixed case, one listing a tag twice, plus a draft
+    let mut draft = make_game_with_tags("2024", "d", vec!["romance"]);
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game_with_tags("2024", "a", vec!["Romance", "r18"]),
+        make_game_with_tags("2024", "b", vec!["romance", "ROMANCE", "Horror"]),
+        make_game_with_tags("2024", "c", vec![]),
+        draft,
+    ]);
+
+    // when: counting works per tag
+    let counts = tag_counts(&games);
+
+    // then: one lowercased key per tag in use, each work counted once
+    let expected: Vec<(&str, usize)> = vec![("horror", 1), ("r18", 1), ("romance", 2)];
+    let actual: Vec<(&str, usize)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn year_counts_newest_first_with_other_folders_last() {
+    // given: works across years, a series folder, a non-year folder and a draft
+    let mut draft = make_game("2024", "Draft", "x", "2024/01/01");
+    draft.meta.draft = Some(true);
+    let games = games_map(vec![
+        make_game("2022", "A", "x", "2022/01/01"),
+        make_game("2024", "B", "x", "2024/01/01"),
+        make_game("2024", "Series/C", "x", "2024/01/01"),
+        make_game("Extras", "D", "x", ""),
+        make_game("2023", "E", "x", "2023/01/01"),
+        draft,
+    ]);
+
+    // when: counting works per year
+    let years = year_counts(&games);
+
+    // then: years newest first, then named folders; drafts left out
+    let actual: Vec<(&str, usize)> = years.iter().map(|y| (y.year.as_str(), y.count)).collect();
+    assert_eq!(
+        actual,
+        vec![("2024", 2), ("2023", 1), ("2022", 1), ("Extras", 1)]
+    );
+}
+
+#[rstest]
+#[case::same_case("Romance", true)]
+#[case::other_case("rOMANCE", true)]
+#[case::absent("horror", false)]
+fn has_tag_ignores_case(#[case] tag: &str, #[case] expected: bool) {
+    // given: a work tagged "Romance"
+    let game = make_game_with_tags("2024", "a", vec!["Romance"]);
+
+    // when / then: matching is case-insensitive
+    assert_eq!(has_tag(&game.meta, tag), expected);
+}
+
 #[rstest]
 fn language_tags_share_a_colour(cfg: HashMap<String, TagInfo>) {
     // given: the registered language tags loaded from the production yaml
@@ -1525,6 +2185,7 @@ fn tag_with_url_renders_event_link() {
 
 fn make_game_with_tags(year: &str, title: &str, tags: Vec<&str>) -> ParsedGame {
     ParsedGame {
+        collection: None,
         year: year.to_string(),
         title: title.to_string(),
         path: format!("/works/{}/{}", year, title),
@@ -1537,6 +2198,20 @@ fn make_game_with_tags(year: &str, title: &str, tags: Vec<&str>) -> ParsedGame {
         thumbnail: None,
         thumbnail_ribbon: None,
         thumbnail_composite: false,
+        thumbnail_alt: None,
+        links: vec![],
+        plaintext: false,
+        ext: "md",
+        headings: vec![],
+        reading_minutes: 1,
+        word_count: 1,
+        char_count: 1,
+        math: false,
+        mermaid: false,
+        excerpt: None,
+        lang: None,
+        langs: vec![],
+        mtime: None,
     }
 }
 
@@ -1711,3 +2386,2407 @@ fn tag_index_configured_carries_colour() {
     let row = bar.iter().find(|e| e.name == "Foo").unwrap();
     assert_eq!(row.colour.as_deref(), Some("#abcdef"));
 }
+
+#[test]
+fn expand_includes_inlines_shared_snippet() {
+    // given: a work that includes a shared disclaimer relative to itself
+    let root = scratch_dir("include-ok");
+    std::fs::create_dir_all(root.join("_shared")).unwrap();
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    std::fs::write(root.join("_shared/disclaimer.md"), "*Fan work.*\n").unwrap();
+    let work = root.join("2024/Game.md");
+    std::fs::write(&work, "").unwrap();
+    let body = "Intro.\n\n{{ include: ../_shared/disclaimer.md }}\n\nOutro.";
+
+    // when: expanding includes
+    let out = expand_includes(body, &work, &root);
+
+    // then: the directive is replaced by the snippet's markdown
+    assert_eq!(out.unwrap(), "Intro.\n\n*Fan work.*\n\nOutro.");
+}
+
+#[rstest]
+#[case::backtick_fence("```\n{{ include: snippet.md }}\n```")]
+#[case::tilde_fence("~~~md\n{{ include: snippet.md }}\n~~~")]
+#[case::indented("Text.\n\n    {{ include: snippet.md }}")]
+#[case::in_list("- Item\n\n  ```\n  {{ include: snippet.md }}\n  ```")]
+fn expand_includes_skips_code_blocks(#[case] body: &str) {
+    // given: a work showing the include syntax in a code block, with the
+    // file it names present
+    let root = scratch_dir(&format!("include-code-{}", body.len()));
+    std::fs::write(root.join("snippet.md"), "SNIPPET").unwrap();
+    let work = root.join("Game.md");
+    let body = format!("{}\n\n{{{{ include: snippet.md }}}}", body);
+
+    // when: expanding includes
+    let out = expand_includes(&body, &work, &root).unwrap();
+
+    // then: the directive in the code block is kept; the one after it expands
+    assert!(out.contains("{{ include: snippet.md }}"), "{}", out);
+    assert_eq!(out.matches("SNIPPET").count(), 1, "{}", out);
+    assert!(out.ends_with("SNIPPET"), "{}", out);
+}
+
+#[test]
+fn expand_includes_expands_inside_spoiler_fences() {
+    // given: an include inside a spoiler fence, whose body is markdown
+    let root = scratch_dir("include-spoiler");
+    std::fs::write(root.join("ending.md"), "The end.").unwrap();
+    let work = root.join("Game.md");
+
+    // when: expanding includes
+    let out = expand_includes("```spoiler\n{{ include: ending.md }}\n```", &work, &root);
+
+    // then: inlined like anywhere else in the body
+    assert_eq!(out.unwrap(), "```spoiler\nThe end.\n```");
+}
+
+#[rstest]
+#[case::dotdot("{{ include: ../../outside.md }}")]
+#[case::absolute("{{ include: /etc/hostname }}")]
+fn expand_includes_rejects_paths_outside_root(#[case] body: &str) {
+    // given: a file just outside the content root
+    let base = scratch_dir(&format!("include-escape-{}", body.len()));
+    let root = base.join("works");
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    std::fs::write(base.join("outside.md"), "secret").unwrap();
+    let work = root.join("2024/Game.md");
+    std::fs::write(&work, "").unwrap();
+
+    // when: a work tries to include it
+    let out = expand_includes(body, &work, &root);
+
+    // then: rejected as an escape, nothing inlined
+    assert!(matches!(out, Err(IncludeError::Escape(_))), "{:?}", out);
+}
+
+#[test]
+fn expand_includes_detects_cycle() {
+    // given: a.md includes b.md, which includes a.md back
+    let root = scratch_dir("include-cycle");
+    std::fs::write(root.join("a.md"), "A {{ include: b.md }}").unwrap();
+    std::fs::write(root.join("b.md"), "B {{ include: a.md }}").unwrap();
+    let work = root.join("a.md");
+
+    // when: expanding a.md's body
+    let out = expand_includes("A {{ include: b.md }}", &work, &root);
+
+    // then: the loop is reported instead of recursing forever
+    assert_eq!(out, Err(IncludeError::Cycle("a.md".to_string())));
+}
+
+#[test]
+fn expand_includes_follows_nested_includes() {
+    // given: a credits snippet that itself includes a shared footer
+    let root = scratch_dir("include-nested");
+    std::fs::create_dir_all(root.join("_shared/parts")).unwrap();
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    std::fs::write(
+        root.join("_shared/credits.md"),
+        "Credits.\n{{ include: parts/footer.md }}\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("_shared/parts/footer.md"), "Footer.\n").unwrap();
+    let work = root.join("2024/Game.md");
+    std::fs::write(&work, "").unwrap();
+
+    // when: expanding the work's include
+    let out = expand_includes("{{ include: ../_shared/credits.md }}", &work, &root);
+
+    // then: both levels are inlined, each relative to its own file
+    assert_eq!(out.unwrap(), "Credits.\nFooter.");
+}
+
+#[test]
+fn expand_includes_stops_past_max_depth() {
+    // given: a chain of distinct snippets longer than MAX_INCLUDE_DEPTH
+    let root = scratch_dir("include-deep");
+    for i in 0..=MAX_INCLUDE_DEPTH + 1 {
+        let body = format!("{} {{{{ include: {}.md }}}}", i, i + 1);
+        std::fs::write(root.join(format!("{}.md", i)), body).unwrap();
+    }
+    let work = root.join("0.md");
+
+    // when: expanding the head of the chain
+    let out = expand_includes("{{ include: 1.md }}", &work, &root);
+
+    // then: it gives up at the limit rather than following it all
+    assert!(matches!(out, Err(IncludeError::TooDeep(_))), "{:?}", out);
+}
+
+#[test]
+fn expand_includes_with_notes_replaces_only_the_failing_directive() {
+    // given: a work with a good include and one into a cycle
+    let root = scratch_dir("include-notes");
+    std::fs::write(root.join("ok.md"), "Shared.").unwrap();
+    std::fs::write(root.join("a.md"), "{{ include: b.md }}").unwrap();
+    std::fs::write(root.join("b.md"), "{{ include: a.md }}").unwrap();
+    let work = root.join("Game.md");
+    std::fs::write(&work, "").unwrap();
+    let body = "{{ include: ok.md }}\n\n{{ include: a.md }}\n\nOutro.";
+
+    // when: expanding for rendering
+    let (out, errors) = expand_includes_with_notes(body, &work, &root);
+
+    // then: the good include is inlined, the cyclic one noted in its place
+    assert_eq!(
+        out,
+        "Shared.\n\n*(include failed: a.md includes itself)*\n\nOutro."
+    );
+    assert_eq!(errors, [IncludeError::Cycle("a.md".to_string())]);
+}
+
+#[test]
+fn expand_includes_leaves_other_braces_alone() {
+    // given: a body with a non-include `{{…}}` (e.g. a template example in prose)
+    let root = scratch_dir("include-noop");
+    let work = root.join("Game.md");
+    std::fs::write(&work, "").unwrap();
+
+    // when: expanding includes
+    let out = expand_includes("Use {{name}} here.", &work, &root);
+
+    // then: unchanged
+    assert_eq!(out.unwrap(), "Use {{name}} here.");
+}
+
+#[rstest]
+#[case::epoch(0, "Thu, 01 Jan 1970 00:00:00 GMT")]
+#[case::rfc_example(784_111_777, "Sun, 06 Nov 1994 08:49:37 GMT")]
+#[case::leap_day(951_827_696, "Tue, 29 Feb 2000 12:34:56 GMT")]
+#[case::year_end(1_735_689_599, "Tue, 31 Dec 2024 23:59:59 GMT")]
+fn http_date_round_trips(#[case] secs: u64, #[case] expected: &str) {
+    // given: a Unix timestamp
+
+    // when: formatting it as an HTTP date, and parsing that back
+    let out = http_date(secs);
+
+    // then: the IMF-fixdate, which parses to the same second
+    assert_eq!(out, expected);
+    assert_eq!(parse_http_date(&out), Some(secs));
+}
+
+#[rstest]
+#[case::rfc_850("Sunday, 06-Nov-94 08:49:37 GMT")]
+#[case::asctime("Sun Nov  6 08:49:37 1994")]
+#[case::not_gmt("Sun, 06 Nov 1994 08:49:37 UTC")]
+#[case::bad_month("Sun, 06 Foo 1994 08:49:37 GMT")]
+#[case::bad_time("Sun, 06 Nov 1994 8:49:37 GMT")]
+#[case::out_of_range("Sun, 06 Nov 1994 24:00:00 GMT")]
+#[case::empty("")]
+fn parse_http_date_rejects_other_forms(#[case] raw: &str) {
+    // given: a date that isn't an IMF-fixdate
+    // when: parsing it
+    // then: nothing
+    assert_eq!(parse_http_date(raw), None);
+}
+
+#[rstest]
+#[case::same_second("Sun, 06 Nov 1994 08:49:37 GMT", true)]
+#[case::later("Mon, 07 Nov 1994 00:00:00 GMT", true)]
+#[case::earlier("Sun, 06 Nov 1994 08:49:36 GMT", false)]
+#[case::unparsable("yesterday", false)]
+fn not_modified_since_compares_to_mtime(#[case] since: &str, #[case] expected: bool) {
+    // given: a file modified at the RFC's example date
+    let mtime = 784_111_777;
+
+    // when: checking an If-Modified-Since against it
+    let out = not_modified_since(since, mtime);
+
+    // then: unchanged only for a parsable date at or after the mtime
+    assert_eq!(out, expected);
+}
+
+#[rstest]
+#[case::epoch(0, "1970-01-01")]
+#[case::leap_day(951_782_400, "2000-02-29")]
+#[case::year_end(1_735_603_200, "2024-12-31")]
+fn iso_date_from_unix_converts(#[case] secs: u64, #[case] expected: &str) {
+    // given: a Unix timestamp
+
+    // when: converting to a civil date
+    let out = iso_date_from_unix(secs);
+
+    // then: the matching UTC calendar day
+    assert_eq!(out, expected);
+}
+
+#[rstest]
+#[case::draft(Some(true), None, true)]
+#[case::draft_false(Some(false), None, false)]
+#[case::scheduled(None, Some("2024/07/01"), true)]
+#[case::added_today(None, Some("2024/06/15"), false)]
+#[case::already_added(None, Some("2024/01/01"), false)]
+#[case::normal(None, None, false)]
+fn is_noindex_flags_drafts_and_scheduled(
+    #[case] draft: Option<bool>,
+    #[case] date_added: Option<&str>,
+    #[case] expected: bool,
+) {
+    // given: a work's meta and a fixed "today"
+    let meta = GameMeta {
+        draft,
+        date_added: date_added.map(String::from),
+        ..Default::default()
+    };
+
+    // when: deciding whether to send noindex
+    let out = is_noindex(&meta, "2024-06-15");
+
+    // then: only drafts and future-dated works are noindexed
+    assert_eq!(out, expected);
+}
+
+#[rstest]
+#[case::matching(Some("s3cret"), Some("s3cret"), true)]
+#[case::wrong(Some("guess"), Some("s3cret"), false)]
+#[case::missing(None, Some("s3cret"), false)]
+#[case::no_secret(Some("s3cret"), None, false)]
+#[case::empty_secret(Some(""), Some(""), false)]
+fn draft_preview_requires_configured_token(
+    #[case] supplied: Option<&str>,
+    #[case] secret: Option<&str>,
+    #[case] expected: bool,
+) {
+    // given: a ?preview= value and the draft_preview_token setting
+
+    // when: deciding whether the preview unlocks drafts
+    let out = draft_preview_ok(supplied, secret);
+
+    // then: only an exact match against a non-empty secret does
+    assert_eq!(out, expected);
+}
+
+#[rstest]
+#[case::year_month("/:year/:month/:slug", Some("/{year}/{month}/{slug}"))]
+#[case::literal_prefix("/games/:year/:slug", Some("/games/{year}/{slug}"))]
+#[case::no_slug("/:year/:month", None)]
+#[case::unknown_token("/:year/:author/:slug", None)]
+#[case::reserved_prefix("/works/:year/:slug", None)]
+#[case::relative(":year/:slug", None)]
+#[case::empty_segment("/:year//:slug", None)]
+fn permalink_route_validates_pattern(#[case] pattern: &str, #[case] expected: Option<&str>) {
+    // given: a PERMALINK_PATTERN value
+
+    // when: converting it to an axum route
+    let route = permalink_route(pattern);
+
+    // then: usable patterns map `:tok` → `{tok}`; unusable ones are refused
+    assert_eq!(route.as_deref(), expected);
+}
+
+#[rstest]
+#[case::full_date("/:year/:month/:slug", Some("2024/09/30"), Some("/2024/09/My Game"))]
+#[case::day(
+    "/:year/:month/:day/:slug",
+    Some("2024/09/30"),
+    Some("/2024/09/30/My Game")
+)]
+#[case::year_only_pattern("/:year/:slug", Some("2024"), Some("/2024/My Game"))]
+#[case::partial_date("/:year/:month/:slug", Some("2024"), None)]
+#[case::unknown("/:year/:month/:slug", Some(RELEASED_UNKNOWN), None)]
+#[case::undated("/:year/:slug", None, None)]
+fn permalink_for_uses_release_date(
+    #[case] pattern: &str,
+    #[case] released: Option<&str>,
+    #[case] expected: Option<&str>,
+) {
+    // given: a work titled "My Game" with the given release date
+
+    // when: resolving its permalink
+    let link = permalink_for(pattern, released, "My Game");
+
+    // then: date parts come from `released`; missing parts mean no permalink
+    assert_eq!(link.as_deref(), expected);
+}
+
+#[rstest]
+#[case::canonical_path(r#"{"/works/2024/A": 3}"#, Some(vec![("/works/2024/A", 3)]))]
+#[case::file_path(r#"{"works/2024/A.md": 2}"#, Some(vec![("/works/2024/A", 2)]))]
+#[case::zero_dropped(r#"{"/works/2024/A": 0}"#, Some(vec![]))]
+#[case::not_an_object("[1, 2]", None)]
+#[case::negative_count(r#"{"/works/2024/A": -1}"#, None)]
+fn parse_comment_counts_cases(#[case] raw: &str, #[case] expected: Option<Vec<(&str, u64)>>) {
+    // given: a comment-count payload
+
+    // when: parsing it
+    let counts = parse_comment_counts(raw);
+
+    // then: keys are canonical work paths, zero counts dropped, bad payloads rejected
+    let expected = expected.map(|pairs| {
+        pairs
+            .into_iter()
+            .map(|(k, n)| (k.to_string(), n))
+            .collect::<std::collections::HashMap<_, _>>()
+    });
+    assert_eq!(counts, expected);
+}
+
+#[rstest]
+#[case::auto_alt_on("![](my-cover.png)", true, r#"alt="My Cover""#)]
+#[case::auto_alt_off("![](my-cover.png)", false, r#"alt="""#)]
+#[case::explicit_alt_kept("![Box art](my-cover.png)", true, r#"alt="Box art""#)]
+fn markdown_to_html_with_auto_alt(
+    #[case] md: &str,
+    #[case] auto_alt: bool,
+    #[case] expected: &str,
+) {
+    // given: an image, with or without alt text
+
+    // when: rendering with the option set or not
+    let html = markdown_to_html_with(
+        md,
+        &RenderOptions {
+            auto_alt,
+            ..Default::default()
+        },
+    );
+
+    // then: only an empty alt is filled in, and only when opted in
+    assert!(html.contains(expected), "{}", html);
+}
+
+#[test]
+fn markdown_renders_gfm_table_and_task_list() {
+    // given: a stats table, a route checklist, strikethrough and a footnote
+    let md = "| Stat | Value |\n|------|-------|\n| STR | 5 |\n\n- [x] Route A\n- [ ] Route B\n\n~~cut~~ scene[^1]\n\n[^1]: Restored in 1.1.";
+
+    // when: rendering
+    let html = markdown_to_html(md);
+
+    // then: the extensions render as HTML, and the table carries its class
+    assert!(html.contains(r#"<table class="md-table">"#), "{}", html);
+    assert!(html.contains(r#"type="checkbox""#), "{}", html);
+    assert!(html.contains("<del>cut</del>"), "{}", html);
+    assert!(html.contains("footnote-definition"), "{}", html);
+}
+
+#[rstest]
+#[case::dashes("my-cover.png", "My Cover")]
+#[case::underscores_and_dirs("/raw/2024/title_screen_01.webp", "Title Screen 01")]
+#[case::query_dropped("https://example.com/a/key.visual.jpg?w=800", "Key Visual")]
+#[case::encoded_space("cg%20one.png", "Cg One")]
+#[case::nothing_readable("https://example.com/", "")]
+fn alt_from_filename_cases(#[case] url: &str, #[case] expected: &str) {
+    // given: an image URL
+
+    // when: deriving alt text from it
+    let alt = alt_from_filename(url);
+
+    // then: the filename is humanized
+    assert_eq!(alt, expected);
+}
+
+#[rstest]
+#[case::known(
+    "<link href=\"{{asset:style.css}}\">",
+    "<link href=\"/assets/style.abc.css\">"
+)]
+#[case::unknown_falls_back(
+    "<script src=\"{{asset:nope.js}}\">",
+    "<script src=\"/assets/nope.js\">"
+)]
+#[case::unterminated("{{asset:style.css", "{{asset:style.css")]
+#[case::other_placeholders_untouched("{{title_display}}", "{{title_display}}")]
+fn resolve_asset_refs_cases(#[case] template: &str, #[case] expected: &str) {
+    // given: a manifest with one hashed asset
+    let urls = std::collections::HashMap::from([(
+        "style.css".to_string(),
+        "/assets/style.abc.css".to_string(),
+    )]);
+
+    // when: resolving asset placeholders in a template
+    let html = resolve_asset_refs(template, &urls);
+
+    // then: known names get their hashed URL, unknown ones their plain path
+    assert_eq!(html, expected);
+}
+
+#[test]
+fn hashed_asset_name_changes_with_contents() {
+    // given: two versions of the same file
+
+    // when: fingerprinting each
+    let a = hashed_asset_name("style.css", b"body{}");
+    let b = hashed_asset_name("style.css", b"body{color:red}");
+
+    // then: the stem and extension are kept and the hash differs
+    assert!(a.starts_with("style.") && a.ends_with(".css"));
+    assert_eq!(a.len(), "style..css".len() + 16);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn extract_link_targets_finds_markdown_and_html_targets() {
+    // given: markdown links/images plus an inline-HTML image
+    let md = "[site](https://example.com) ![](cover.png)\n\n<img src=\"/raw/2024/shot.webp\" width=\"10\">\n";
+
+    // when: extracting link targets
+    let targets = extract_link_targets(md);
+
+    // then: all three, in document order
+    assert_eq!(
+        targets,
+        vec!["https://example.com", "cover.png", "/raw/2024/shot.webp"]
+    );
+}
+
+#[rstest]
+#[case::relative("works/2024", "cover.png", Some("works/2024/cover.png"))]
+#[case::dot_segments("works/2024", "./a/../b.png", Some("works/2024/b.png"))]
+#[case::parent("works/2024", "../2023/Other", Some("works/2023/Other"))]
+#[case::absolute_ignores_base("works/2024", "/raw/x.png", Some("raw/x.png"))]
+#[case::query_and_fragment("works/2024", "a.png?v=2#top", Some("works/2024/a.png"))]
+#[case::percent_decoded("works/2024", "my%20cover.png", Some("works/2024/my cover.png"))]
+#[case::escapes_root("works/2024", "../../../etc/passwd", None)]
+#[case::absolute_escapes_root("works/2024", "/raw/../../etc/passwd", None)]
+#[case::encoded_escape("works/2024", "%2E%2E/%2E%2E/%2E%2E/etc/passwd", None)]
+#[case::nothing_left("works/2024", "../..", None)]
+#[case::external("works/2024", "https://example.com/a.png", None)]
+#[case::protocol_relative("works/2024", "//cdn.example.com/a.png", None)]
+#[case::anchor("works/2024", "#credits", None)]
+#[case::mailto("works/2024", "mailto:a@example.com", None)]
+fn resolve_relative_cases(
+    #[case] base: &str,
+    #[case] target: &str,
+    #[case] expected: Option<&str>,
+) {
+    // given: a link target on a page under `base`
+
+    // when: resolving it
+    let path = resolve_relative(base, target);
+
+    // then: `.`/`..` applied against `base` (or the root), never climbing above it
+    assert_eq!(path.as_deref(), expected);
+}
+
+#[rstest]
+#[case::relative("2024", "cover.png", Some("2024/cover.png"))]
+#[case::dot_relative("2024", "./img/a.png", Some("2024/img/a.png"))]
+#[case::parent("2024", "../_shared/logo.png", Some("_shared/logo.png"))]
+#[case::raw_route("2024", "/raw/2023/x.png?v=2", Some("2023/x.png"))]
+#[case::percent_decoded("2024", "my%20cover.png", Some("2024/my cover.png"))]
+#[case::escapes_works("2024", "../../etc/passwd", None)]
+#[case::page_route("2024", "/works/2024/Other", None)]
+fn local_asset_path_cases(#[case] dir: &str, #[case] target: &str, #[case] expected: Option<&str>) {
+    // given: a link target in a work under `dir`
+
+    // when: resolving it to a file under works/
+    let path = local_asset_path(dir, target);
+
+    // then: only local files resolve, relative to the content root
+    assert_eq!(path.as_deref(), expected);
+}
+
+#[test]
+fn find_orphans_reports_unreferenced_files_only() {
+    // given: a content root with a referenced image, an unreferenced one,
+    // markdown, and support files
+    let root = scratch_dir("orphans");
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    std::fs::create_dir_all(root.join("_shared")).unwrap();
+    std::fs::write(root.join("2024/Game.md"), "![](used.png)").unwrap();
+    std::fs::write(root.join("2024/used.png"), [0u8; 4]).unwrap();
+    std::fs::write(root.join("2024/unused.png"), [0u8; 7]).unwrap();
+    std::fs::write(root.join("_shared/logo.png"), [0u8; 1]).unwrap();
+    std::fs::write(root.join("2024/.gitkeep"), "").unwrap();
+    let referenced = std::collections::HashSet::from(["2024/used.png".to_string()]);
+
+    // when: scanning for orphans
+    let orphans = find_orphans(&root, &referenced);
+
+    // then: just the unreferenced image, with its size
+    assert_eq!(orphans, vec![("2024/unused.png".to_string(), 7)]);
+}
+
+#[test]
+fn fs_limiter_never_exceeds_bound() {
+    // given: a limiter of 3 and a gauge of currently-held permits
+    let limiter = FsLimiter::new(3);
+    let held = std::sync::atomic::AtomicUsize::new(0);
+    let peak = std::sync::atomic::AtomicUsize::new(0);
+
+    // when: 16 threads each take a permit and hold it briefly
+    std::thread::scope(|s| {
+        for _ in 0..16 {
+            s.spawn(|| {
+                let _permit = limiter.acquire();
+                let now = held.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                peak.fetch_max(now, std::sync::atomic::Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                held.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            });
+        }
+    });
+
+    // then: no more than 3 were ever held at once, and all were released
+    let peak = peak.load(std::sync::atomic::Ordering::SeqCst);
+    assert!((1..=3).contains(&peak), "peak {}", peak);
+    assert_eq!(held.load(std::sync::atomic::Ordering::SeqCst), 0);
+}
+
+#[test]
+fn rate_limiter_spends_and_refills_per_client() {
+    // given: a limiter of 60 a minute (one token a second)
+    let limiter = RateLimiter::new(60);
+    let start = std::time::Instant::now();
+
+    // when: one client spends its whole burst, then asks once more
+    for _ in 0..60 {
+        assert!(limiter.check("10.0.0.1", start).is_ok());
+    }
+    let refused = limiter.check("10.0.0.1", start);
+
+    // then: refused with about a second to wait; another client is unaffected,
+    //   and the first is let back in once a token has refilled
+    let wait = refused.unwrap_err();
+    assert!(wait > std::time::Duration::from_millis(900), "{:?}", wait);
+    assert!(wait <= std::time::Duration::from_secs(1), "{:?}", wait);
+    assert!(limiter.check("10.0.0.2", start).is_ok());
+    let later = start + std::time::Duration::from_secs(1);
+    assert!(limiter.check("10.0.0.1", later).is_ok());
+    assert!(limiter.check("10.0.0.1", later).is_err());
+}
+
+#[test]
+fn plaintext_to_html_escapes_markup() {
+    // given: a .txt body containing HTML and significant whitespace
+    let text = "<script>alert(1)</script>\n  indented & <b>bold</b>";
+
+    // when: rendering it as a plaintext work
+    let html = plaintext_to_html(text);
+
+    // then: a <pre> with the markup escaped verbatim, never interpreted
+    assert_eq!(
+        html,
+        "<pre class=\"plaintext\">&lt;script&gt;alert(1)&lt;/script&gt;\n  indented &amp; &lt;b&gt;bold&lt;/b&gt;</pre>"
+    );
+}
+
+#[rstest]
+#[case::markdown("md", "Title.md")]
+#[case::long_markdown("markdown", "Title.markdown")]
+#[case::plaintext("txt", "Title.txt")]
+fn parsed_game_file_name(#[case] ext: &'static str, #[case] expected: &str) {
+    // given: a game from a .md, .markdown or .txt source
+    let game = ParsedGame {
+        plaintext: ext == "txt",
+        ext,
+        ..make_game("2024", "Title", "A", "2024/01/01")
+    };
+
+    // when: asking for its source file name
+    let name = game.file_name();
+
+    // then: the extension follows the source kind
+    assert_eq!(name, expected);
+}
+
+#[rstest]
+#[case::canonical("/works/2023/Title", PathNormalization::Canonical)]
+#[case::root("/", PathNormalization::Canonical)]
+#[case::trailing_slash_kept("/works/", PathNormalization::Canonical)]
+#[case::doubled_slashes("/works//2023//Title", PathNormalization::Redirect("/works/2023/Title".into()))]
+#[case::dot_segment("/works/2023/./Title", PathNormalization::Redirect("/works/2023/Title".into()))]
+#[case::encoded_dot("/works/%2E/2023/Title", PathNormalization::Redirect("/works/2023/Title".into()))]
+#[case::leading_doubled("//works/2023/Title", PathNormalization::Redirect("/works/2023/Title".into()))]
+#[case::dotdot("/works/2023/../2024/Title", PathNormalization::Reject)]
+#[case::encoded_dotdot("/works/%2e%2e/Title", PathNormalization::Reject)]
+fn normalize_path_cases(#[case] path: &str, #[case] expected: PathNormalization) {
+    // given: a raw request path
+
+    // when: normalizing it
+    let outcome = normalize_path(path);
+
+    // then: clean paths pass, sloppy ones redirect, `..` is refused
+    assert_eq!(outcome, expected);
+}
+
+#[rstest]
+#[case::ordered_and_normalized(
+    "works = [\"works/2024/B.md\", \"/works/2023/A\"]\n",
+    Some(vec!["/works/2024/B", "/works/2023/A"])
+)]
+#[case::duplicates_dropped(
+    "works = [\"/works/2024/B\", \"works/2024/B.md\"]",
+    Some(vec!["/works/2024/B"])
+)]
+#[case::empty_file("", Some(vec![]))]
+#[case::not_a_list("works = 3", None)]
+#[case::not_toml("- works/2024/B.md", None)]
+fn parse_featured_cases(#[case] raw: &str, #[case] expected: Option<Vec<&str>>) {
+    // given: a featured-works TOML payload
+
+    // when: parsing it
+    let featured = parse_featured(raw);
+
+    // then: canonical paths in file order, first occurrence wins
+    let expected = expected.map(|v| v.into_iter().map(String::from).collect::<Vec<_>>());
+    assert_eq!(featured, expected);
+}
+
+#[rstest]
+#[case::webp_sibling(&["cover.png", "cover.webp"], "<picture><source srcset=\"/raw/2024/cover.webp\" type=\"image/webp\"><img src=\"/raw/2024/cover.png\"")]
+#[case::avif_first(&["cover.png", "cover.webp", "cover.avif"], "<picture><source srcset=\"/raw/2024/cover.avif\" type=\"image/avif\"><source srcset=\"/raw/2024/cover.webp\" type=\"image/webp\"><img")]
+#[case::no_sibling(&["cover.png"], "<p><img src=\"/raw/2024/cover.png\"")]
+fn markdown_image_with_modern_siblings(#[case] files: &[&str], #[case] expected: &str) {
+    // given: a content root holding cover.png and maybe modern-format siblings
+    let root = scratch_dir(&format!("picture-{}", files.len()));
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    for f in files {
+        std::fs::write(root.join("2024").join(f), [0u8]).unwrap();
+    }
+
+    // when: rendering a work body that embeds cover.png
+    let html = markdown_to_html_with(
+        "![Cover](/raw/2024/cover.png)",
+        &RenderOptions {
+            asset_root: Some(root),
+            work_dir: "2024".to_string(),
+            ..Default::default()
+        },
+    );
+
+    // then: each existing sibling is offered as a <source>, the <img> stays the fallback
+    assert!(html.contains(expected), "{}", html);
+    assert_eq!(html.contains("</picture>"), files.len() > 1);
+}
+
+#[test]
+fn markdown_images_get_lazy_loading_and_local_dimensions() {
+    // given: a content root with a 3×2 PNG, embedded by markdown next to a remote image and an HTML one
+    let root = scratch_dir("image-hints");
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    image::RgbImage::new(3, 2)
+        .save(root.join("2024").join("shot.png"))
+        .unwrap();
+    let md = "![Shot](shot.png)\n\n![Remote](https://example.com/a.png)\n\n<img src=\"shot.png\" width=\"10\" loading=\"eager\">";
+
+    // when: rendering the body of a 2024 work
+    let html = markdown_to_html_with(
+        md,
+        &RenderOptions {
+            work_dir: "2024".to_string(),
+            image_root: Some(root),
+            ..Default::default()
+        },
+    );
+
+    // then: every image loads lazily; only the local one gets its size, and set attributes are kept
+    assert!(html.contains(r#"<img src="/raw/2024/shot.png" alt="Shot" loading="lazy" decoding="async" width="3" height="2" />"#), "{html}");
+    assert!(html.contains(r#"<img src="https://example.com/a.png" alt="Remote" loading="lazy" decoding="async" />"#), "{html}");
+    assert!(
+        html.contains(r#"<img src="shot.png" width="10" loading="eager" decoding="async" />"#),
+        "{html}"
+    );
+}
+
+#[rstest]
+#[case::absolute("2024", "/works/2023/Other", Some("/works/2023/Other"))]
+#[case::encoded("2024", "/works/2023/My%20Game?lang=ja", Some("/works/2023/My Game"))]
+#[case::same_year_relative("2024", "Other", Some("/works/2024/Other"))]
+#[case::other_year_relative("2024", "../2023/Other.md", Some("/works/2023/Other"))]
+#[case::not_a_work("2024", "/creator/Sumica", None)]
+#[case::raw_asset("2024", "/raw/2024/cover.png", None)]
+#[case::external("2024", "https://example.com/works/2023/Other", None)]
+#[case::nested_sibling("2024/Series", "Chapter 2", Some("/works/2024/Series/Chapter 2"))]
+#[case::nested_up("2024/Series", "../Other", Some("/works/2024/Other"))]
+fn work_link_target_cases(
+    #[case] year: &str,
+    #[case] target: &str,
+    #[case] expected: Option<&str>,
+) {
+    // given: a link target on a work page in `year`
+
+    // when: resolving it to a work path
+    let path = work_link_target(year, target);
+
+    // then: only work-shaped URLs resolve, relative to /works/{year}/
+    assert_eq!(path.as_deref(), expected);
+}
+
+#[test]
+fn link_graph_dot_clusters_by_year_and_keeps_known_links() {
+    // given:
+    // - A links to B (twice, in two forms), to itself, and to a missing work
+    // - B's title needs DOT escaping
+    let mut a = make_game("2023", "A", "X", "2023/01/01");
+    a.links = vec![
+        "/works/2024/B".to_string(),
+        "../2024/B.md".to_string(),
+        "A".to_string(),
+        "/works/2024/Gone".to_string(),
+    ];
+    let mut b = make_game("2024", "B", "X", "2024/01/01");
+    b.title = "B \"quoted\"".to_string();
+    let games = std::collections::HashMap::from([(a.path.clone(), a), (b.path.clone(), b)]);
+
+    // when: rendering the link graph
+    let dot = link_graph_dot(&games);
+
+    // then:
+    // - one cluster per year, nodes labelled by (escaped) title
+    // - a single A → B edge; the self-link and the missing target are dropped
+    assert!(dot.starts_with("digraph works {"));
+    assert!(dot.contains("subgraph \"cluster_2023\" {"));
+    assert!(dot.contains("subgraph \"cluster_2024\" {"));
+    assert!(dot.contains("\"/works/2024/B\" [label=\"B \\\"quoted\\\"\"];"));
+    assert_eq!(dot.matches(" -> ").count(), 1);
+    assert!(dot.contains("\"/works/2023/A\" -> \"/works/2024/B\";"));
+}
+
+#[test]
+fn link_graph_dot_leaves_out_drafts() {
+    // given: A links to B, a draft, which links back
+    let mut a = make_game("2023", "A", "X", "2023/01/01");
+    a.links = vec!["/works/2024/B".to_string()];
+    let mut b = make_game("2024", "B", "X", "2024/01/01");
+    b.meta.draft = Some(true);
+    b.links = vec!["/works/2023/A".to_string()];
+    let games = games_map(vec![a, b]);
+
+    // when: rendering the link graph
+    let dot = link_graph_dot(&games);
+
+    // then: only A is there, with no edges
+    assert!(dot.contains("\"/works/2023/A\""));
+    assert!(!dot.contains("/works/2024/B"), "{}", dot);
+    assert!(!dot.contains(" -> "));
+}
+
+#[rstest]
+#[case::html_default("", "/works/2024/Title", Some("text/html; charset=utf-8"), 0)]
+#[case::png_default("", "/raw/2024/cover.png", Some("image/png"), 86_400)]
+#[case::json_default("", "/api/tree", Some("application/json"), 0)]
+#[case::html_override(
+    "text/html=60",
+    "/works/2024/Title",
+    Some("text/html; charset=utf-8"),
+    60
+)]
+#[case::extension_beats_type(".png=5,image/*=100", "/raw/a.PNG", Some("image/png"), 5)]
+#[case::exact_beats_wildcard("image/png=7", "/raw/a.png", Some("image/png"), 7)]
+#[case::later_rule_wins("text/css=1,text/css=2", "/style.css", Some("text/css"), 2)]
+#[case::malformed_skipped("text/html=soon,=3,junk", "/", Some("text/html"), 0)]
+#[case::raw_asset_default("", "/raw/2024/theme.mp3", Some("audio/mpeg"), 86_400)]
+#[case::raw_source_revalidates("", "/raw/2024/Title.md", Some("text/markdown"), 0)]
+#[case::raw_json_revalidates("", "/raw/_comments.json", Some("application/json"), 0)]
+#[case::prefix_override("/raw/=60", "/raw/2024/theme.mp3", Some("audio/mpeg"), 60)]
+#[case::longest_prefix_wins("/raw/=60,/raw/2024/=5", "/raw/2024/a.zip", None, 5)]
+#[case::prefix_outside_raw("", "/download.zip", Some("application/zip"), 0)]
+fn cache_policy_cases(
+    #[case] raw: &str,
+    #[case] path: &str,
+    #[case] content_type: Option<&str>,
+    #[case] expected: u64,
+) {
+    // given: a cache policy from Config::cache_policy (empty = defaults)
+    let policy = CachePolicy::parse(raw);
+
+    // when: looking up a response's max-age
+    let max_age = policy.max_age(path, content_type);
+
+    // then: the most specific matching rule applies
+    assert_eq!(max_age, expected);
+}
+
+#[rstest]
+#[case::revalidate(0, "no-cache")]
+#[case::cached(60, "public, max-age=60")]
+fn cache_policy_header_value(#[case] max_age: u64, #[case] expected: &str) {
+    // given: a max-age
+
+    // when: rendering it as a Cache-Control value
+    let value = CachePolicy::header_value(max_age);
+
+    // then: zero revalidates every time, anything else is a public max-age
+    assert_eq!(value, expected);
+}
+
+#[rstest]
+#[case::english("<p>Three little words</p>", 3)]
+#[case::tags_stripped("<p><a href=\"x y z\">one</a> two</p>", 2)]
+#[case::japanese("<p>幽霊少女室</p>", 5)]
+#[case::mixed("<p>Light.vn で作った</p>", 5)]
+#[case::empty("", 0)]
+fn word_count_cases(#[case] html: &str, #[case] expected: usize) {
+    // given: rendered work HTML
+
+    // when: counting its words
+    let count = word_count(html);
+
+    // then: markup is ignored and CJK characters count individually
+    assert_eq!(count, expected);
+}
+
+#[test]
+fn quality_failures_flags_each_rule() {
+    // given: a work with no thumbnail, release date, heading or much text
+    let mut game = make_game("2024", "Bare", "X", "");
+    game.meta.released = None;
+    game.body_html = "<p>Too short.</p>".to_string();
+
+    // when: checking it against the default rules, and with none enabled
+    let all = quality_failures(&game, &QualityRules::default());
+    let none = quality_failures(&game, &QualityRules::from_params(Some(""), None));
+
+    // then: every default rule fails, and disabled rules never do
+    assert_eq!(
+        all,
+        vec![
+            "missing_thumbnail",
+            "missing_released",
+            "short_body",
+            "no_headings"
+        ]
+    );
+    assert!(none.is_empty());
+}
+
+#[test]
+fn quality_failures_passes_a_complete_work() {
+    // given: a work with a thumbnail, release date, heading and enough text
+    let mut game = make_game("2024", "Complete", "X", "2024/01/01");
+    game.thumbnail = Some("/thumb/abc/card".to_string());
+    game.body_html = format!("<h2>Story</h2><p>{}</p>", "word ".repeat(40));
+
+    // when: checking it against the default rules
+    let failed = quality_failures(&game, &QualityRules::default());
+
+    // then: nothing fails
+    assert!(failed.is_empty(), "{:?}", failed);
+}
+
+#[rstest]
+#[case::defaults(None, None, QualityRules::default())]
+#[case::subset(Some("thumbnail,words"), Some("5"), QualityRules { thumbnail: true, released: false, min_words: Some(5), headings: false })]
+#[case::bad_floor(Some("words"), Some("many"), QualityRules { thumbnail: false, released: false, min_words: Some(DEFAULT_MIN_WORDS), headings: false })]
+fn quality_rules_from_params(
+    #[case] rules: Option<&str>,
+    #[case] min_words: Option<&str>,
+    #[case] expected: QualityRules,
+) {
+    // given: /api/quality query params
+
+    // when: building the rule set
+    let parsed = QualityRules::from_params(rules, min_words);
+
+    // then: only the named rules are on, with the requested floor
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+fn reloading_reuses_value_until_file_changes() {
+    // given: a reloading value over a file, counting how often it's derived
+    let dir = scratch_dir("reloading");
+    let file = dir.join("data.txt");
+    std::fs::write(&file, "one").unwrap();
+    let derived = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = derived.clone();
+    let value = Reloading::new(file.clone(), move |raw| {
+        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        raw.unwrap_or("").to_string()
+    });
+
+    // when: reading it twice, then again after the file is rewritten
+    let first = value.current();
+    let second = value.current();
+    std::fs::write(&file, "two").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+    let third = value.current();
+
+    // then:
+    // - the second read is served from cache (no re-read, no re-derive)
+    // - the change is picked up on the next read
+    assert_eq!((first.as_str(), second.as_str()), ("one", "one"));
+    assert!(std::sync::Arc::ptr_eq(&first, &second));
+    assert_eq!(third.as_str(), "two");
+    assert_eq!(derived.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+#[test]
+fn reloading_missing_file_derives_from_none() {
+    // given: a reloading value over a file that doesn't exist
+    let file = scratch_dir("reloading-missing").join("absent.json");
+
+    // when: reading it
+    let value = Reloading::new(file, |raw| raw.is_none());
+
+    // then: derive saw no contents
+    assert!(*value.current());
+}
+
+#[test]
+fn reloading_lazy_derives_on_first_read() {
+    // given: a lazy reloading value, counting derives
+    let file = scratch_dir("reloading-lazy").join("absent.json");
+    let derived = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = derived.clone();
+    let value = Reloading::lazy(file, move |_| {
+        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    });
+    let before = (
+        value.is_loaded(),
+        derived.load(std::sync::atomic::Ordering::SeqCst),
+    );
+
+    // when: reading it twice
+    value.current();
+    value.current();
+
+    // then: nothing was derived until the first read, and only once after
+    assert_eq!(before, (false, 0));
+    assert!(value.is_loaded());
+    assert_eq!(derived.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[test]
+fn reloading_concurrent_reads_share_one_derive() {
+    // given: a lazy reloading value whose derive waits for a go-ahead
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    let file = scratch_dir("reloading-single-flight").join("absent.json");
+    let (derived, go) = (
+        std::sync::Arc::new(AtomicUsize::new(0)),
+        std::sync::Arc::new(AtomicBool::new(false)),
+    );
+    let (counter, gate) = (derived.clone(), go.clone());
+    let value = std::sync::Arc::new(Reloading::lazy(file, move |_| {
+        while !gate.load(Ordering::SeqCst) {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        counter.fetch_add(1, Ordering::SeqCst)
+    }));
+
+    // when: two threads read it at once, and the derive is let go
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let value = value.clone();
+            std::thread::spawn(move || *value.current())
+        })
+        .collect();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    let loaded_mid_derive = value.is_loaded();
+    go.store(true, Ordering::SeqCst);
+    let seen: Vec<usize> = readers.into_iter().map(|r| r.join().unwrap()).collect();
+
+    // then: the derive didn't hold up `is_loaded`, ran once, and both reads
+    // got its value
+    assert!(!loaded_mid_derive);
+    assert_eq!(derived.load(Ordering::SeqCst), 1);
+    assert_eq!(seen, [0, 0]);
+}
+
+#[cfg(unix)]
+#[test]
+fn resolves_within_follows_symlinks() {
+    // given: a root holding a real file, a link to it, and a link escaping the root
+    let base = scratch_dir("within");
+    let root = base.join("works");
+    std::fs::create_dir_all(root.join("2024")).unwrap();
+    std::fs::write(root.join("2024/Real.md"), "x").unwrap();
+    std::fs::write(base.join("secret.md"), "x").unwrap();
+    std::os::unix::fs::symlink(root.join("2024/Real.md"), root.join("2024/Alias.md")).unwrap();
+    std::os::unix::fs::symlink(base.join("secret.md"), root.join("2024/Escape.md")).unwrap();
+    let canonical_root = std::fs::canonicalize(&root).unwrap();
+
+    // when: checking each path, plus a `..` path and a dangling one
+    let real = resolves_within(&canonical_root, &root.join("2024/Real.md"));
+    let alias = resolves_within(&canonical_root, &root.join("2024/Alias.md"));
+    let escape = resolves_within(&canonical_root, &root.join("2024/Escape.md"));
+    let dotdot = resolves_within(&canonical_root, &root.join("2024/../../secret.md"));
+    let missing = resolves_within(&canonical_root, &root.join("2024/Nope.md"));
+
+    // then: only paths that really land inside the root pass
+    assert!(real && alias);
+    assert!(!escape && !dotdot && !missing);
+}
+
+#[rstest]
+#[case::single_term(
+    "The Mansion holds secrets.",
+    "mansion",
+    Some("The Mansion holds secrets.")
+)]
+#[case::all_terms_required("The Mansion holds secrets.", "mansion ghost", None)]
+#[case::terms_in_any_order(
+    "The Mansion holds secrets.",
+    "SECRETS the",
+    Some("The Mansion holds secrets.")
+)]
+#[case::blank_query("The Mansion holds secrets.", "  ", None)]
+#[case::cjk("幽霊少女が住む部屋", "少女", Some("幽霊少女が住む部屋"))]
+fn search_snippet_cases(#[case] text: &str, #[case] query: &str, #[case] expected: Option<&str>) {
+    // given: a work's plain text and a query
+
+    // when: matching
+    let snippet = search_snippet(text, query);
+
+    // then: a snippet only when every term occurs
+    assert_eq!(snippet.as_deref(), expected);
+}
+
+#[rstest]
+#[case::dropped_letter("The protagonist wakes.", "protagnist", Some("protagonist"))]
+#[case::exact("The protagonist wakes.", "wakes", Some("wakes"))]
+#[case::any_case("The Protagonist wakes.", "PROTAGNIST", Some("Protagonist"))]
+#[case::all_terms_required("The protagonist wakes.", "protagnist ghost", None)]
+#[case::scattered_letters("Extraordinarily long words.", "eol", None)]
+#[case::blank_query("The protagonist wakes.", "  ", None)]
+fn fuzzy_search_cases(#[case] text: &str, #[case] query: &str, #[case] expected: Option<&str>) {
+    // given: a work's plain text and a query, maybe misspelt
+
+    // when: matching fuzzily
+    let hit = fuzzy_search(text, query);
+
+    // then: every term finds a close word; the first term's is reported
+    assert_eq!(hit.map(|(_, word)| word).as_deref(), expected);
+}
+
+#[test]
+fn fuzzy_search_scores_exact_above_typo() {
+    // given: one text spelling the word right, one dropping a letter
+    let (right, typo) = ("The protagonist wakes.", "The protagnist wakes.");
+
+    // when: scoring the right spelling against both
+    let right = fuzzy_search(right, "protagonist").unwrap().0;
+    let typo = fuzzy_search(typo, "protagnist").unwrap().0;
+    let nearly = fuzzy_search("The protagonist wakes.", "protagnist")
+        .unwrap()
+        .0;
+
+    // then: an exact word beats a near miss
+    assert!(right > nearly, "{} vs {}", right, nearly);
+    assert!(typo > nearly, "{} vs {}", typo, nearly);
+}
+
+#[test]
+fn search_snippet_windows_long_text_around_first_hit() {
+    // given: a long body with the term in the middle
+    let text = format!("{} needle {}", "a ".repeat(300), "b ".repeat(300));
+
+    // when: matching
+    let snippet = search_snippet(&text, "needle").unwrap();
+
+    // then: a bounded window, cut on both sides, containing the hit
+    assert!(snippet.contains("needle"));
+    assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+    assert!(snippet.chars().count() <= SEARCH_SNIPPET_CHARS + 2);
+}
+
+#[test]
+fn html_to_text_strips_tags_and_decodes() {
+    // given: rendered markdown
+    let html = "<h2>Cast</h2><p>Tom &amp; Jerry&#39;s <em>house</em></p>";
+
+    // when: flattening to text
+    let text = html_to_text(html);
+
+    // then: words across block boundaries stay separate
+    assert_eq!(text, "Cast Tom & Jerry's house");
+}
+
+#[test]
+fn markdown_highlights_rust_fence() {
+    // given: a rust code fence
+    let md = "```rust\nfn main() { let s = \"<hi>\"; } // done\n```";
+
+    // when: rendering
+    let html = markdown_to_html(md);
+
+    // then: keyword, string and comment spans, with the source still escaped
+    assert!(html.contains(r#"<span style="color"#), "{}", html);
+    assert!(
+        html.contains(r#"<span style="color:#c792ea">fn</span>"#),
+        "{}",
+        html
+    );
+    assert!(html.contains("&quot;&lt;hi&gt;&quot;"), "{}", html);
+    assert!(html.contains("// done</span>"), "{}", html);
+}
+
+#[rstest]
+#[case::unknown_hint("```brainfuck\n+[-]\n```", r#"<code class="language-brainfuck">"#)]
+#[case::no_hint("```\nplain\n```", "<pre><code>plain")]
+fn markdown_unhighlighted_fence_falls_back(#[case] md: &str, #[case] expected: &str) {
+    // given: a fence without a language the highlighter knows
+
+    // when: rendering
+    let html = markdown_to_html(md);
+
+    // then: pulldown's plain <pre><code>, no color spans
+    assert!(html.contains(expected), "{}", html);
+    assert!(!html.contains("<span style"), "{}", html);
+}
+
+#[test]
+fn duplicate_headings_get_unique_ids_and_toc_entries() {
+    // given: two h2s named "Intro" and an h3 between them
+    let md = "## Intro\n\nFirst.\n\n### Route A\n\n## Intro\n\nAgain.";
+
+    // when: extracting headings, rendering the body and the TOC
+    let headings = extract_headings(md);
+    let html = markdown_to_html(md);
+    let toc = render_toc(&headings);
+
+    // then:
+    // - ids are slugged and de-duplicated in document order
+    // - the body's headings carry those ids, each with an anchor
+    // - the TOC links every heading
+    let ids: Vec<&str> = headings.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, vec!["intro", "route-a", "intro-2"]);
+    assert!(html.contains(r#"<h2 id="intro">"#), "{}", html);
+    assert!(html.contains(r#"<h2 id="intro-2">"#), "{}", html);
+    assert!(html.contains(r##"href="#intro-2""##), "{}", html);
+    assert!(toc.starts_with(r#"<nav class="toc">"#));
+    assert!(toc.contains(r##"<li class="toc-h2"><a href="#intro">Intro</a></li>"##));
+    assert!(toc.contains(r##"<li class="toc-h2"><a href="#intro-2">Intro</a></li>"##));
+    assert!(toc.contains(r##"<li class="toc-h3"><a href="#route-a">Route A</a></li>"##));
+}
+
+#[rstest]
+#[case::punctuation("What's New? (v1.2)", "what-s-new-v1-2")]
+#[case::japanese("第1章 はじまり", "第1章-はじまり")]
+#[case::nothing_left("???", "section")]
+fn slugify_cases(#[case] text: &str, #[case] expected: &str) {
+    // given: heading text
+
+    // when: slugging it
+    let slug = slugify(text);
+
+    // then: lowercase words joined by single dashes
+    assert_eq!(slug, expected);
+}
+
+#[test]
+fn every_heading_level_gets_an_id_but_toc_lists_h2_h3() {
+    // given: an h1 and an h4 around an h2/h3 pair
+    let md = "# Story\n\n## Part One\n\n### Scene\n\n#### Aside";
+
+    // when: extracting headings, rendering the body and the TOC
+    let headings = extract_headings(md);
+    let html = markdown_to_html(md);
+    let toc = render_toc(&headings);
+
+    // then: every level is extracted and anchored; the TOC keeps to h2/h3
+    let levels: Vec<u8> = headings.iter().map(|h| h.level).collect();
+    assert_eq!(levels, vec![1, 2, 3, 4]);
+    assert!(html.contains(r#"<h1 id="story">"#), "{}", html);
+    assert!(html.contains(r#"<h4 id="aside">"#), "{}", html);
+    assert!(!toc.contains("#story"));
+    assert!(!toc.contains("#aside"));
+    assert!(toc.contains("#part-one"));
+}
+
+#[rstest]
+#[case::known("Route done :tada:", "<p>Route done 🎉</p>")]
+#[case::unknown("Mood: :not_an_emoji:", "<p>Mood: :not_an_emoji:</p>")]
+#[case::code_span("Type `:smile:` to smile", "<p>Type <code>:smile:</code> to smile</p>")]
+#[case::code_block("```\n:heart:\n```", "<pre><code>:heart:\n</code></pre>")]
+#[case::chained("Flags :not:an:emoji: stay", "<p>Flags :not:an:emoji: stay</p>")]
+#[case::inside_word("a:heart:b and :heart::heart:", "<p>a:heart:b and ❤️❤️</p>")]
+fn emoji_shortcodes_expand_in_prose_only(#[case] md: &str, #[case] expected: &str) {
+    // when: rendering a body with shortcodes
+    let html = markdown_to_html(md);
+
+    // then: known, free-standing shortcodes become emoji; code and unknown names stay literal
+    assert!(html.contains(expected), "{}", html);
+}
+
+#[test]
+fn emoji_expansion_borrows_when_nothing_changes() {
+    // given: text with colons but no known shortcode
+    let text = "Chapter 2: the :unknown: part";
+
+    // when: expanding
+    let expanded = expand_emoji_shortcodes(text);
+
+    // then: the input comes back untouched, unallocated
+    assert!(matches!(expanded, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(expanded, text);
+}
+
+#[test]
+fn single_heading_gets_no_toc() {
+    // given: a body with one heading
+    let headings = extract_headings("## Only\n\nText.");
+
+    // when: rendering the TOC
+    let toc = render_toc(&headings);
+
+    // then: nothing — a one-entry contents list is noise
+    assert_eq!(toc, "");
+}
+
+#[rstest]
+#[case::relative("screens/title.png", "/raw/2023/screens/title.png")]
+#[case::dot_prefix("./screens/title.png", "/raw/2023/screens/title.png")]
+#[case::query_kept("cg one.png?v=2", "/raw/2023/cg%20one.png?v=2")]
+#[case::absolute_url("https://example.com/a.png", "https://example.com/a.png")]
+#[case::root_relative("/raw/2024/a.png", "/raw/2024/a.png")]
+#[case::escaping_works("../../secret.png", "../../secret.png")]
+fn resolve_image_url_cases(#[case] url: &str, #[case] expected: &str) {
+    // given: an image target in a work under works/2023/
+
+    // when: resolving it for the browser
+    let resolved = resolve_image_url("2023", url);
+
+    // then: relative targets move onto /raw, everything else is untouched
+    assert_eq!(resolved, expected);
+}
+
+#[test]
+fn markdown_relative_image_served_from_raw() {
+    // given: a work in works/2023 embedding a relative screenshot
+    let md = "![](screens/title.png)";
+
+    // when: rendering with the work's directory known
+    let html = markdown_to_html_with(
+        md,
+        &RenderOptions {
+            work_dir: "2023".into(),
+            ..Default::default()
+        },
+    );
+
+    // then: the src points at the /raw mount
+    assert!(
+        html.contains(r#"src="/raw/2023/screens/title.png""#),
+        "{}",
+        html
+    );
+}
+
+#[rstest]
+#[case::skips_image_paragraph(
+    "<p><img src=\"a.png\" /></p>\n<p>Noah returns &amp; <em>waits</em>.</p>",
+    200,
+    Some("Noah returns & waits.")
+)]
+#[case::truncated("<p>one two three four</p>", 7, Some("one two…"))]
+#[case::no_paragraph("<h2>Only</h2>", 200, None)]
+fn first_paragraph_cases(#[case] html: &str, #[case] max: usize, #[case] expected: Option<&str>) {
+    // given: rendered body HTML
+
+    // when: taking a description from it
+    let description = first_paragraph(html, max);
+
+    // then: the first paragraph with text, cut to length
+    assert_eq!(description.as_deref(), expected);
+}
+
+#[test]
+fn weak_etag_changes_with_content() {
+    // given: a rendered page before and after its source changed
+    let before = weak_etag(b"<h1>Title</h1><p>v1</p>");
+    let after = weak_etag(b"<h1>Title</h1><p>v2</p>");
+
+    // when / then: the tags differ, and each matches only itself
+    assert_ne!(before, after);
+    assert!(before.starts_with("W/\""));
+    assert!(etag_matches(&before, &before));
+    assert!(!etag_matches(&before, &after));
+}
+
+#[rstest]
+#[case::exact(r#"W/"abc""#, true)]
+#[case::strong_form(r#""abc""#, true)]
+#[case::in_list(r#""x", W/"abc""#, true)]
+#[case::wildcard("*", true)]
+#[case::other(r#"W/"abd""#, false)]
+fn etag_matches_cases(#[case] if_none_match: &str, #[case] expected: bool) {
+    // given: an If-None-Match header and the current tag
+    let etag = r#"W/"abc""#;
+
+    // when: comparing
+    let matched = etag_matches(if_none_match, etag);
+
+    // then: weak comparison over every listed tag
+    assert_eq!(matched, expected);
+}
+
+#[rstest]
+#[case::script_block("<script>alert(1)</script>\n\nSafe text.", "alert")]
+#[case::inline_script("Hi <script>steal()</script> there.", "steal")]
+#[case::onclick(r#"<div onclick="alert(1)">x</div>"#, "onclick")]
+#[case::javascript_href(r#"<a href="javascript:alert(1)">x</a>"#, "javascript:")]
+#[case::obfuscated_scheme("<a href=\"java\tscript:alert(1)\">x</a>", "script:")]
+#[case::markdown_link("[x](javascript:alert(1))", "javascript:")]
+#[case::iframe(r#"<iframe src="https://evil.example"></iframe>"#, "iframe")]
+fn markdown_strips_unsafe_html(#[case] md: &str, #[case] unsafe_part: &str) {
+    // given: a work body with script, a handler or a script URL
+
+    // when: rendering
+    let html = markdown_to_html(md);
+
+    // then: the unsafe part is gone
+    assert!(!html.contains(unsafe_part), "{}", html);
+}
+
+#[test]
+fn markdown_keeps_github_image_and_safe_markup() {
+    // given: the GitHub attachment tag works paste, and a plain link in a div
+    let img = r#"<img width="384" height="216" alt="image" src="https://github.com/user-attachments/assets/abc123" />"#;
+    let md = format!(
+        "{}\n\n<div align=\"center\"><a href=\"https://example.com\">site</a></div>",
+        img
+    );
+
+    // when: rendering
+    let html = markdown_to_html(&md);
+
+    // then: both survive (the image only gaining lazy-loading hints), and the image is still found and strippable
+    let lazy_img = img.replace(" />", r#" loading="lazy" decoding="async" />"#);
+    assert!(html.contains(&lazy_img), "{}", html);
+    assert!(
+        html.contains(r#"<div align="center"><a href="https://example.com">site</a></div>"#),
+        "{}",
+        html
+    );
+    assert!(!strip_img_tags(&html).contains("<img"));
+}
+
+#[rstest]
+#[case::relative("screens/a.png", true)]
+#[case::anchor("#intro", true)]
+#[case::https("https://example.com", true)]
+#[case::mailto("mailto:a@example.com", true)]
+#[case::colon_in_path("/works/2024/Re:Birth", true)]
+#[case::javascript("JavaScript:alert(1)", false)]
+#[case::data("data:text/html;base64,PHNjcmlwdD4=", false)]
+fn is_safe_url_cases(#[case] url: &str, #[case] expected: bool) {
+    // given: a link target
+
+    // when / then: only relative and http(s)/mailto URLs pass
+    assert_eq!(is_safe_url(url), expected);
+}
+
+#[rstest]
+#[case::two_segments("2024/Title.md", Some(("2024", "Title")))]
+#[case::three_segments("2023/series-name/chapter-1.md", Some(("2023", "series-name/chapter-1")))]
+#[case::four_segments("2023/saga/part-1/chapter-1.txt", Some(("2023", "saga/part-1/chapter-1")))]
+#[case::long_markdown("2024/Title.markdown", Some(("2024", "Title")))]
+#[case::bare_extension("2024/.markdown", None)]
+#[case::outside_year("README.md", None)]
+#[case::not_a_work("2024/cover.png", None)]
+fn split_work_path_cases(#[case] rel_path: &str, #[case] expected: Option<(&str, &str)>) {
+    // given: a file path relative to works/
+
+    // when: splitting it into year and title
+    let split = split_work_path(rel_path);
+
+    // then: everything below the year is the title, minus the extension
+    assert_eq!(split, expected.map(|(y, t)| (y.to_string(), t.to_string())));
+}
+
+#[rstest]
+#[case::flat("Title", "2024", "Title")]
+#[case::nested("series-name/chapter-1", "2024/series-name", "chapter-1")]
+#[case::deeper("saga/part-1/chapter-1", "2024/saga/part-1", "chapter-1")]
+fn parsed_game_dir_and_name(#[case] title: &str, #[case] dir: &str, #[case] name: &str) {
+    // given: a game, possibly nested below its year
+    let game = make_game("2024", title, "A", "2024/01/01");
+
+    // when / then: dir is the source file's directory, name its last segment
+    assert_eq!(game.dir(), dir);
+    assert_eq!(game.name(), name);
+}
+
+#[test]
+fn parallel_map_matches_sequential_over_many_files() {
+    // given: 200 small work files
+    let dir = scratch_dir("parallel-map");
+    let paths: Vec<PathBuf> = (0..200)
+        .map(|i| {
+            let path = dir.join(format!("{:03}.md", i));
+            std::fs::write(
+                &path,
+                format!("---\ncreator: C{}\n---\n\n## Part {}\n", i, i),
+            )
+            .unwrap();
+            path
+        })
+        .collect();
+    let parse = |path: &PathBuf| {
+        let content = std::fs::read_to_string(path).unwrap();
+        let (meta, body) = parse_frontmatter(&content);
+        (meta.creator, markdown_to_html(body))
+    };
+
+    // when: reading and parsing them on 16 workers, and one at a time
+    let parallel = parallel_map(&paths, 16, parse);
+    let sequential: Vec<_> = paths.iter().map(parse).collect();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // then: identical results, in input order
+    assert_eq!(parallel, sequential);
+    assert_eq!(parallel[123].0.as_deref(), Some("C123"));
+}
+
+#[test]
+fn parallel_map_overlaps_slow_items() {
+    // given: 32 items that each block for 20ms (standing in for I/O wait)
+    let items: Vec<u64> = (0..32).collect();
+    let slow = |i: &u64| {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        i * 2
+    };
+
+    // when: mapping them on 8 workers
+    let started = std::time::Instant::now();
+    let doubled = parallel_map(&items, 8, slow);
+    let elapsed = started.elapsed();
+
+    // then: well under the 640ms a sequential pass needs, results in order
+    assert!(
+        elapsed < std::time::Duration::from_millis(320),
+        "{:?}",
+        elapsed
+    );
+    assert_eq!(doubled, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn parallel_map_handles_empty_input() {
+    // given: nothing to do
+    let items: Vec<u8> = Vec::new();
+
+    // when: mapping
+    let out = parallel_map(&items, 16, |i| *i);
+
+    // then: nothing back, and no worker hangs
+    assert!(out.is_empty());
+}
+
+#[rstest]
+#[case::empty(0, 1)]
+#[case::short(150, 1)]
+#[case::exact(440, 2)]
+#[case::rounds_up(441, 3)]
+#[case::saturates(usize::MAX, u32::MAX)]
+fn reading_time_minutes_cases(#[case] words: usize, #[case] expected: u32) {
+    // given: a word count
+
+    // when / then: whole minutes at READING_WORDS_PER_MINUTE, rounded up, never 0
+    assert_eq!(reading_time_minutes(words), expected);
+}
+
+#[rstest]
+#[case::empty("", 1)]
+#[case::prose(&"word ".repeat(440), 2)]
+#[case::just_over(&"word ".repeat(441), 3)]
+#[case::formatting_splits_no_words(&"**bo**ld ".repeat(440), 2)]
+#[case::code_fence_excluded(&format!("{}\n\n```\n{}\n```\n", "word ".repeat(200), "code ".repeat(1000)), 1)]
+#[case::inline_code_counted(&"`word` ".repeat(441), 3)]
+fn reading_time_cases(#[case] md: &str, #[case] expected: u32) {
+    // given: a markdown body
+
+    // when: estimating its reading time from its word count
+    let minutes = reading_time_minutes(markdown_word_count(md));
+
+    // then: prose words at 220 wpm, rounded up; code blocks don't count
+    assert_eq!(minutes, expected);
+}
+
+#[rstest]
+#[case::prose("Hello brave new world.", 4)]
+#[case::formatting_joined("**bo**ld and *it*alic", 3)]
+#[case::code_fence_excluded("One two.\n\n```\nlet x = 1;\n```\n\nThree.", 3)]
+#[case::cjk_per_character("今日は晴れ", 5)]
+fn markdown_word_count_cases(#[case] md: &str, #[case] expected: usize) {
+    // given: a markdown body
+
+    // when / then: prose words, CJK characters one each, code blocks left out
+    assert_eq!(markdown_word_count(md), expected);
+}
+
+#[rstest]
+#[case::inline(
+    "Energy is $E = mc^2$ here.",
+    r#"<p>Energy is <span class="math inline">\(E = mc^2\)</span> here.</p>"#
+)]
+#[case::display_unwrapped("$$\\sum_i x_i$$", r#"<div class="math display">\[\sum_i x_i\]</div>"#)]
+#[case::display_mid_paragraph(
+    "see $$a$$ below",
+    r#"<p>see <div class="math display">\[a\]</div> below</p>"#
+)]
+#[case::escaped_tex("$a < b$", r#"<p><span class="math inline">\(a &lt; b\)</span></p>"#)]
+fn markdown_math_cases(#[case] md: &str, #[case] expected: &str) {
+    // given: markdown with $…$ / $$…$$ math
+
+    // when: rendering it
+    let html = markdown_to_html(md);
+
+    // then: TeX is wrapped for KaTeX auto-render, HTML-escaped, delimiters intact
+    assert_eq!(html.trim(), expected);
+}
+
+#[rstest]
+#[case::escaped_dollar(r"costs \$5 and \$10")]
+#[case::code_span("`$x$`")]
+#[case::lone_dollar("costs $5 today")]
+fn markdown_dollars_without_math(#[case] md: &str) {
+    // given: dollar signs that aren't a math span
+
+    // when: rendering it
+    let html = markdown_to_html(md);
+
+    // then: no math markup, and has_math agrees
+    assert!(!html.contains("class=\"math"), "{html}");
+    assert!(!has_math(md));
+}
+
+#[rstest]
+#[case::inline("a $x$ b", true)]
+#[case::display("$$x$$", true)]
+#[case::plain("no math", false)]
+fn has_math_cases(#[case] md: &str, #[case] expected: bool) {
+    // given: a markdown body
+
+    // when / then: math is detected only for real math spans
+    assert_eq!(has_math(md), expected);
+}
+
+#[rstest]
+#[case::sentence_end("See https://example.com/a.", &["https://example.com/a"])]
+#[case::in_parens("(via https://example.com/x_(y))", &["https://example.com/x_(y)"])]
+#[case::two("http://a.example and https://b.example/?q=1", &["http://a.example", "https://b.example/?q=1"])]
+#[case::mid_word("xhttps://example.com", &[])]
+#[case::scheme_only("https:// alone", &[])]
+fn bare_urls_cases(#[case] text: &str, #[case] expected: &[&str]) {
+    // given: a run of prose
+
+    // when: finding its bare URLs
+    let urls: Vec<&str> = bare_urls(text).into_iter().map(|r| &text[r]).collect();
+
+    // then: each URL, without trailing punctuation it doesn't own
+    assert_eq!(urls, expected);
+}
+
+#[rstest]
+#[case::bare(
+    "Play it at https://example.com/game today.",
+    r#"<p>Play it at <a href="https://example.com/game">https://example.com/game</a> today.</p>"#
+)]
+#[case::code_span(
+    "Not `https://example.com` this.",
+    "<p>Not <code>https://example.com</code> this.</p>"
+)]
+#[case::code_block(
+    "```\nhttps://example.com\n```",
+    "<pre><code>https://example.com\n</code></pre>"
+)]
+#[case::already_linked(
+    "[https://example.com](https://example.com)",
+    r#"<p><a href="https://example.com">https://example.com</a></p>"#
+)]
+#[case::raw_anchor(
+    r#"<a href="https://example.com">https://example.com</a>"#,
+    r#"<p><a href="https://example.com">https://example.com</a></p>"#
+)]
+fn markdown_autolinks_bare_urls_outside_code(#[case] md: &str, #[case] expected: &str) {
+    // given: markdown with a URL in prose, code, or an existing link
+
+    // when: rendering it
+    let html = markdown_to_html(md);
+
+    // then: only the bare prose URL becomes a link
+    assert_eq!(html.trim(), expected);
+}
+
+#[test]
+fn markdown_mermaid_fence_is_left_for_mermaid() {
+    // given: a mermaid fence next to an ordinary code fence
+    let md = "```mermaid\ngraph TD\n  A-->B\n```\n\n```text\nA-->B\n```\n";
+
+    // when: rendering it
+    let html = markdown_to_html(md);
+
+    // then: the diagram is a <pre class="mermaid"> of its escaped source, the other fence unchanged
+    assert!(
+        html.contains("<pre class=\"mermaid\">graph TD\n  A--&gt;B\n</pre>"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<pre><code class=\"language-text\">A--&gt;B\n</code></pre>"),
+        "{html}"
+    );
+    assert!(has_mermaid(md));
+    assert!(!has_mermaid("```text\nA-->B\n```\n"));
+}
+
+#[test]
+fn markdown_spoiler_fence_renders_details_with_markdown_inside() {
+    // given: a spoiler fence holding a list, emphasis and math
+    let md = "Before.\n\n```spoiler\n- Aoi *survives*\n- $x$\n```\n\nAfter.\n";
+
+    // when: rendering it
+    let html = markdown_to_html(md);
+
+    // then: a closed <details> whose body is rendered markdown, not a code block
+    assert!(
+        html.contains("<details class=\"spoiler\"><summary>Spoiler</summary>\n<ul>"),
+        "{html}"
+    );
+    assert!(html.contains("<li>Aoi <em>survives</em></li>"), "{html}");
+    assert!(!html.contains("<code"), "{html}");
+    assert!(html.contains("</details>\n<p>After.</p>"), "{html}");
+    assert!(has_math(md));
+}
+
+#[rstest]
+#[case::bare("spoiler", Some("Spoiler"))]
+#[case::titled("spoiler Route B ending", Some("Route B ending"))]
+#[case::other_lang("rust", None)]
+#[case::prefix_only("spoilers", None)]
+fn spoiler_summary_cases(#[case] info: &str, #[case] expected: Option<&str>) {
+    // given: a fence info string
+
+    // when / then: only a `spoiler` fence has a summary, defaulting to "Spoiler"
+    assert_eq!(spoiler_summary(info), expected);
+}
+
+#[test]
+fn markdown_spoiler_summary_is_escaped() {
+    // given: a titled spoiler with markup in the title
+    let md = "```spoiler <b>Ending</b>\ntext\n```\n";
+
+    // when: rendering it
+    let html = markdown_to_html(md);
+
+    // then: the title is text, not markup
+    assert!(
+        html.contains("<summary>&lt;b&gt;Ending&lt;/b&gt;</summary>"),
+        "{html}"
+    );
+}
+
+fn render_key(path: &str) -> RenderKey {
+    RenderKey {
+        path: path.to_string(),
+        variant: "en".to_string(),
+    }
+}
+
+#[test]
+fn render_cache_reuses_page_until_evicted() {
+    // given: a cache and a renderer that counts its calls
+    let cache = RenderCache::new(8);
+    let renders = std::cell::Cell::new(0);
+    let render = || {
+        renders.set(renders.get() + 1);
+        Ok::<_, ()>(format!("page {}", renders.get()))
+    };
+
+    // when: rendering the same key twice, then again once the work is evicted
+    let first = cache
+        .get_or_render(render_key("/works/2024/A"), render)
+        .unwrap();
+    let second = cache
+        .get_or_render(render_key("/works/2024/A"), render)
+        .unwrap();
+    assert_eq!(cache.evict("/works/2024/A"), 1);
+    let edited = cache
+        .get_or_render(render_key("/works/2024/A"), render)
+        .unwrap();
+
+    // then: the repeat is the cached string; after eviction it renders afresh
+    assert_eq!(&*first, "page 1");
+    assert!(std::sync::Arc::ptr_eq(&first, &second));
+    assert_eq!(&*edited, "page 2");
+    assert_eq!(renders.get(), 2);
+    assert_eq!(cache.hits_misses(), (1, 2));
+}
+
+#[test]
+fn render_cache_evicts_least_recently_used() {
+    // given: a two-page cache holding A and B, with A used last
+    let cache = RenderCache::new(2);
+    let page = |p: &str| Ok::<_, ()>(p.to_string());
+    cache.get_or_render(render_key("A"), || page("A")).unwrap();
+    cache.get_or_render(render_key("B"), || page("B")).unwrap();
+    cache
+        .get_or_render(render_key("A"), || page("A again"))
+        .unwrap();
+
+    // when: a third page arrives
+    cache.get_or_render(render_key("C"), || page("C")).unwrap();
+
+    // then: B made room; A is still cached
+    assert_eq!(cache.len(), 2);
+    let a = cache
+        .get_or_render(render_key("A"), || page("A again"))
+        .unwrap();
+    assert_eq!(&*a, "A");
+    let b = cache
+        .get_or_render(render_key("B"), || page("B again"))
+        .unwrap();
+    assert_eq!(&*b, "B again");
+}
+
+#[rstest]
+#[case::disabled(0)]
+#[case::enabled(4)]
+fn render_cache_passes_render_errors_through(#[case] capacity: usize) {
+    // given: a cache, possibly disabled, and a failing renderer
+    let cache = RenderCache::new(capacity);
+
+    // when: rendering
+    let result = cache.get_or_render(render_key("A"), || Err::<String, _>("boom"));
+
+    // then: the error surfaces and nothing is cached
+    assert_eq!(result.unwrap_err(), "boom");
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn works_watcher_reports_changed_works() {
+    // given: two works, and a watcher on their directory
+    let dir = scratch_dir("watch-works");
+    std::fs::create_dir_all(dir.join("2024")).unwrap();
+    std::fs::write(dir.join("2024/A.md"), "Before.").unwrap();
+    std::fs::write(dir.join("2024/B.md"), "Untouched.").unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let _watcher = watch_works(
+        &dir,
+        None,
+        move |changed: &std::collections::HashSet<String>| tx.send(changed.clone()).unwrap(),
+        std::time::Duration::from_millis(50),
+    )
+    .unwrap();
+
+    // when: A is edited
+    std::fs::write(dir.join("2024/A.md"), "After.").unwrap();
+
+    // then: once the burst settles, only A is reported
+    let changed = rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+    assert_eq!(changed, ["/works/2024/A".to_string()].into_iter().collect());
+}
+
+#[test]
+fn config_parses_cli_flags() {
+    // given: a synthetic argv setting every flag
+    let argv = [
+        "lightvn-works",
+        "--port",
+        "9000",
+        "--works-dir",
+        "/srv/works",
+        "--public-dir",
+        "static",
+        "--bind",
+        "127.0.0.1",
+        "--render-cache-size",
+        "64",
+        "--thumb-cache-dir",
+        "/var/cache/thumbs",
+        "--request-timeout",
+        "5",
+        "--cache-policy",
+        "text/html=60",
+        "--cors-origins",
+        "https://a.example,https://b.example",
+        "--rate-limit",
+        "30",
+        "--trust-forwarded-for",
+        "--site-title",
+        "VN Archive",
+        "--site-logo-url",
+        "/logo.webp",
+        "--metrics",
+        "--collection",
+        "games=/srv/games,novels=/srv/novels",
+        "--max-work-bytes",
+        "1048576",
+        "--max-fs-concurrency",
+        "4",
+        "--disallow-crawlers",
+        "--check",
+        "--thumbnail-sources",
+        "https://cdn.example/,/raw/",
+        "--base-lang",
+        "ja",
+        "--permalink-pattern",
+        "/:year/:slug",
+        "--comments-file",
+        "/srv/comments.json",
+        "--featured-file",
+        "/srv/featured.toml",
+        "--stats-file",
+        "/srv/stats.json",
+        "--media-hosts",
+        "cdn.example.com,media.example.org",
+        "--auto-alt",
+        "--draft-preview-token",
+        "let-me-see",
+    ];
+
+    // when: parsing it
+    let config = Config::try_parse_from(argv).unwrap();
+
+    // then: each flag lands in its field
+    assert_eq!(
+        config,
+        Config {
+            works_dir: PathBuf::from("/srv/works"),
+            public_dir: PathBuf::from("static"),
+            port: 9000,
+            bind: "127.0.0.1".parse().unwrap(),
+            render_cache_size: 64,
+            export: None,
+            check: true,
+            thumb_cache_dir: PathBuf::from("/var/cache/thumbs"),
+            request_timeout: 5,
+            cache_policy: Some("text/html=60".to_string()),
+            cors_origins: vec![
+                "https://a.example".to_string(),
+                "https://b.example".to_string()
+            ],
+            rate_limit: 30,
+            trust_forwarded_for: true,
+            site_title: "VN Archive".to_string(),
+            site_logo_url: Some("/logo.webp".to_string()),
+            metrics: true,
+            collections: vec![
+                Collection {
+                    name: "games".to_string(),
+                    dir: PathBuf::from("/srv/games"),
+                },
+                Collection {
+                    name: "novels".to_string(),
+                    dir: PathBuf::from("/srv/novels"),
+                },
+            ],
+            max_work_bytes: 1_048_576,
+            max_fs_concurrency: 4,
+            disallow_crawlers: true,
+            thumbnail_sources: vec!["https://cdn.example/".to_string(), "/raw/".to_string()],
+            media_hosts: vec![
+                "cdn.example.com".to_string(),
+                "media.example.org".to_string()
+            ],
+            auto_alt: true,
+            base_lang: "ja".to_string(),
+            permalink_pattern: Some("/:year/:slug".to_string()),
+            comments_file: Some(PathBuf::from("/srv/comments.json")),
+            stats_file: Some(PathBuf::from("/srv/stats.json")),
+            featured_file: Some(PathBuf::from("/srv/featured.toml")),
+            draft_preview_token: Some("let-me-see".to_string()),
+        }
+    );
+    assert_eq!(config.addr().to_string(), "127.0.0.1:9000");
+}
+
+#[test]
+fn config_default_takes_the_cli_defaults() {
+    // given: nothing set
+    // when: building the default config
+    let config = Config::default();
+
+    // then: each field holds its `#[arg]` default
+    assert_eq!(config.works_dir, PathBuf::from("works"));
+    assert_eq!(config.public_dir, PathBuf::from("public"));
+    assert_eq!(config.addr().to_string(), "0.0.0.0:8080");
+    assert_eq!(config.render_cache_size, DEFAULT_RENDER_CACHE_SIZE);
+    assert_eq!(config.request_timeout, DEFAULT_REQUEST_TIMEOUT);
+    assert_eq!(config.rate_limit, DEFAULT_RATE_LIMIT);
+    assert_eq!(config.max_fs_concurrency, DEFAULT_FS_CONCURRENCY);
+    assert_eq!(config.site_title, DEFAULT_SITE_TITLE);
+    assert_eq!(config.thumbnail_sources, DEFAULT_THUMBNAIL_SOURCES);
+    assert_eq!(config.base_lang, DEFAULT_BASE_LANG);
+    assert!(!config.metrics && !config.trust_forwarded_for && !config.check);
+    assert!(config.collections.is_empty() && config.draft_preview_token.is_none());
+}
+
+#[rstest]
+#[case::plain("games=/srv/games", Some(("games", "/srv/games")))]
+#[case::trimmed(" games = /srv/games ", Some(("games", "/srv/games")))]
+#[case::no_dir("games=", None)]
+#[case::no_separator("games", None)]
+#[case::nested_name("a/b=/srv", None)]
+#[case::hidden_name("_games=/srv", None)]
+fn collection_parses_name_and_dir(#[case] raw: &str, #[case] expected: Option<(&str, &str)>) {
+    // given: a --collection value
+    // when: parsing it
+    let parsed = raw.parse::<Collection>().ok();
+
+    // then: a one-segment, visible name and a dir, or an error
+    assert_eq!(
+        parsed,
+        expected.map(|(name, dir)| Collection {
+            name: name.to_string(),
+            dir: PathBuf::from(dir),
+        })
+    );
+}
+
+#[rstest]
+#[case::single_root(&[], "2024/A.md", Some("/srv/works/2024/A.md"))]
+#[case::in_collection(&["games=/srv/games"], "games/2024/A.md", Some("/srv/games/2024/A.md"))]
+#[case::collection_itself(&["games=/srv/games"], "games", Some("/srv/games/"))]
+#[case::unknown_collection(&["games=/srv/games"], "novels/2024/A.md", None)]
+#[case::name_prefix_only(&["games=/srv/games"], "gamesx/2024/A.md", None)]
+fn config_locates_sources_across_collections(
+    #[case] collections: &[&str],
+    #[case] rel: &str,
+    #[case] expected: Option<&str>,
+) {
+    // given: a works dir, and maybe collections replacing it
+    let config = Config {
+        works_dir: PathBuf::from("/srv/works"),
+        collections: collections.iter().map(|c| c.parse().unwrap()).collect(),
+        ..Config::default()
+    };
+
+    // when: locating a path as URLs see it
+    let path = config.source_path(rel);
+
+    // then: it's under the root it names, or nowhere
+    assert_eq!(path, expected.map(PathBuf::from));
+}
+
+#[rstest]
+#[case::help("--help", clap::error::ErrorKind::DisplayHelp)]
+#[case::bad_port("--port=http", clap::error::ErrorKind::ValueValidation)]
+#[case::no_fs_reads("--max-fs-concurrency=0", clap::error::ErrorKind::ValueValidation)]
+#[case::unknown_flag("--verbose", clap::error::ErrorKind::UnknownArgument)]
+fn config_cli_rejects_or_explains(#[case] arg: &str, #[case] kind: clap::error::ErrorKind) {
+    // given: a single flag that isn't a plain setting
+
+    // when: parsing it
+    let err = Config::try_parse_from(["lightvn-works", arg]).unwrap_err();
+
+    // then: clap stops with help text or a usage error instead of a Config
+    assert_eq!(err.kind(), kind);
+}
+
+#[rstest]
+#[case::title_only(
+    "A & B",
+    None,
+    r#"<header class="site-bar"><a href="/"><span>A &amp; B</span></a></header>"#
+)]
+#[case::with_logo(
+    "Works",
+    Some("/logo.webp?v=\"1\""),
+    r#"<header class="site-bar"><a href="/"><img class="site-logo" src="/logo.webp?v=&quot;1&quot;" alt=""><span>Works</span></a></header>"#
+)]
+fn site_header_html_cases(#[case] title: &str, #[case] logo: Option<&str>, #[case] expected: &str) {
+    // given: a configured site title and optional logo
+
+    // when: building the header bar
+    let html = site_header_html(title, logo);
+
+    // then: a link home with the logo before the title, both escaped
+    assert_eq!(html, expected);
+}
+
+#[rstest]
+#[case::templated(
+    Some("<h1>No {{title}} in {{year}}</h1>"),
+    "Lost",
+    "<h1>No Lost in 2024</h1>"
+)]
+#[case::templated_script(
+    Some("<h1>No {{title}} in {{year}}</h1>"),
+    "<script>alert(1)</script>",
+    "<h1>No &lt;script&gt;alert(1)&lt;/script&gt; in 2024</h1>"
+)]
+#[case::built_in(None, "Lost", "<code>2024/Lost.md</code>")]
+#[case::built_in_script(
+    None,
+    "<script>alert(1)</script>",
+    "<code>2024/&#60;script&#62;alert(1)&#60;/script&#62;.md</code>"
+)]
+fn work_not_found_html_fills_template_or_falls_back(
+    #[case] template: Option<&str>,
+    #[case] title: &str,
+    #[case] expected: &str,
+) {
+    // given: a deployment's 404 template, or none, and a requested title
+
+    // when: rendering the missing-work page
+    let html = work_not_found_html(template, "2024", title);
+
+    // then: the placeholders (or the built-in page) carry the escaped request
+    assert!(html.contains(expected), "{}", html);
+    assert!(!html.contains("<script>"), "{}", html);
+}
+
+#[rstest]
+#[case::with_year("2024", "Lost", "2024/Lost.md")]
+#[case::nested("2024", "Series/Lost", "2024/Series/Lost.md")]
+#[case::yearless("", "Lost", "works/Lost")]
+fn missing_work_cases(#[case] year: &str, #[case] title: &str, #[case] expected: &str) {
+    // given: the year and title a request asked for
+
+    // when: naming the missing work
+    let what = missing_work(year, title);
+
+    // then: its file under the year, or its year-less URL path without one
+    assert_eq!(what, expected);
+}
+
+#[test]
+fn page_template_escapes_text_and_keeps_body_html() {
+    // given: sample page data with markup in the title and an already-rendered body
+    let mut assets = HashMap::new();
+    assets.insert(
+        "style.css".to_string(),
+        "/assets/style.0123abcd.css".to_string(),
+    );
+    let page = PageTemplate {
+        lang: "en",
+        strings: get_lang("en"),
+        assets: &assets,
+        critical_css: "<style>html{}</style>",
+        title_display: "Tom & <Jerry>",
+        year: "2024",
+        year_anchor: "2024",
+        tagline: "A \"quoted\" tagline",
+        og_image: "",
+        twitter_card: "summary",
+        canonical_url: "https://example.com/works/2024/Tom",
+        home_suffix: "",
+        breadcrumb_dirs: "",
+        hero_html: "",
+        creator_html: "",
+        released_html: "",
+        reading_time_html: "",
+        comments_html: "",
+        link_html: "",
+        extra_links_html: "",
+        tags_line: "",
+        toc: r#"<nav class="toc"></nav>"#,
+        md_html: "<p>Body <em>text</em></p>",
+        gallery_html: "",
+        editor_mockup: "",
+        more_from_creator: "",
+        work_nav_html: "",
+        work_langs_html: "",
+        math: false,
+        mermaid: false,
+        theme_css: "",
+        site_title: "Light.vn Works",
+        site_header: r#"<header class="site-bar"></header>"#,
+        print: false,
+    };
+
+    // when: rendering it
+    let html = page.render().unwrap();
+
+    // then:
+    // - user-derived text is escaped wherever it appears
+    // - the body, TOC and inline CSS go in verbatim
+    // - asset names resolve to their hashed URLs, unknown ones to /assets/NAME
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(!html.contains("<Jerry>"));
+    assert!(html.contains("<h1>Tom &#38; &#60;Jerry&#62;</h1>"));
+    assert!(html.contains("<title>Tom &#38; &#60;Jerry&#62; (2024) — Light.vn Works</title>"));
+    assert!(html.contains(r#"content="A &#34;quoted&#34; tagline""#));
+    assert!(html.contains(r#"<div class="synopsis"><p>Body <em>text</em></p></div>"#));
+    assert!(html.contains(r#"<nav class="toc"></nav>"#));
+    assert!(html.contains(r#"<header class="site-bar"></header>"#));
+    assert!(html.contains("<style>html{}</style>"));
+    assert!(html.contains(r#"href="/assets/style.0123abcd.css""#));
+    assert!(html.contains(r#"href="/assets/game.css""#));
+}
+
+#[test]
+fn not_found_template_escapes_requested_path() {
+    // given: a requested year/title carrying markup
+    let page = NotFoundTemplate {
+        what: "2024/<script>x</script>.md",
+    };
+
+    // when: rendering it
+    let html = page.render().unwrap();
+
+    // then: the path is shown, escaped
+    assert!(html.contains("<code>2024/&#60;script&#62;x&#60;/script&#62;.md</code>"));
+    assert!(!html.contains("<script>"));
+}
+
+#[test]
+fn robots_txt_keeps_crawlers_out_of_api_raw_and_previews() {
+    // given/when: the production policy
+    let robots = robots_txt("https://example.com/", false);
+
+    // then: private paths are disallowed, works allowed, and the sitemap listed
+    assert!(robots.contains("Disallow: /api/\n"));
+    assert!(robots.contains("Disallow: /raw/\n"));
+    assert!(robots.contains("Disallow: /*preview=\n"));
+    assert!(robots.contains("Allow: /works/\n"));
+    assert!(robots.contains("Sitemap: https://example.com/sitemap.xml\n"));
+}
+
+#[test]
+fn robots_txt_disallow_all_blocks_everything() {
+    // given/when: the staging policy
+    let robots = robots_txt("https://staging.example.com", true);
+
+    // then: the whole site is off limits and no sitemap is advertised
+    assert_eq!(robots, "User-agent: *\nDisallow: /\n");
+}
+
+#[test]
+fn work_excerpt_prefers_frontmatter_description() {
+    // given: a work with both a description and a body paragraph
+    let meta = GameMeta {
+        description: Some("  A short hand-written summary.  ".to_string()),
+        ..Default::default()
+    };
+
+    // when: taking its excerpt
+    let excerpt = work_excerpt(&meta, "<p>The body's opening paragraph.</p>");
+
+    // then: the description wins, trimmed
+    assert_eq!(excerpt.as_deref(), Some("A short hand-written summary."));
+}
+
+#[test]
+fn work_excerpt_falls_back_to_first_body_paragraph() {
+    // given: no description, and a body opening with a heading and a screenshot
+    let html = "<h2 id=\"story\">Story</h2>\n<p><img src=\"a.png\" alt=\"\"></p>\n\
+                <p>A <em>quiet</em> tale &amp; more.</p>\n<p>Second.</p>";
+
+    // when: taking its excerpt
+    let excerpt = work_excerpt(&GameMeta::default(), html);
+
+    // then: the first paragraph with text, as plain text
+    assert_eq!(excerpt.as_deref(), Some("A quiet tale & more."));
+    assert_eq!(work_excerpt(&GameMeta::default(), "<pre>plain</pre>"), None);
+}
+
+#[rstest]
+#[case::fits("short text", 20, "short text")]
+#[case::word_boundary("one two three four", 12, "one two…")]
+#[case::boundary_at_limit("one two three", 7, "one two…")]
+#[case::long_word("abcdefghij", 4, "abcd…")]
+#[case::multibyte("ああ いい うう", 6, "ああ いい…")]
+fn truncate_at_word_cases(#[case] text: &str, #[case] max: usize, #[case] expected: &str) {
+    // given/when: truncating text to `max` characters
+    let out = truncate_at_word(text, max);
+
+    // then: cut at the last whole word, with an ellipsis when shortened
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn work_excerpt_is_capped_at_excerpt_chars() {
+    // given: a long description
+    let meta = GameMeta {
+        description: Some("word ".repeat(100)),
+        ..Default::default()
+    };
+
+    // when: taking its excerpt
+    let excerpt = work_excerpt(&meta, "").unwrap();
+
+    // then: within EXCERPT_CHARS plus the ellipsis, ending on a whole word
+    assert!(excerpt.chars().count() <= EXCERPT_CHARS + 1);
+    assert!(excerpt.ends_with("word…"));
+}
+
+#[rstest]
+#[case::video_link("[Trailer](trailer.mp4)", "video", "/raw/2024/trailer.mp4", "Trailer")]
+#[case::audio_link(
+    "[OST](/raw/2024/ost/Theme.MP3)",
+    "audio",
+    "/raw/2024/ost/Theme.MP3",
+    "OST"
+)]
+#[case::video_image(
+    "![](clip.webm)",
+    "video",
+    "/raw/2024/clip.webm",
+    "/raw/2024/clip.webm"
+)]
+fn markdown_embeds_local_media(
+    #[case] md: &str,
+    #[case] element: &str,
+    #[case] src: &str,
+    #[case] label: &str,
+) {
+    // given: a work in 2024 linking a local media file
+
+    // when: rendering it
+    let html = markdown_to_html_with(
+        md,
+        &RenderOptions {
+            work_dir: "2024".to_string(),
+            ..Default::default()
+        },
+    );
+
+    // then: a player with controls, the link kept inside as a fallback
+    let expected = format!(
+        r#"<{el} controls preload="metadata" src="{src}"><a href="{src}">{label}</a></{el}>"#,
+        el = element
+    );
+    assert!(html.contains(&expected), "{}", html);
+}
+
+#[test]
+fn markdown_embeds_remote_media_only_from_allowed_hosts() {
+    // given: media links on an allowed host and on another one
+    let md = "[A](https://cdn.example.com/a.mp4) [B](https://other.example/b.mp3)";
+
+    // when: rendering with only cdn.example.com allowed
+    let html = markdown_to_html_with(
+        md,
+        &RenderOptions {
+            media_hosts: vec!["cdn.example.com".to_string()],
+            ..Default::default()
+        },
+    );
+
+    // then: the allowed one is a player, the other stays a plain link
+    assert!(
+        html.contains(r#"<video controls preload="metadata" src="https://cdn.example.com/a.mp4">"#),
+        "{}",
+        html
+    );
+    assert!(
+        html.contains(r#"<a href="https://other.example/b.mp3">B</a>"#),
+        "{}",
+        html
+    );
+    assert!(!html.contains("<audio"), "{}", html);
+}
+
+#[rstest]
+#[case::mp4("trailer.mp4", Some("video"))]
+#[case::upper_with_query("/raw/2024/Theme.MP3?v=2", Some("audio"))]
+#[case::image("shot.png", None)]
+#[case::no_extension("/raw/2024/mp4", None)]
+fn media_element_cases(#[case] url: &str, #[case] expected: Option<&str>) {
+    // given/when: classifying a URL by extension
+    let element = media_element(url);
+
+    // then: video or audio for known media, None otherwise
+    assert_eq!(element, expected);
+}
+
+#[test]
+fn extract_all_images_skips_media() {
+    // given: a video embedded with image syntax ahead of a screenshot
+    let md = "![](trailer.mp4)\n\n![shot](shot.png)";
+
+    // when: extracting images
+    let images = extract_all_images(md);
+
+    // then: only the screenshot, so the video never becomes the thumbnail
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].url, "shot.png");
+}
+
+fn image(url: &str) -> ImageInfo {
+    ImageInfo {
+        url: url.to_string(),
+        width: None,
+        height: None,
+        alt: None,
+    }
+}
+
+#[rstest]
+#[case::allowed_first(&["https://github.com/user-attachments/assets/a", "/raw/2024/b.png"], None, Some("https://github.com/user-attachments/assets/a"))]
+#[case::disallowed_skipped(&["https://tracker.example/pixel.gif", "/raw/2024/shot.png"], None, Some("/raw/2024/shot.png"))]
+#[case::index_allowed(&["/raw/2024/a.png", "/raw/2024/b.png"], Some(1), Some("/raw/2024/b.png"))]
+#[case::index_disallowed(&["/raw/2024/a.png", "https://evil.example/b.png"], Some(1), Some("/raw/2024/a.png"))]
+#[case::none_allowed(&["https://evil.example/a.png", "//github.com/user-attachments/x"], None, None)]
+#[case::no_images(&[], None, None)]
+fn pick_thumbnail_cases(
+    #[case] urls: &[&str],
+    #[case] index: Option<usize>,
+    #[case] expected: Option<&str>,
+) {
+    // given: a work's images and the default thumbnail sources
+    let images: Vec<ImageInfo> = urls.iter().map(|u| image(u)).collect();
+    let sources = Config::default().thumbnail_sources;
+
+    // when: picking the thumbnail
+    let picked = pick_thumbnail(&images, index, &sources);
+
+    // then: the requested or first image from an allowed source, if any
+    assert_eq!(picked.map(|img| img.url.as_str()), expected);
+}
+
+#[rstest]
+#[case::no_frontmatter("Just a body.", None)]
+#[case::valid("---\ncreator: A\ntags: [r18]\n---\nBody.", None)]
+#[case::unclosed("---\ncreator: A\nBody.", Some("never closed"))]
+#[case::bad_yaml("---\ncreator: [A\n---\nBody.", Some("invalid frontmatter"))]
+#[case::wrong_type("---\ntags: 5\n---\nBody.", Some("invalid frontmatter"))]
+fn frontmatter_error_cases(#[case] content: &str, #[case] expected: Option<&str>) {
+    // given/when: checking a work's frontmatter
+    let error = frontmatter_error(content);
+
+    // then: an error only when a block is there and won't parse
+    match expected {
+        Some(needle) => assert!(
+            error.as_deref().unwrap_or("").contains(needle),
+            "{:?}",
+            error
+        ),
+        None => assert_eq!(error, None),
+    }
+}
+
+#[rstest]
+#[case::suffixed("Chapter 1.ja", "Chapter 1", Some("ja"))]
+#[case::nested("Series/Chapter 1.en", "Series/Chapter 1", Some("en"))]
+#[case::plain("Chapter 1", "Chapter 1", None)]
+#[case::not_a_language("Light.vn", "Light.vn", None)]
+#[case::bare_code("ja", "ja", None)]
+#[case::dot_file("Series/.ja", "Series/.ja", None)]
+fn split_lang_suffix_cases(#[case] title: &str, #[case] base: &str, #[case] lang: Option<&str>) {
+    // given/when: splitting a work title
+    // then: only a known language code after the last dot is taken off
+    assert_eq!(split_lang_suffix(title), (base, lang));
+}
+
+#[rstest]
+#[case::param(&["en", "ja"], Some("ja"), Some("en-US"), "en", Some("ja"))]
+#[case::param_missing(&["en", "ja"], Some("fr"), None, "en", Some("en"))]
+#[case::accept_language(&["en", "ja"], None, Some("ja-JP,ja;q=0.9"), "en", Some("ja"))]
+#[case::accept_language_by_q(&["en", "ja"], None, Some("en;q=0.4,ja;q=0.8"), "en", Some("ja"))]
+#[case::accept_language_q_zero(&["en", "ja"], None, Some("ja;q=0"), "en", Some("en"))]
+#[case::accept_language_missing(&["en", "ja"], None, Some("fr,de"), "en", Some("en"))]
+#[case::no_base_variant(&["de", "ja"], None, None, "en", Some("de"))]
+#[case::none_available(&[], Some("ja"), None, "en", None)]
+fn pick_work_lang_cases(
+    #[case] available: &[&str],
+    #[case] lang_param: Option<&str>,
+    #[case] accept_language: Option<&str>,
+    #[case] base: &str,
+    #[case] expected: Option<&str>,
+) {
+    // given: a work's language variants
+    let available: Vec<String> = available.iter().map(|l| l.to_string()).collect();
+
+    // when: picking one for a request
+    let picked = pick_work_lang(&available, lang_param, accept_language, base);
+
+    // then: the param, else Accept-Language, else the base, else the first
+    assert_eq!(picked, expected);
+}
+
+#[test]
+fn work_langs_html_links_other_variants() {
+    // given: a work in English and Japanese, shown in Japanese
+    let langs = vec!["en".to_string(), "ja".to_string()];
+
+    // when: building its selector
+    let html = work_langs_html(&langs, "ja");
+
+    // then: English is a link, Japanese is marked current
+    assert!(html.contains(r#"<a href="?lang=en" hreflang="en">EN</a>"#));
+    assert!(html.contains(r#"<span aria-current="true">JA</span>"#));
+    assert!(work_langs_html(&langs[..1], "en").is_empty());
+}